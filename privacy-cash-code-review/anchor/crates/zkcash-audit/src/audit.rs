@@ -0,0 +1,329 @@
+//! Consistency checks over a decoded snapshot.
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::Range;
+
+use anchor_lang::prelude::Pubkey;
+use light_hasher::Poseidon;
+use zkcash::merkle_tree::{MerkleTree, TreeHasher};
+use zkcash::MerkleTreeAccount;
+use zkcash_client::OffchainMerkleTree;
+
+use crate::snapshot::Snapshot;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum RootCheck {
+    /// The rebuilt root is the account's current root.
+    Current,
+    /// The rebuilt root is not the current root but is still in the root history.
+    InHistory,
+    NotFound,
+    Skipped(&'static str),
+}
+
+#[derive(Debug)]
+pub struct AuditReport {
+    pub tree_account: Option<Pubkey>,
+    pub next_index: u64,
+    pub commitment_count: usize,
+    pub nullifier_count: usize,
+    /// Leaf indices below `next_index` with no commitment account, merged into ranges.
+    pub gaps: Vec<Range<u64>>,
+    /// Indices claimed by more than one commitment account.
+    pub duplicates: Vec<(u64, Vec<Pubkey>)>,
+    /// Commitment accounts at or past `next_index`, which the tree never assigned.
+    pub out_of_range: Vec<(u64, Pubkey)>,
+    pub root_check: RootCheck,
+}
+
+impl AuditReport {
+    /**
+     * Every transact appends two leaves and creates two nullifier accounts, and nullifier
+     * accounts are never closed, so there must be exactly `next_index` of them. Extra ones
+     * were created outside of an append, missing ones mean the snapshot is incomplete.
+     */
+    pub fn orphaned_nullifiers(&self) -> usize {
+        self.nullifier_count.saturating_sub(self.next_index as usize)
+    }
+
+    pub fn missing_nullifiers(&self) -> usize {
+        (self.next_index as usize).saturating_sub(self.nullifier_count)
+    }
+
+    pub fn is_consistent(&self) -> bool {
+        self.tree_account.is_some()
+            && self.gaps.is_empty()
+            && self.duplicates.is_empty()
+            && self.out_of_range.is_empty()
+            && self.orphaned_nullifiers() == 0
+            && self.missing_nullifiers() == 0
+            && matches!(self.root_check, RootCheck::Current)
+    }
+}
+
+pub fn audit(snapshot: &Snapshot) -> AuditReport {
+    let tree = match snapshot.tree_accounts.as_slice() {
+        [(pubkey, account)] => Some((*pubkey, account)),
+        _ => None,
+    };
+    let next_index = tree.map_or(0, |(_, account)| account.next_index);
+
+    let mut by_index: BTreeMap<u64, Vec<(Pubkey, [u8; 32])>> = BTreeMap::new();
+    for (pubkey, commitment) in &snapshot.commitments {
+        by_index
+            .entry(commitment.index)
+            .or_default()
+            .push((*pubkey, commitment.commitment));
+    }
+
+    let duplicates: Vec<_> = by_index
+        .iter()
+        .filter(|(_, entries)| entries.len() > 1)
+        .map(|(index, entries)| (*index, entries.iter().map(|(pubkey, _)| *pubkey).collect()))
+        .collect();
+    let out_of_range: Vec<_> = by_index
+        .range(next_index..)
+        .flat_map(|(index, entries)| entries.iter().map(move |(pubkey, _)| (*index, *pubkey)))
+        .collect();
+    let gaps = find_gaps(by_index.range(..next_index).map(|(index, _)| *index), next_index);
+
+    let root_check = match tree {
+        None if snapshot.tree_accounts.is_empty() => RootCheck::Skipped("no tree account in snapshot"),
+        None => RootCheck::Skipped("more than one tree account in snapshot"),
+        Some(_) if !gaps.is_empty() => RootCheck::Skipped("commitments are missing"),
+        Some(_) if !duplicates.is_empty() => RootCheck::Skipped("leaf indices are duplicated"),
+        Some((_, account)) => {
+            let leaves = by_index
+                .range(..next_index)
+                .map(|(index, entries)| (*index, entries[0].1));
+            check_root(account, leaves)
+        }
+    };
+
+    AuditReport {
+        tree_account: tree.map(|(pubkey, _)| pubkey),
+        next_index,
+        commitment_count: snapshot.commitments.len(),
+        nullifier_count: snapshot.nullifiers.len(),
+        gaps,
+        duplicates,
+        out_of_range,
+        root_check,
+    }
+}
+
+fn check_root(account: &MerkleTreeAccount, leaves: impl Iterator<Item = (u64, [u8; 32])>) -> RootCheck {
+    if TreeHasher::of(account).is_err() {
+        return RootCheck::Skipped("unknown tree hasher");
+    }
+
+    let rebuilt = match OffchainMerkleTree::<Poseidon>::from_indexed_leaves(account.height as usize, leaves) {
+        Ok(tree) => tree,
+        Err(_) => return RootCheck::Skipped("commitments do not form a valid tree"),
+    };
+
+    let root = rebuilt.root();
+    if root == account.root {
+        RootCheck::Current
+    } else if MerkleTree::is_known_root(account, root) {
+        RootCheck::InHistory
+    } else {
+        RootCheck::NotFound
+    }
+}
+
+/// Missing values of `0..end` given the sorted present ones, merged into ranges.
+fn find_gaps(present: impl Iterator<Item = u64>, end: u64) -> Vec<Range<u64>> {
+    let mut gaps = Vec::new();
+    let mut expected = 0;
+    for index in present {
+        if index > expected {
+            gaps.push(expected..index);
+        }
+        expected = index + 1;
+    }
+    if expected < end {
+        gaps.push(expected..end);
+    }
+    gaps
+}
+
+impl fmt::Display for AuditReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.tree_account {
+            Some(pubkey) => writeln!(f, "tree account:      {} (next_index {})", pubkey, self.next_index)?,
+            None => writeln!(f, "tree account:      missing or ambiguous")?,
+        }
+        writeln!(f, "commitments:       {}", self.commitment_count)?;
+        writeln!(f, "nullifiers:        {}", self.nullifier_count)?;
+        writeln!(f, "root:              {:?}", self.root_check)?;
+
+        if !self.gaps.is_empty() {
+            let missing: u64 = self.gaps.iter().map(|gap| gap.end - gap.start).sum();
+            writeln!(f, "gaps:              {} missing leaves (reclaimed commitments also show up here)", missing)?;
+            for gap in &self.gaps {
+                writeln!(f, "  {}..{}", gap.start, gap.end)?;
+            }
+        }
+        for (index, pubkeys) in &self.duplicates {
+            writeln!(f, "duplicate index {}: {:?}", index, pubkeys)?;
+        }
+        for (index, pubkey) in &self.out_of_range {
+            writeln!(f, "index {} past next_index: {}", index, pubkey)?;
+        }
+        if self.orphaned_nullifiers() > 0 {
+            writeln!(f, "orphaned nullifiers: {}", self.orphaned_nullifiers())?;
+        }
+        if self.missing_nullifiers() > 0 {
+            writeln!(f, "missing nullifiers: {}", self.missing_nullifiers())?;
+        }
+
+        write!(f, "{}", if self.is_consistent() { "OK" } else { "INCONSISTENT" })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem::MaybeUninit;
+    use zkcash::{CommitmentAccount, NullifierAccount};
+
+    fn create_tree_account() -> MerkleTreeAccount {
+        let mut uninit: MaybeUninit<MerkleTreeAccount> = MaybeUninit::uninit();
+        let mut account = unsafe {
+            std::ptr::write_bytes(uninit.as_mut_ptr(), 0, 1);
+            uninit.assume_init()
+        };
+        account.height = 26;
+        account.root_history_size = 100;
+        MerkleTree::initialize::<Poseidon>(&mut account).unwrap();
+        account
+    }
+
+    fn leaf(index: u64) -> [u8; 32] {
+        let mut leaf = [0u8; 32];
+        leaf[24..].copy_from_slice(&(index + 1).to_be_bytes());
+        leaf
+    }
+
+    fn commitment(index: u64) -> (Pubkey, CommitmentAccount) {
+        (
+            Pubkey::new_unique(),
+            CommitmentAccount {
+                commitment: leaf(index),
+                encrypted_output: vec![],
+                index,
+                bump: 255,
+                payer: Pubkey::default(),
+                slot: 0,
+            },
+        )
+    }
+
+    /// A pool after `transactions` transacts, with every account present.
+    fn consistent_snapshot(transactions: u64) -> Snapshot {
+        let mut account = create_tree_account();
+        let mut snapshot = Snapshot::default();
+        for index in 0..transactions * 2 {
+            MerkleTree::append::<Poseidon>(leaf(index), &mut account).unwrap();
+            snapshot.commitments.push(commitment(index));
+            snapshot.nullifiers.push((Pubkey::new_unique(), NullifierAccount { bump: 255 }));
+        }
+        snapshot.tree_accounts.push((Pubkey::new_unique(), account));
+        snapshot
+    }
+
+    #[test]
+    fn test_consistent_snapshot() {
+        let report = audit(&consistent_snapshot(3));
+        assert_eq!(report.root_check, RootCheck::Current);
+        assert!(report.is_consistent(), "{}", report);
+    }
+
+    #[test]
+    fn test_empty_pool() {
+        let report = audit(&consistent_snapshot(0));
+        assert_eq!(report.root_check, RootCheck::Current);
+        assert!(report.is_consistent());
+    }
+
+    #[test]
+    fn test_missing_tree_account() {
+        let mut snapshot = consistent_snapshot(1);
+        snapshot.tree_accounts.clear();
+        let report = audit(&snapshot);
+        assert!(matches!(report.root_check, RootCheck::Skipped(_)));
+        assert!(!report.is_consistent());
+    }
+
+    #[test]
+    fn test_gaps_are_merged_into_ranges() {
+        let mut snapshot = consistent_snapshot(4);
+        snapshot.commitments.retain(|(_, c)| ![1, 2, 5, 7].contains(&c.index));
+        let report = audit(&snapshot);
+        assert_eq!(report.gaps, vec![1..3, 5..6, 7..8]);
+        assert!(matches!(report.root_check, RootCheck::Skipped(_)));
+        assert!(!report.is_consistent());
+    }
+
+    #[test]
+    fn test_duplicate_and_out_of_range_indices() {
+        let mut snapshot = consistent_snapshot(2);
+        let (duplicate_key, mut duplicate) = commitment(1);
+        duplicate.commitment = [9u8; 32];
+        snapshot.commitments.push((duplicate_key, duplicate));
+        let (past_key, past) = commitment(4);
+        snapshot.commitments.push((past_key, past));
+
+        let report = audit(&snapshot);
+        assert_eq!(report.duplicates.len(), 1);
+        assert_eq!(report.duplicates[0].0, 1);
+        assert!(report.duplicates[0].1.contains(&duplicate_key));
+        assert_eq!(report.out_of_range, vec![(4, past_key)]);
+        assert!(!report.is_consistent());
+    }
+
+    #[test]
+    fn test_tampered_commitment_changes_root() {
+        let mut snapshot = consistent_snapshot(2);
+        snapshot.commitments[3].1.commitment = [9u8; 32];
+        assert_eq!(audit(&snapshot).root_check, RootCheck::NotFound);
+    }
+
+    #[test]
+    fn test_stale_commitments_root_in_history() {
+        // The tree account moved on after the commitments were captured
+        let mut account = create_tree_account();
+        for index in 0..6 {
+            MerkleTree::append::<Poseidon>(leaf(index), &mut account).unwrap();
+        }
+        assert_eq!(check_root(&account, (0..4).map(|i| (i, leaf(i)))), RootCheck::InHistory);
+        assert_eq!(check_root(&account, (0..6).map(|i| (i, leaf(i)))), RootCheck::Current);
+    }
+
+    #[test]
+    fn test_root_check_skips_unknown_hashers() {
+        let mut account = create_tree_account();
+        for index in 0..2 {
+            MerkleTree::append::<Poseidon>(leaf(index), &mut account).unwrap();
+        }
+        assert_eq!(check_root(&account, (0..2).map(|i| (i, leaf(i)))), RootCheck::Current);
+
+        account.hasher = 1;
+        assert!(matches!(check_root(&account, (0..2).map(|i| (i, leaf(i)))), RootCheck::Skipped(_)));
+    }
+
+    #[test]
+    fn test_orphaned_and_missing_nullifiers() {
+        let mut snapshot = consistent_snapshot(2);
+        snapshot.nullifiers.push((Pubkey::new_unique(), NullifierAccount { bump: 255 }));
+        let report = audit(&snapshot);
+        assert_eq!(report.orphaned_nullifiers(), 1);
+        assert!(!report.is_consistent());
+
+        snapshot.nullifiers.truncate(2);
+        let report = audit(&snapshot);
+        assert_eq!(report.missing_nullifiers(), 2);
+        assert!(!report.is_consistent());
+    }
+}
@@ -0,0 +1,42 @@
+//! Offline consistency audit of a zkcash account snapshot, for incident response.
+//!
+//! Usage: `zkcash-audit <snapshot.json>`, where the snapshot is the base64 JSON output of
+//! `getProgramAccounts` for the program. Rebuilds the commitment tree from
+//! `CommitmentAccount.index`, checks the root against the `MerkleTreeAccount` and reports
+//! gaps, duplicate indices and orphaned nullifiers. Exits with 1 if anything is inconsistent.
+mod audit;
+mod snapshot;
+
+use std::process::ExitCode;
+
+use snapshot::Snapshot;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().collect();
+    let [_, path] = args.as_slice() else {
+        eprintln!("usage: zkcash-audit <snapshot.json>");
+        return ExitCode::from(2);
+    };
+
+    let snapshot = match std::fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|json| Snapshot::from_json(&json).map_err(|e| e.to_string()))
+    {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            eprintln!("failed to load {}: {}", path, e);
+            return ExitCode::from(2);
+        }
+    };
+
+    if snapshot.skipped > 0 {
+        println!("skipped {} accounts that are not pool state", snapshot.skipped);
+    }
+    let report = audit::audit(&snapshot);
+    println!("{}", report);
+    if report.is_consistent() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
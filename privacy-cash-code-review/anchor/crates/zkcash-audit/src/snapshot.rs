@@ -0,0 +1,161 @@
+//! Account snapshots as written by `solana account --output json` or returned by
+//! `getProgramAccounts` with base64 encoding: a JSON array of `{ pubkey, account }` objects.
+use std::str::FromStr;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AccountDeserialize, Discriminator};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::Deserialize;
+use thiserror::Error;
+use zkcash::migration::migrate_merkle_tree_account_data;
+use zkcash::{CommitmentAccount, MerkleTreeAccount, NullifierAccount};
+
+#[derive(Error, Debug)]
+pub enum SnapshotError {
+    #[error("Invalid snapshot JSON: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Invalid pubkey {0}")]
+    InvalidPubkey(String),
+
+    #[error("Account {0}: unsupported data encoding {1}, export with base64")]
+    UnsupportedEncoding(Pubkey, String),
+
+    #[error("Account {0}: invalid base64 data")]
+    InvalidData(Pubkey),
+
+    #[error("Account {0}: failed to decode {1}")]
+    InvalidAccount(Pubkey, &'static str),
+}
+
+#[derive(Deserialize)]
+struct RawKeyedAccount {
+    pubkey: String,
+    account: RawAccount,
+}
+
+#[derive(Deserialize)]
+struct RawAccount {
+    owner: String,
+    /// `[data, encoding]`
+    data: (String, String),
+}
+
+/// The zkcash accounts of a snapshot, decoded by discriminator. Other accounts are skipped.
+#[derive(Default)]
+pub struct Snapshot {
+    pub tree_accounts: Vec<(Pubkey, MerkleTreeAccount)>,
+    pub commitments: Vec<(Pubkey, CommitmentAccount)>,
+    pub nullifiers: Vec<(Pubkey, NullifierAccount)>,
+    pub skipped: usize,
+}
+
+impl Snapshot {
+    pub fn from_json(json: &str) -> Result<Self, SnapshotError> {
+        let raw_accounts: Vec<RawKeyedAccount> = serde_json::from_str(json)?;
+        let mut snapshot = Snapshot::default();
+
+        for raw in raw_accounts {
+            let pubkey = parse_pubkey(&raw.pubkey)?;
+            if parse_pubkey(&raw.account.owner)? != zkcash::ID {
+                snapshot.skipped += 1;
+                continue;
+            }
+
+            let (data, encoding) = &raw.account.data;
+            if encoding != "base64" {
+                return Err(SnapshotError::UnsupportedEncoding(pubkey, encoding.clone()));
+            }
+            let data = BASE64.decode(data).map_err(|_| SnapshotError::InvalidData(pubkey))?;
+            snapshot.insert(pubkey, &data)?;
+        }
+
+        Ok(snapshot)
+    }
+
+    fn insert(&mut self, pubkey: Pubkey, data: &[u8]) -> Result<(), SnapshotError> {
+        if data.starts_with(MerkleTreeAccount::DISCRIMINATOR) {
+            // Snapshots taken before `migrate` still have the v1 layout
+            let data = migrate_merkle_tree_account_data(data)
+                .map_err(|_| SnapshotError::InvalidAccount(pubkey, "MerkleTreeAccount"))?
+                .unwrap_or_else(|| data.to_vec());
+            self.tree_accounts
+                .push((pubkey, bytemuck::pod_read_unaligned(&data[8..MerkleTreeAccount::SPACE])));
+        } else if data.starts_with(CommitmentAccount::DISCRIMINATOR) {
+            let account = CommitmentAccount::try_deserialize(&mut &data[..])
+                .map_err(|_| SnapshotError::InvalidAccount(pubkey, "CommitmentAccount"))?;
+            self.commitments.push((pubkey, account));
+        } else if data.starts_with(NullifierAccount::DISCRIMINATOR) {
+            let account = NullifierAccount::try_deserialize(&mut &data[..])
+                .map_err(|_| SnapshotError::InvalidAccount(pubkey, "NullifierAccount"))?;
+            self.nullifiers.push((pubkey, account));
+        } else {
+            self.skipped += 1;
+        }
+        Ok(())
+    }
+}
+
+fn parse_pubkey(value: &str) -> Result<Pubkey, SnapshotError> {
+    Pubkey::from_str(value).map_err(|_| SnapshotError::InvalidPubkey(value.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::AccountSerialize;
+
+    fn keyed_account(pubkey: Pubkey, owner: Pubkey, data: &[u8], encoding: &str) -> String {
+        format!(
+            r#"{{"pubkey":"{}","account":{{"lamports":1,"owner":"{}","executable":false,"rentEpoch":0,"space":{},"data":["{}","{}"]}}}}"#,
+            pubkey,
+            owner,
+            data.len(),
+            BASE64.encode(data),
+            encoding
+        )
+    }
+
+    #[test]
+    fn test_decodes_program_accounts_by_discriminator() {
+        let mut commitment_data = Vec::new();
+        CommitmentAccount {
+            commitment: [1u8; 32],
+            encrypted_output: vec![2, 3],
+            index: 7,
+            bump: 255,
+            payer: Pubkey::new_unique(),
+            slot: 9,
+        }
+        .try_serialize(&mut commitment_data)
+        .unwrap();
+        let mut nullifier_data = Vec::new();
+        NullifierAccount { bump: 254 }.try_serialize(&mut nullifier_data).unwrap();
+
+        let json = format!(
+            "[{},{},{},{}]",
+            keyed_account(Pubkey::new_unique(), zkcash::ID, &commitment_data, "base64"),
+            keyed_account(Pubkey::new_unique(), zkcash::ID, &nullifier_data, "base64"),
+            // Same data under another owner is not a zkcash account
+            keyed_account(Pubkey::new_unique(), Pubkey::new_unique(), &commitment_data, "base64"),
+            keyed_account(Pubkey::new_unique(), zkcash::ID, &[0u8; 16], "base64"),
+        );
+
+        let snapshot = Snapshot::from_json(&json).unwrap();
+        assert_eq!(snapshot.commitments.len(), 1);
+        assert_eq!(snapshot.commitments[0].1.index, 7);
+        assert_eq!(snapshot.nullifiers.len(), 1);
+        assert!(snapshot.tree_accounts.is_empty());
+        assert_eq!(snapshot.skipped, 2);
+    }
+
+    #[test]
+    fn test_rejects_non_base64_encoding() {
+        let json = format!("[{}]", keyed_account(Pubkey::new_unique(), zkcash::ID, &[0u8; 8], "base58"));
+        assert!(matches!(
+            Snapshot::from_json(&json),
+            Err(SnapshotError::UnsupportedEncoding(_, _))
+        ));
+    }
+}
@@ -0,0 +1,203 @@
+//! One subcommand per authority-only instruction, built with `zkcash_client::admin`.
+use anchor_lang::Discriminator;
+use clap::{ArgAction, Subcommand, ValueEnum};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use zkcash::nullifier_tree::NullifierMode;
+use zkcash_client::admin;
+
+use crate::parse_hex32;
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum NullifierModeArg {
+    Pda,
+    Dual,
+    Compressed,
+}
+
+impl From<NullifierModeArg> for NullifierMode {
+    fn from(mode: NullifierModeArg) -> Self {
+        match mode {
+            NullifierModeArg::Pda => NullifierMode::Pda,
+            NullifierModeArg::Dual => NullifierMode::Dual,
+            NullifierModeArg::Compressed => NullifierMode::Compressed,
+        }
+    }
+}
+
+/// `backfill_nullifier` is left out: it needs insert proofs from an indexer, not an authority.
+#[derive(Subcommand)]
+pub enum AdminCommand {
+    /// Create the pool accounts, and the nullifier tree unless the mode is pda
+    Initialize {
+        #[arg(long, value_enum, default_value = "pda")]
+        nullifier_mode: NullifierModeArg,
+    },
+    /// Set the maximum deposit amount, in lamports
+    UpdateDepositLimit { new_limit: u64 },
+    /// Update the fee rates and fee error margin, in basis points. Omitted values are unchanged
+    UpdateGlobalConfig {
+        #[arg(long)]
+        deposit_fee_rate: Option<u16>,
+        #[arg(long)]
+        withdrawal_fee_rate: Option<u16>,
+        #[arg(long)]
+        fee_error_margin: Option<u16>,
+    },
+    AddToBlocklist { address: Pubkey },
+    RemoveFromBlocklist { address: Pubkey },
+    /// Also screen withdrawal recipients against the blocklist
+    SetWithdrawalScreening {
+        #[arg(action = ArgAction::Set)]
+        enabled: bool,
+    },
+    UpdateEncryptedOutputRules {
+        min_len: u16,
+        max_len: u16,
+        #[arg(long)]
+        require_envelope: bool,
+    },
+    /// Set the minimum deposit (0 for none) and the allowed denominations (none for any amount)
+    UpdateDepositRules {
+        min_deposit_amount: u64,
+        #[arg(long = "denomination")]
+        denominations: Vec<u64>,
+    },
+    /// Require relayer fees to also cover the PDA rent
+    SetRentFeeMode {
+        #[arg(action = ArgAction::Set)]
+        enabled: bool,
+    },
+    /// Delay withdrawals above `threshold` lamports by `delay_slots`, a threshold of 0 disables delays
+    UpdateWithdrawalDelay { threshold: u64, delay_slots: u64 },
+    /// Set the swap program, 11111111111111111111111111111111 disables swaps
+    UpdateSwapProgram { swap_program: Pubkey },
+    /// Set the fee of internal transfers in lamports, and how many are accepted per slot (0 for no cap)
+    UpdateTransferRules { transfer_fee: u64, max_transfers_per_slot: u16 },
+    SetEmergency {
+        #[arg(action = ArgAction::Set)]
+        active: bool,
+    },
+    /// Cancel a pending withdrawal, refunding its rent to its payer
+    VetoWithdrawal {
+        /// First input nullifier of the delayed withdrawal
+        #[arg(value_parser = parse_hex32)]
+        input_nullifier: [u8; 32],
+        payer: Pubkey,
+    },
+    /// Pay `amount` lamports of vetoed withdrawals out of the pool to `recipient`
+    ReleaseVetoedFunds { amount: u64, recipient: Pubkey },
+    /// Keep commitment PDAs for this many leaves and slots before their rent can be reclaimed
+    UpdateCommitmentRetention { retention_leaves: u64, retention_slots: u64 },
+    /// Upgrade the pool accounts to the current layouts
+    Migrate,
+    RegisterViewingKey {
+        #[arg(value_parser = parse_hex32)]
+        viewing_key: [u8; 32],
+        auditor: Pubkey,
+    },
+    RevokeViewingKey {
+        #[arg(value_parser = parse_hex32)]
+        viewing_key: [u8; 32],
+    },
+    InitializeNullifierTree,
+    SetNullifierMode {
+        #[arg(value_enum)]
+        nullifier_mode: NullifierModeArg,
+    },
+}
+
+impl AdminCommand {
+    pub fn instruction(&self, authority: Pubkey) -> Instruction {
+        match self {
+            AdminCommand::Initialize { nullifier_mode } => {
+                admin::initialize(authority, (*nullifier_mode).into())
+            }
+            AdminCommand::UpdateDepositLimit { new_limit } => admin::update_deposit_limit(authority, *new_limit),
+            AdminCommand::UpdateGlobalConfig {
+                deposit_fee_rate,
+                withdrawal_fee_rate,
+                fee_error_margin,
+            } => admin::update_global_config(authority, *deposit_fee_rate, *withdrawal_fee_rate, *fee_error_margin),
+            AdminCommand::AddToBlocklist { address } => admin::add_to_blocklist(authority, *address),
+            AdminCommand::RemoveFromBlocklist { address } => admin::remove_from_blocklist(authority, *address),
+            AdminCommand::SetWithdrawalScreening { enabled } => admin::set_withdrawal_screening(authority, *enabled),
+            AdminCommand::UpdateEncryptedOutputRules {
+                min_len,
+                max_len,
+                require_envelope,
+            } => admin::update_encrypted_output_rules(authority, *min_len, *max_len, *require_envelope),
+            AdminCommand::UpdateDepositRules {
+                min_deposit_amount,
+                denominations,
+            } => admin::update_deposit_rules(authority, *min_deposit_amount, denominations.clone()),
+            AdminCommand::SetRentFeeMode { enabled } => admin::set_rent_fee_mode(authority, *enabled),
+            AdminCommand::UpdateWithdrawalDelay { threshold, delay_slots } => {
+                admin::update_withdrawal_delay(authority, *threshold, *delay_slots)
+            }
+            AdminCommand::UpdateSwapProgram { swap_program } => admin::update_swap_program(authority, *swap_program),
+            AdminCommand::UpdateTransferRules {
+                transfer_fee,
+                max_transfers_per_slot,
+            } => admin::update_transfer_rules(authority, *transfer_fee, *max_transfers_per_slot),
+            AdminCommand::SetEmergency { active } => admin::set_emergency(authority, *active),
+            AdminCommand::VetoWithdrawal { input_nullifier, payer } => {
+                admin::veto_withdrawal(authority, input_nullifier, *payer)
+            }
+            AdminCommand::ReleaseVetoedFunds { amount, recipient } => {
+                admin::release_vetoed_funds(authority, *amount, *recipient)
+            }
+            AdminCommand::UpdateCommitmentRetention {
+                retention_leaves,
+                retention_slots,
+            } => admin::update_commitment_retention(authority, *retention_leaves, *retention_slots),
+            AdminCommand::Migrate => admin::migrate(authority),
+            AdminCommand::RegisterViewingKey { viewing_key, auditor } => {
+                admin::register_viewing_key(authority, *viewing_key, *auditor)
+            }
+            AdminCommand::RevokeViewingKey { viewing_key } => admin::revoke_viewing_key(authority, *viewing_key),
+            AdminCommand::InitializeNullifierTree => admin::initialize_nullifier_tree(authority),
+            AdminCommand::SetNullifierMode { nullifier_mode } => {
+                admin::set_nullifier_mode(authority, (*nullifier_mode).into())
+            }
+        }
+    }
+}
+
+/// Name of the zkcash instruction with this data, by discriminator.
+pub fn instruction_name(data: &[u8]) -> Option<&'static str> {
+    use zkcash::instruction::*;
+
+    let names: &[(&[u8], &'static str)] = &[
+        (Initialize::DISCRIMINATOR, "initialize"),
+        (UpdateDepositLimit::DISCRIMINATOR, "update_deposit_limit"),
+        (UpdateGlobalConfig::DISCRIMINATOR, "update_global_config"),
+        (AddToBlocklist::DISCRIMINATOR, "add_to_blocklist"),
+        (RemoveFromBlocklist::DISCRIMINATOR, "remove_from_blocklist"),
+        (SetWithdrawalScreening::DISCRIMINATOR, "set_withdrawal_screening"),
+        (UpdateEncryptedOutputRules::DISCRIMINATOR, "update_encrypted_output_rules"),
+        (UpdateDepositRules::DISCRIMINATOR, "update_deposit_rules"),
+        (SetRentFeeMode::DISCRIMINATOR, "set_rent_fee_mode"),
+        (UpdateWithdrawalDelay::DISCRIMINATOR, "update_withdrawal_delay"),
+        (UpdateSwapProgram::DISCRIMINATOR, "update_swap_program"),
+        (UpdateTransferRules::DISCRIMINATOR, "update_transfer_rules"),
+        (SetEmergency::DISCRIMINATOR, "set_emergency"),
+        (FinalizeWithdrawal::DISCRIMINATOR, "finalize_withdrawal"),
+        (VetoWithdrawal::DISCRIMINATOR, "veto_withdrawal"),
+        (ReleaseVetoedFunds::DISCRIMINATOR, "release_vetoed_funds"),
+        (UpdateCommitmentRetention::DISCRIMINATOR, "update_commitment_retention"),
+        (ReclaimCommitment::DISCRIMINATOR, "reclaim_commitment"),
+        (Migrate::DISCRIMINATOR, "migrate"),
+        (RegisterViewingKey::DISCRIMINATOR, "register_viewing_key"),
+        (RevokeViewingKey::DISCRIMINATOR, "revoke_viewing_key"),
+        (InitializeNullifierTree::DISCRIMINATOR, "initialize_nullifier_tree"),
+        (SetNullifierMode::DISCRIMINATOR, "set_nullifier_mode"),
+        (BackfillNullifier::DISCRIMINATOR, "backfill_nullifier"),
+        (WriteNullifierProof::DISCRIMINATOR, "write_nullifier_proof"),
+        (Transact::DISCRIMINATOR, "transact"),
+    ];
+    names
+        .iter()
+        .find(|(discriminator, _)| data.starts_with(discriminator))
+        .map(|(_, name)| *name)
+}
@@ -0,0 +1,293 @@
+//! Decoding of the pool accounts from `solana account <address> --output json`. Accounts that
+//! still have an old layout are upgraded in memory as `migrate` would, and reported as such.
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anchor_lang::AccountDeserialize;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use clap::Subcommand;
+use serde::Deserialize;
+use solana_sdk::pubkey::Pubkey;
+use thiserror::Error;
+use zkcash::merkle_tree::{MerkleTree, TreeHasher};
+use zkcash::migration::{migrate_global_config_data, migrate_merkle_tree_account_data};
+use zkcash::{GlobalConfig, MerkleTreeAccount};
+
+use crate::hex;
+
+#[derive(Error, Debug)]
+pub enum InspectError {
+    #[error("Failed to read {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+
+    #[error("Invalid account JSON: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Invalid pubkey {0}")]
+    InvalidPubkey(String),
+
+    #[error("Unsupported data encoding {0}, export with --output json")]
+    UnsupportedEncoding(String),
+
+    #[error("Invalid base64 data")]
+    InvalidData,
+
+    #[error("Account {0} is not owned by the zkcash program")]
+    WrongOwner(Pubkey),
+
+    #[error("Account {0} is not a valid {1}")]
+    InvalidAccount(Pubkey, &'static str),
+}
+
+#[derive(Subcommand)]
+pub enum InspectCommand {
+    /// The MerkleTreeAccount, with the leaf range of each root in the history
+    Tree { path: PathBuf },
+    /// The GlobalConfig
+    Config { path: PathBuf },
+}
+
+impl InspectCommand {
+    pub fn run(&self) -> Result<String, InspectError> {
+        let (InspectCommand::Tree { path } | InspectCommand::Config { path }) = self;
+        let json = std::fs::read_to_string(path).map_err(|e| InspectError::Io(path.clone(), e))?;
+        let (pubkey, data) = read_account(&json)?;
+        match self {
+            InspectCommand::Tree { .. } => describe_tree(pubkey, &data),
+            InspectCommand::Config { .. } => describe_config(pubkey, &data),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct RawKeyedAccount {
+    pubkey: String,
+    account: RawAccount,
+}
+
+#[derive(Deserialize)]
+struct RawAccount {
+    owner: String,
+    /// `[data, encoding]`
+    data: (String, String),
+}
+
+fn parse_pubkey(value: &str) -> Result<Pubkey, InspectError> {
+    Pubkey::from_str(value).map_err(|_| InspectError::InvalidPubkey(value.to_string()))
+}
+
+/// Address and data of a zkcash account.
+pub fn read_account(json: &str) -> Result<(Pubkey, Vec<u8>), InspectError> {
+    let raw: RawKeyedAccount = serde_json::from_str(json)?;
+    let pubkey = parse_pubkey(&raw.pubkey)?;
+    if parse_pubkey(&raw.account.owner)? != zkcash::ID {
+        return Err(InspectError::WrongOwner(pubkey));
+    }
+    let (data, encoding) = &raw.account.data;
+    if encoding != "base64" {
+        return Err(InspectError::UnsupportedEncoding(encoding.clone()));
+    }
+    let data = BASE64.decode(data).map_err(|_| InspectError::InvalidData)?;
+    Ok((pubkey, data))
+}
+
+fn version_line(version: u8, current: u8, migrated: bool) -> String {
+    if migrated {
+        format!("version: {} (shown upgraded to {}, run migrate)", version, current)
+    } else {
+        format!("version: {}", version)
+    }
+}
+
+pub fn describe_tree(pubkey: Pubkey, data: &[u8]) -> Result<String, InspectError> {
+    let invalid = |_| InspectError::InvalidAccount(pubkey, "MerkleTreeAccount");
+    let migrated = migrate_merkle_tree_account_data(data).map_err(invalid)?;
+    // v1 trees are shorter and have no version
+    let original_version = if data.len() == MerkleTreeAccount::SPACE {
+        bytemuck::pod_read_unaligned::<MerkleTreeAccount>(&data[8..]).version
+    } else {
+        1
+    };
+    let data = migrated.as_deref().unwrap_or(data);
+    let tree_account: MerkleTreeAccount = bytemuck::pod_read_unaligned(&data[8..MerkleTreeAccount::SPACE]);
+    let hasher = TreeHasher::of(&tree_account)
+        .map(|hasher| format!("{:?}", hasher))
+        .unwrap_or_else(|_| format!("invalid ({})", tree_account.hasher));
+
+    let mut lines = vec![
+        format!("address: {}", pubkey),
+        version_line(original_version, MerkleTreeAccount::VERSION, migrated.is_some()),
+        format!("authority: {}", tree_account.authority),
+        format!("hasher: {}", hasher),
+        format!("height: {}", tree_account.height),
+        format!("next_index: {} of {}", tree_account.next_index, 1u64 << tree_account.height),
+        format!("root: {}", hex(&tree_account.root)),
+        format!("max_deposit_amount: {} lamports", tree_account.max_deposit_amount),
+        format!("root history, most recent first ({} entries):", tree_account.root_history_size),
+    ];
+
+    let size = tree_account.root_history_size as usize;
+    for age in 0..size {
+        let index = (tree_account.root_index as usize + size - age) % size;
+        let root = tree_account.root_history[index];
+        if root == [0u8; 32] {
+            continue;
+        }
+        let range = MerkleTree::root_leaf_range(&tree_account, root)
+            .map(|range| format!("leaves {}..{}", range.start, range.end))
+            .unwrap_or_else(|| "leaf range unknown".to_string());
+        lines.push(format!("  {} {}", hex(&root), range));
+    }
+    Ok(lines.join("\n"))
+}
+
+pub fn describe_config(pubkey: Pubkey, data: &[u8]) -> Result<String, InspectError> {
+    let invalid = |_| InspectError::InvalidAccount(pubkey, "GlobalConfig");
+    let migrated = migrate_global_config_data(data).map_err(invalid)?;
+    let config = GlobalConfig::try_deserialize(&mut migrated.as_deref().unwrap_or(data)).map_err(invalid)?;
+    // Only v1 configs are migrated, and they have no version field
+    let original_version = if migrated.is_some() { 1 } else { config.version };
+
+    let denominations: Vec<String> = config
+        .deposit_denominations
+        .iter()
+        .filter(|&&denomination| denomination != 0)
+        .map(|denomination| denomination.to_string())
+        .collect();
+    let swap_program = if config.swap_program == Pubkey::default() {
+        "disabled".to_string()
+    } else {
+        config.swap_program.to_string()
+    };
+
+    Ok([
+        format!("address: {}", pubkey),
+        version_line(original_version, GlobalConfig::VERSION, migrated.is_some()),
+        format!("authority: {}", config.authority),
+        format!("deposit_fee_rate: {} bps", config.deposit_fee_rate),
+        format!("withdrawal_fee_rate: {} bps", config.withdrawal_fee_rate),
+        format!("fee_error_margin: {} bps", config.fee_error_margin),
+        format!("require_rent_in_fee: {}", config.require_rent_in_fee),
+        format!("screen_withdrawals: {}", config.screen_withdrawals),
+        format!(
+            "encrypted_output_len: {}..={}, envelope required: {}",
+            config.min_encrypted_output_len, config.max_encrypted_output_len, config.require_output_envelope
+        ),
+        format!("min_deposit_amount: {} lamports", config.min_deposit_amount),
+        format!(
+            "deposit_denominations: {}",
+            if denominations.is_empty() { "any".to_string() } else { denominations.join(", ") }
+        ),
+        format!("nullifier_mode: {:?}", config.nullifier_mode),
+        format!(
+            "withdrawal_delay: {} slots above {} lamports",
+            config.withdrawal_delay_slots, config.withdrawal_delay_threshold
+        ),
+        format!("emergency: {}", config.emergency),
+        format!(
+            "commitment_retention: {} leaves, {} slots",
+            config.commitment_retention_leaves, config.commitment_retention_slots
+        ),
+        format!("swap_program: {}", swap_program),
+        format!(
+            "transfer_fee: {} lamports, max {} per slot",
+            config.transfer_fee, config.max_transfers_per_slot
+        ),
+    ]
+    .join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::{AccountSerialize, Discriminator};
+    use zkcash::migration::GlobalConfigV1;
+    use light_hasher::Poseidon;
+    use std::mem::MaybeUninit;
+    use zkcash::nullifier_tree::NullifierMode;
+
+    fn keyed_account(pubkey: Pubkey, owner: Pubkey, data: &[u8]) -> String {
+        format!(
+            r#"{{"pubkey":"{}","account":{{"lamports":1,"owner":"{}","executable":false,"rentEpoch":0,"space":{},"data":["{}","base64"]}}}}"#,
+            pubkey,
+            owner,
+            data.len(),
+            BASE64.encode(data)
+        )
+    }
+
+    fn tree_account_data(leaves: u64, version: u8) -> Vec<u8> {
+        let mut uninit: MaybeUninit<MerkleTreeAccount> = MaybeUninit::uninit();
+        let mut account = unsafe {
+            std::ptr::write_bytes(uninit.as_mut_ptr(), 0, 1);
+            uninit.assume_init()
+        };
+        account.height = 26;
+        account.root_history_size = 100;
+        MerkleTree::initialize::<Poseidon>(&mut account).unwrap();
+        for index in 0..leaves {
+            let mut leaf = [0u8; 32];
+            leaf[24..].copy_from_slice(&(index + 1).to_be_bytes());
+            MerkleTree::append::<Poseidon>(leaf, &mut account).unwrap();
+        }
+        account.version = version;
+
+        let mut data = MerkleTreeAccount::DISCRIMINATOR.to_vec();
+        data.extend_from_slice(bytemuck::bytes_of(&account));
+        data
+    }
+
+    #[test]
+    fn test_read_account_checks_owner() {
+        let pubkey = Pubkey::new_unique();
+        let data = tree_account_data(0, MerkleTreeAccount::VERSION);
+        assert_eq!(read_account(&keyed_account(pubkey, zkcash::ID, &data)).unwrap(), (pubkey, data.clone()));
+        assert!(matches!(
+            read_account(&keyed_account(pubkey, Pubkey::new_unique(), &data)),
+            Err(InspectError::WrongOwner(_))
+        ));
+    }
+
+    #[test]
+    fn test_describe_tree_lists_root_ranges() {
+        let description = describe_tree(Pubkey::new_unique(), &tree_account_data(3, MerkleTreeAccount::VERSION)).unwrap();
+        assert!(description.contains("next_index: 3 of 67108864"), "{}", description);
+        assert!(description.contains("leaves 0..3"), "{}", description);
+        assert!(description.contains("leaves 0..0"), "{}", description);
+        assert!(!description.contains("run migrate"), "{}", description);
+
+        // A v2 tree has no recorded ranges but the current one, set by the in-memory upgrade
+        let description = describe_tree(Pubkey::new_unique(), &tree_account_data(3, 2)).unwrap();
+        assert!(description.contains("version: 2 (shown upgraded to 3, run migrate)"), "{}", description);
+        assert!(description.contains("leaves 0..3"), "{}", description);
+        assert!(description.contains("leaf range unknown"), "{}", description);
+    }
+
+    #[test]
+    fn test_describe_config() {
+        let mut config = zkcash::migration::upgrade_global_config_v1(GlobalConfigV1 {
+            authority: Pubkey::new_unique(),
+            deposit_fee_rate: 0,
+            withdrawal_fee_rate: 25,
+            fee_error_margin: 500,
+            bump: 255,
+        });
+        config.nullifier_mode = NullifierMode::Dual;
+        config.deposit_denominations[0] = 100;
+        config.deposit_denominations[1] = 1_000;
+        let mut data = Vec::new();
+        config.try_serialize(&mut data).unwrap();
+        data.resize(GlobalConfig::SPACE, 0);
+
+        let description = describe_config(Pubkey::new_unique(), &data).unwrap();
+        assert!(description.contains("nullifier_mode: Dual"), "{}", description);
+        assert!(description.contains("deposit_denominations: 100, 1000"), "{}", description);
+        assert!(description.contains("swap_program: disabled"), "{}", description);
+
+        assert!(matches!(
+            describe_config(Pubkey::new_unique(), &tree_account_data(0, MerkleTreeAccount::VERSION)),
+            Err(InspectError::InvalidAccount(_, "GlobalConfig"))
+        ));
+    }
+}
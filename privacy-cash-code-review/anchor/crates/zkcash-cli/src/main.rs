@@ -0,0 +1,169 @@
+//! Admin tool for the zkcash program, replacing the one-off TS admin scripts.
+//!
+//! Nothing is sent from here. `zkcash-cli admin [options] <instruction>` builds the transaction of
+//! an admin instruction for `--blockhash`, or the value of a durable `--nonce-account`, signs it
+//! with the `--keypair`s at hand and prints it as base64. On the air-gapped machine holding the
+//! authority key, `zkcash-cli decode` shows what a transaction does and `zkcash-cli sign` adds the
+//! missing signatures. The result can be submitted with `sendTransaction` and base64 encoding.
+//!
+//! `zkcash-cli inspect tree|config <account.json>` decodes the output of
+//! `solana account <address> --output json`, and `zkcash-cli pda` prints the program addresses.
+mod admin;
+mod inspect;
+mod transaction;
+
+use std::error::Error;
+use std::io::Read;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand};
+use solana_sdk::pubkey::Pubkey;
+use zkcash_client::pda;
+
+#[derive(Parser)]
+#[command(name = "zkcash-cli", about = "Admin tool for the zkcash program")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Build the transaction of an admin instruction and print it as base64
+    Admin {
+        #[command(flatten)]
+        options: Box<transaction::BuildOptions>,
+        #[command(subcommand)]
+        instruction: admin::AdminCommand,
+    },
+    /// Add signatures to a base64 transaction
+    Sign {
+        /// Base64 transaction, `-` to read it from stdin
+        transaction: String,
+        #[arg(long = "keypair", required = true)]
+        keypairs: Vec<PathBuf>,
+    },
+    /// Show the signers and instructions of a base64 transaction
+    Decode {
+        /// Base64 transaction, `-` to read it from stdin
+        transaction: String,
+    },
+    /// Decode a pool account from `solana account <address> --output json`
+    Inspect {
+        #[command(subcommand)]
+        account: inspect::InspectCommand,
+    },
+    /// Print a program derived address
+    Pda {
+        #[command(subcommand)]
+        pda: Pda,
+    },
+}
+
+#[derive(Subcommand)]
+enum Pda {
+    TreeAccount,
+    TreeTokenAccount,
+    GlobalConfig,
+    NullifierTree,
+    SwapAuthority,
+    BlocklistEntry {
+        address: Pubkey,
+    },
+    ViewingKey {
+        #[arg(value_parser = parse_hex32)]
+        viewing_key: [u8; 32],
+    },
+    PendingWithdrawal {
+        /// First input nullifier of the delayed withdrawal
+        #[arg(value_parser = parse_hex32)]
+        input_nullifier: [u8; 32],
+    },
+    NullifierProofs {
+        signer: Pubkey,
+    },
+    ReferrerStats {
+        #[arg(value_parser = parse_hex32)]
+        referrer: [u8; 32],
+    },
+}
+
+impl Pda {
+    fn address(&self) -> Pubkey {
+        match self {
+            Pda::TreeAccount => pda::tree_account(),
+            Pda::TreeTokenAccount => pda::tree_token_account(),
+            Pda::GlobalConfig => pda::global_config(),
+            Pda::NullifierTree => pda::nullifier_tree(),
+            Pda::SwapAuthority => pda::swap_authority(),
+            Pda::BlocklistEntry { address } => pda::blocklist_entry(address),
+            Pda::ViewingKey { viewing_key } => pda::viewing_key_registry(viewing_key),
+            Pda::PendingWithdrawal { input_nullifier } => pda::pending_withdrawal(input_nullifier),
+            Pda::NullifierProofs { signer } => pda::nullifier_proofs(signer),
+            Pda::ReferrerStats { referrer } => pda::referrer_stats(referrer),
+        }
+    }
+}
+
+/// Parses 32 bytes written as 64 hex digits, as nullifiers, viewing keys and referrers are shown.
+fn parse_hex32(value: &str) -> Result<[u8; 32], String> {
+    let value = value.strip_prefix("0x").unwrap_or(value);
+    if value.len() != 64 || !value.bytes().all(|c| c.is_ascii_hexdigit()) {
+        return Err("expected 64 hex digits".to_string());
+    }
+    let mut bytes = [0u8; 32];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&value[2 * i..2 * i + 2], 16).map_err(|e| e.to_string())?;
+    }
+    Ok(bytes)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn read_transaction_arg(value: &str) -> std::io::Result<String> {
+    if value != "-" {
+        return Ok(value.to_string());
+    }
+    let mut input = String::new();
+    std::io::stdin().read_to_string(&mut input)?;
+    Ok(input.trim().to_string())
+}
+
+fn run(command: Command) -> Result<String, Box<dyn Error>> {
+    match command {
+        Command::Admin { options, instruction } => {
+            let keypairs = transaction::read_keypairs(&options.keypairs)?;
+            let authority = options.authority(&keypairs)?;
+            let mut transaction = options.build(instruction.instruction(authority), authority);
+            transaction::sign(&mut transaction, &keypairs)?;
+            Ok(transaction::encode(&transaction)?)
+        }
+        Command::Sign { transaction: encoded, keypairs } => {
+            let mut transaction = transaction::decode(&read_transaction_arg(&encoded)?)?;
+            transaction::sign(&mut transaction, &transaction::read_keypairs(&keypairs)?)?;
+            Ok(transaction::encode(&transaction)?)
+        }
+        Command::Decode { transaction: encoded } => {
+            let transaction = transaction::decode(&read_transaction_arg(&encoded)?)?;
+            Ok(transaction::describe(&transaction))
+        }
+        Command::Inspect { account } => Ok(account.run()?),
+        Command::Pda { pda } => Ok(pda.address().to_string()),
+    }
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse().command) {
+        Ok(output) => {
+            println!("{}", output);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,253 @@
+//! Offline transaction building and signing. Transactions travel as the base64 of their bincode
+//! serialization, the wire format `sendTransaction` takes with base64 encoding, so partially
+//! signed transactions can be carried to and from an air-gapped signer as plain text.
+use std::path::PathBuf;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use clap::Args;
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::message::Message;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::sanitize::Sanitize;
+use solana_sdk::signature::{read_keypair_file, Keypair, Signer};
+use solana_sdk::transaction::Transaction;
+use thiserror::Error;
+
+use crate::admin;
+
+#[derive(Error, Debug)]
+pub enum TransactionError {
+    #[error("Failed to read keypair {0}: {1}")]
+    Keypair(PathBuf, String),
+
+    #[error("Pass --authority or the authority --keypair")]
+    MissingAuthority,
+
+    #[error("{0} is not a signer of the transaction")]
+    UnexpectedSigner(Pubkey),
+
+    #[error("Invalid base64 transaction")]
+    InvalidBase64,
+
+    #[error("Invalid transaction: {0}")]
+    InvalidTransaction(String),
+
+    #[error("Signing failed: {0}")]
+    Signing(String),
+}
+
+#[derive(Args)]
+pub struct BuildOptions {
+    /// Recent blockhash, or the value stored in `--nonce-account`
+    #[arg(long)]
+    pub blockhash: Hash,
+
+    /// Pool authority, defaults to the pubkey of the first `--keypair`
+    #[arg(long)]
+    pub authority: Option<Pubkey>,
+
+    /// Fee payer, defaults to the authority
+    #[arg(long)]
+    pub fee_payer: Option<Pubkey>,
+
+    /// Durable nonce account to advance, so the transaction doesn't expire while offline
+    #[arg(long, requires = "nonce_authority")]
+    pub nonce_account: Option<Pubkey>,
+
+    #[arg(long, requires = "nonce_account")]
+    pub nonce_authority: Option<Pubkey>,
+
+    /// Keypairs to sign with now, the other signatures are left for `sign`
+    #[arg(long = "keypair")]
+    pub keypairs: Vec<PathBuf>,
+}
+
+impl BuildOptions {
+    pub fn authority(&self, keypairs: &[Keypair]) -> Result<Pubkey, TransactionError> {
+        self.authority
+            .or_else(|| keypairs.first().map(|keypair| keypair.pubkey()))
+            .ok_or(TransactionError::MissingAuthority)
+    }
+
+    /// Unsigned transaction of `instruction`, preceded by the nonce advance when using a nonce.
+    pub fn build(&self, instruction: Instruction, authority: Pubkey) -> Transaction {
+        let fee_payer = self.fee_payer.unwrap_or(authority);
+        let mut message = match (self.nonce_account, self.nonce_authority) {
+            (Some(nonce_account), Some(nonce_authority)) => {
+                Message::new_with_nonce(vec![instruction], Some(&fee_payer), &nonce_account, &nonce_authority)
+            }
+            _ => Message::new(&[instruction], Some(&fee_payer)),
+        };
+        message.recent_blockhash = self.blockhash;
+        Transaction::new_unsigned(message)
+    }
+}
+
+pub fn read_keypairs(paths: &[PathBuf]) -> Result<Vec<Keypair>, TransactionError> {
+    paths
+        .iter()
+        .map(|path| read_keypair_file(path).map_err(|e| TransactionError::Keypair(path.clone(), e.to_string())))
+        .collect()
+}
+
+/// Adds the signatures of `keypairs`, keeping those already present.
+pub fn sign(transaction: &mut Transaction, keypairs: &[Keypair]) -> Result<(), TransactionError> {
+    let signers = signer_keys(transaction);
+    if let Some(keypair) = keypairs.iter().find(|keypair| !signers.contains(&keypair.pubkey())) {
+        return Err(TransactionError::UnexpectedSigner(keypair.pubkey()));
+    }
+
+    let keypairs: Vec<&Keypair> = keypairs.iter().collect();
+    let blockhash = transaction.message.recent_blockhash;
+    transaction
+        .try_partial_sign(&keypairs, blockhash)
+        .map_err(|e| TransactionError::Signing(e.to_string()))
+}
+
+pub fn encode(transaction: &Transaction) -> Result<String, TransactionError> {
+    let bytes = bincode::serialize(transaction).map_err(|e| TransactionError::InvalidTransaction(e.to_string()))?;
+    Ok(BASE64.encode(bytes))
+}
+
+pub fn decode(encoded: &str) -> Result<Transaction, TransactionError> {
+    let bytes = BASE64.decode(encoded.trim()).map_err(|_| TransactionError::InvalidBase64)?;
+    let transaction: Transaction =
+        bincode::deserialize(&bytes).map_err(|e| TransactionError::InvalidTransaction(e.to_string()))?;
+    transaction.sanitize().map_err(|e| TransactionError::InvalidTransaction(e.to_string()))?;
+    Ok(transaction)
+}
+
+fn signer_keys(transaction: &Transaction) -> &[Pubkey] {
+    let num_signers = transaction.message.header.num_required_signatures as usize;
+    &transaction.message.account_keys[..num_signers]
+}
+
+/// What a signer needs to check before signing: fee payer, blockhash, signature status and
+/// the instructions with their accounts, zkcash instructions by name.
+pub fn describe(transaction: &Transaction) -> String {
+    let message = &transaction.message;
+    let verified = transaction.verify_with_results();
+    let mut lines = vec![
+        format!("fee payer: {}", message.account_keys[0]),
+        format!("blockhash: {}", message.recent_blockhash),
+        "signatures:".to_string(),
+    ];
+    for (i, signer) in signer_keys(transaction).iter().enumerate() {
+        let status = if transaction.signatures[i] == Default::default() {
+            "missing"
+        } else if verified[i] {
+            "signed"
+        } else {
+            "INVALID"
+        };
+        lines.push(format!("  {} {}", signer, status));
+    }
+
+    lines.push("instructions:".to_string());
+    for instruction in &message.instructions {
+        let program_id = message.account_keys[instruction.program_id_index as usize];
+        let name = if program_id == zkcash::ID {
+            admin::instruction_name(&instruction.data).unwrap_or("unknown zkcash instruction")
+        } else if program_id == solana_sdk::system_program::ID {
+            "system program"
+        } else {
+            "unknown program"
+        };
+        lines.push(format!("  {} ({})", name, program_id));
+        for &index in &instruction.accounts {
+            let index = index as usize;
+            let mut flags = Vec::new();
+            if message.is_signer(index) {
+                flags.push("signer");
+            }
+            if message.is_maybe_writable(index, None) {
+                flags.push("writable");
+            }
+            lines.push(format!("    {} {}", message.account_keys[index], flags.join(" ")));
+        }
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_options(blockhash: Hash, fee_payer: Option<Pubkey>) -> BuildOptions {
+        BuildOptions {
+            blockhash,
+            authority: None,
+            fee_payer,
+            nonce_account: None,
+            nonce_authority: None,
+            keypairs: vec![],
+        }
+    }
+
+    #[test]
+    fn test_partial_signatures_survive_encoding() {
+        let authority = Keypair::new();
+        let fee_payer = Keypair::new();
+        let options = build_options(Hash::new_unique(), Some(fee_payer.pubkey()));
+        let mut transaction = options.build(
+            zkcash_client::admin::set_emergency(authority.pubkey(), true),
+            authority.pubkey(),
+        );
+
+        // The fee payer signs online, the authority later from the encoded transaction
+        sign(&mut transaction, &[fee_payer]).unwrap();
+        assert!(!transaction.is_signed());
+        let mut transaction = decode(&encode(&transaction).unwrap()).unwrap();
+        sign(&mut transaction, &[authority]).unwrap();
+        assert!(transaction.is_signed());
+        transaction.verify().unwrap();
+        assert_eq!(transaction.message.recent_blockhash, options.blockhash);
+    }
+
+    #[test]
+    fn test_rejects_keypairs_that_are_not_signers() {
+        let authority = Keypair::new();
+        let options = build_options(Hash::new_unique(), None);
+        let mut transaction = options.build(
+            zkcash_client::admin::set_emergency(authority.pubkey(), true),
+            authority.pubkey(),
+        );
+        let stranger = Keypair::new();
+        let stranger_pubkey = stranger.pubkey();
+
+        assert!(matches!(
+            sign(&mut transaction, &[stranger]),
+            Err(TransactionError::UnexpectedSigner(pubkey)) if pubkey == stranger_pubkey
+        ));
+    }
+
+    #[test]
+    fn test_nonce_advance_comes_first() {
+        let authority = Pubkey::new_unique();
+        let nonce_account = Pubkey::new_unique();
+        let mut options = build_options(Hash::new_unique(), None);
+        options.nonce_account = Some(nonce_account);
+        options.nonce_authority = Some(authority);
+        let transaction = options.build(zkcash_client::admin::migrate(authority), authority);
+
+        let message = &transaction.message;
+        assert_eq!(message.instructions.len(), 2);
+        assert_eq!(
+            message.account_keys[message.instructions[0].program_id_index as usize],
+            solana_sdk::system_program::ID
+        );
+        assert_eq!(message.recent_blockhash, options.blockhash);
+        assert!(describe(&transaction).contains("migrate"));
+    }
+
+    #[test]
+    fn test_decode_rejects_garbage() {
+        assert!(matches!(decode("not base64!"), Err(TransactionError::InvalidBase64)));
+        assert!(matches!(
+            decode(&BASE64.encode([1, 2, 3])),
+            Err(TransactionError::InvalidTransaction(_))
+        ));
+    }
+}
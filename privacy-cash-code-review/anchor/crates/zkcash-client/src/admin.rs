@@ -0,0 +1,266 @@
+//! Builders for the authority-only instructions of the zkcash program, used by `zkcash-cli`.
+//! Every builder takes the pool authority, which must sign, and derives the PDAs itself.
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::system_program;
+use anchor_lang::{InstructionData, ToAccountMetas};
+
+use crate::pda;
+use zkcash::nullifier_tree::NullifierMode;
+
+fn instruction(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: zkcash::ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+fn update_global_config_accounts(authority: Pubkey) -> zkcash::accounts::UpdateGlobalConfig {
+    zkcash::accounts::UpdateGlobalConfig {
+        global_config: pda::global_config(),
+        authority,
+    }
+}
+
+/// Creates the pool, with the nullifier tree unless `nullifier_mode` is Pda.
+pub fn initialize(authority: Pubkey, nullifier_mode: NullifierMode) -> Instruction {
+    instruction(
+        zkcash::accounts::Initialize {
+            tree_account: pda::tree_account(),
+            tree_token_account: pda::tree_token_account(),
+            global_config: pda::global_config(),
+            nullifier_tree: nullifier_mode.uses_tree().then(pda::nullifier_tree),
+            authority,
+            system_program: system_program::ID,
+        },
+        zkcash::instruction::Initialize { nullifier_mode },
+    )
+}
+
+pub fn update_deposit_limit(authority: Pubkey, new_limit: u64) -> Instruction {
+    instruction(
+        zkcash::accounts::UpdateDepositLimit {
+            tree_account: pda::tree_account(),
+            authority,
+        },
+        zkcash::instruction::UpdateDepositLimit { new_limit },
+    )
+}
+
+/// Fee rates and margin in basis points, `None` leaves a value unchanged.
+pub fn update_global_config(
+    authority: Pubkey,
+    deposit_fee_rate: Option<u16>,
+    withdrawal_fee_rate: Option<u16>,
+    fee_error_margin: Option<u16>,
+) -> Instruction {
+    instruction(
+        update_global_config_accounts(authority),
+        zkcash::instruction::UpdateGlobalConfig {
+            deposit_fee_rate,
+            withdrawal_fee_rate,
+            fee_error_margin,
+        },
+    )
+}
+
+pub fn add_to_blocklist(authority: Pubkey, address: Pubkey) -> Instruction {
+    instruction(
+        zkcash::accounts::AddToBlocklist {
+            blocklist_entry: pda::blocklist_entry(&address),
+            global_config: pda::global_config(),
+            authority,
+            system_program: system_program::ID,
+        },
+        zkcash::instruction::AddToBlocklist { address },
+    )
+}
+
+/// Closes the blocklist entry of `address`, refunding its rent to the authority.
+pub fn remove_from_blocklist(authority: Pubkey, address: Pubkey) -> Instruction {
+    instruction(
+        zkcash::accounts::RemoveFromBlocklist {
+            blocklist_entry: pda::blocklist_entry(&address),
+            global_config: pda::global_config(),
+            authority,
+        },
+        zkcash::instruction::RemoveFromBlocklist { address },
+    )
+}
+
+pub fn set_withdrawal_screening(authority: Pubkey, enabled: bool) -> Instruction {
+    instruction(
+        update_global_config_accounts(authority),
+        zkcash::instruction::SetWithdrawalScreening { enabled },
+    )
+}
+
+pub fn update_encrypted_output_rules(
+    authority: Pubkey,
+    min_len: u16,
+    max_len: u16,
+    require_envelope: bool,
+) -> Instruction {
+    instruction(
+        update_global_config_accounts(authority),
+        zkcash::instruction::UpdateEncryptedOutputRules {
+            min_len,
+            max_len,
+            require_envelope,
+        },
+    )
+}
+
+pub fn update_deposit_rules(authority: Pubkey, min_deposit_amount: u64, denominations: Vec<u64>) -> Instruction {
+    instruction(
+        update_global_config_accounts(authority),
+        zkcash::instruction::UpdateDepositRules {
+            min_deposit_amount,
+            denominations,
+        },
+    )
+}
+
+pub fn set_rent_fee_mode(authority: Pubkey, enabled: bool) -> Instruction {
+    instruction(
+        update_global_config_accounts(authority),
+        zkcash::instruction::SetRentFeeMode { enabled },
+    )
+}
+
+pub fn update_withdrawal_delay(authority: Pubkey, threshold: u64, delay_slots: u64) -> Instruction {
+    instruction(
+        update_global_config_accounts(authority),
+        zkcash::instruction::UpdateWithdrawalDelay { threshold, delay_slots },
+    )
+}
+
+/// `Pubkey::default()` disables swaps.
+pub fn update_swap_program(authority: Pubkey, swap_program: Pubkey) -> Instruction {
+    instruction(
+        update_global_config_accounts(authority),
+        zkcash::instruction::UpdateSwapProgram { swap_program },
+    )
+}
+
+/// Flat fee in lamports and per-slot cap (0 for none) of internal transfers, ext_amount == 0.
+pub fn update_transfer_rules(authority: Pubkey, transfer_fee: u64, max_transfers_per_slot: u16) -> Instruction {
+    instruction(
+        update_global_config_accounts(authority),
+        zkcash::instruction::UpdateTransferRules {
+            transfer_fee,
+            max_transfers_per_slot,
+        },
+    )
+}
+
+pub fn set_emergency(authority: Pubkey, active: bool) -> Instruction {
+    instruction(
+        update_global_config_accounts(authority),
+        zkcash::instruction::SetEmergency { active },
+    )
+}
+
+/// Cancels the pending withdrawal of the transact whose first input nullifier is `input_nullifier`,
+/// refunding its rent to `payer`, the payer recorded in it.
+pub fn veto_withdrawal(authority: Pubkey, input_nullifier: &[u8; 32], payer: Pubkey) -> Instruction {
+    instruction(
+        zkcash::accounts::VetoWithdrawal {
+            pending_withdrawal: pda::pending_withdrawal(input_nullifier),
+            tree_token_account: pda::tree_token_account(),
+            global_config: pda::global_config(),
+            payer,
+            authority,
+        },
+        zkcash::instruction::VetoWithdrawal {},
+    )
+}
+
+/// Pays `amount` lamports of vetoed withdrawals out of the pool to `recipient`.
+pub fn release_vetoed_funds(authority: Pubkey, amount: u64, recipient: Pubkey) -> Instruction {
+    instruction(
+        zkcash::accounts::ReleaseVetoedFunds {
+            tree_token_account: pda::tree_token_account(),
+            global_config: pda::global_config(),
+            recipient,
+            authority,
+        },
+        zkcash::instruction::ReleaseVetoedFunds { amount },
+    )
+}
+
+pub fn update_commitment_retention(authority: Pubkey, retention_leaves: u64, retention_slots: u64) -> Instruction {
+    instruction(
+        update_global_config_accounts(authority),
+        zkcash::instruction::UpdateCommitmentRetention {
+            retention_leaves,
+            retention_slots,
+        },
+    )
+}
+
+/// Upgrades the pool accounts to the current layouts, a no-op for accounts already up to date.
+pub fn migrate(authority: Pubkey) -> Instruction {
+    instruction(
+        zkcash::accounts::Migrate {
+            tree_account: pda::tree_account(),
+            tree_token_account: pda::tree_token_account(),
+            global_config: pda::global_config(),
+            authority,
+            system_program: system_program::ID,
+        },
+        zkcash::instruction::Migrate {},
+    )
+}
+
+pub fn register_viewing_key(authority: Pubkey, viewing_key: [u8; 32], auditor: Pubkey) -> Instruction {
+    instruction(
+        zkcash::accounts::RegisterViewingKey {
+            viewing_key_registry: pda::viewing_key_registry(&viewing_key),
+            global_config: pda::global_config(),
+            authority,
+            system_program: system_program::ID,
+        },
+        zkcash::instruction::RegisterViewingKey { viewing_key, auditor },
+    )
+}
+
+pub fn revoke_viewing_key(authority: Pubkey, viewing_key: [u8; 32]) -> Instruction {
+    instruction(
+        zkcash::accounts::RevokeViewingKey {
+            viewing_key_registry: pda::viewing_key_registry(&viewing_key),
+            global_config: pda::global_config(),
+            authority,
+        },
+        zkcash::instruction::RevokeViewingKey {
+            _viewing_key: viewing_key,
+        },
+    )
+}
+
+pub fn initialize_nullifier_tree(authority: Pubkey) -> Instruction {
+    instruction(
+        zkcash::accounts::InitializeNullifierTree {
+            nullifier_tree: pda::nullifier_tree(),
+            tree_account: pda::tree_account(),
+            global_config: pda::global_config(),
+            authority,
+            system_program: system_program::ID,
+        },
+        zkcash::instruction::InitializeNullifierTree {},
+    )
+}
+
+/// Passes the nullifier tree unless switching to Pda, where the program doesn't need it.
+pub fn set_nullifier_mode(authority: Pubkey, nullifier_mode: NullifierMode) -> Instruction {
+    instruction(
+        zkcash::accounts::SetNullifierMode {
+            global_config: pda::global_config(),
+            tree_account: pda::tree_account(),
+            nullifier_tree: nullifier_mode.uses_tree().then(pda::nullifier_tree),
+            authority,
+        },
+        zkcash::instruction::SetNullifierMode { nullifier_mode },
+    )
+}
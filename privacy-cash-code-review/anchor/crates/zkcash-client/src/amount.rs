@@ -0,0 +1,44 @@
+use ark_bn254::Fr;
+use ark_ff::{BigInteger, PrimeField};
+
+use crate::ClientError;
+
+/**
+ * Encodes the public amount public input for a given ext_amount and fee, as a
+ * big-endian field element. This is the value `zkcash::utils::check_public_amount` accepts:
+ * - deposits (ext_amount > 0): ext_amount - fee, which must be positive
+ * - withdrawals and internal transfers (ext_amount <= 0): FIELD_SIZE - (abs(ext_amount) + fee)
+ */
+pub fn public_amount(ext_amount: i64, fee: u64) -> Result<[u8; 32], ClientError> {
+    if ext_amount == i64::MIN {
+        return Err(ClientError::InvalidExtAmount);
+    }
+
+    let fee_fr = Fr::from(fee);
+    let public_amount = if ext_amount > 0 {
+        let ext_amount_fr = Fr::from(ext_amount as u64);
+        if ext_amount_fr <= fee_fr {
+            return Err(ClientError::FeeExceedsDeposit);
+        }
+        ext_amount_fr - fee_fr
+    } else {
+        -(Fr::from(ext_amount.unsigned_abs()) + fee_fr)
+    };
+
+    Ok(to_be_bytes(public_amount))
+}
+
+/**
+ * Converts an ext data hash into the `ext_data_hash` public input of the proof.
+ * `transact` reduces the sha256 hash as a little-endian integer and compares it to the
+ * big-endian public input, so the public input is the reduced value in big-endian.
+ */
+pub fn ext_data_hash_public_input(ext_data_hash: &[u8; 32]) -> [u8; 32] {
+    to_be_bytes(Fr::from_le_bytes_mod_order(ext_data_hash))
+}
+
+pub(crate) fn to_be_bytes(value: Fr) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    bytes.copy_from_slice(&value.into_bigint().to_bytes_be());
+    bytes
+}
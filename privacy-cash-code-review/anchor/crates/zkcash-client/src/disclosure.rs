@@ -0,0 +1,241 @@
+//! Selective disclosure to auditors: copies of a transaction's outputs encrypted to a registered
+//! auditor viewing key, carried in `ExtData::auditor` and emitted by `transact` as
+//! `AuditorDisclosureEvent`s. The auditor learns the outputs, never the spending key.
+//!
+//! Each copy is an envelope of scheme X25519ChaCha20Poly1305:
+//!
+//! ```text
+//! ephemeral key = X25519 public key of a fresh secret
+//! key           = SHA-256(X25519(ephemeral secret, viewing key) || ephemeral key || viewing key)
+//! ciphertext    = ChaCha20-Poly1305(key, nonce 0, plaintext, aad = commitment)
+//! plaintext     = amount (u64 BE) || pubkey (32 bytes BE) || blinding (32 bytes BE)
+//! ```
+//!
+//! Every key encrypts a single message, so the fixed nonce is never reused. Auditors recompute
+//! the commitment from the plaintext, so a user can't disclose anything but the actual outputs.
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use ark_bn254::Fr;
+use ark_ff::PrimeField;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand_core::CryptoRngCore;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
+use zkcash::disclosure::AuditorDisclosureEvent;
+use zkcash::AuditorDisclosure;
+use zkcash_envelope::{Envelope, EnvelopeError, Scheme};
+
+use crate::amount::to_be_bytes;
+use crate::utxo::{poseidon, sol_mint_address};
+use crate::{ClientError, Utxo};
+
+const PLAINTEXT_LEN: usize = 8 + 32 + 32;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum DisclosureError {
+    #[error("Invalid envelope: {0}")]
+    Envelope(#[from] EnvelopeError),
+
+    #[error("Envelope does not use the X25519ChaCha20Poly1305 scheme")]
+    UnexpectedScheme,
+
+    #[error("Decryption failed")]
+    DecryptionFailed,
+
+    #[error("Decrypted output has an invalid length")]
+    InvalidPlaintext,
+
+    #[error("Decrypted output does not match its commitment")]
+    CommitmentMismatch,
+
+    #[error(transparent)]
+    Client(#[from] ClientError),
+}
+
+/// An auditor's X25519 secret. Its public key is what gets registered on-chain.
+pub struct ViewingKey {
+    secret: StaticSecret,
+}
+
+impl ViewingKey {
+    pub fn generate(rng: &mut impl CryptoRngCore) -> Self {
+        ViewingKey {
+            secret: StaticSecret::random_from_rng(rng),
+        }
+    }
+
+    pub fn from_bytes(secret: [u8; 32]) -> Self {
+        ViewingKey {
+            secret: StaticSecret::from(secret),
+        }
+    }
+
+    pub fn public_key(&self) -> [u8; 32] {
+        PublicKey::from(&self.secret).to_bytes()
+    }
+}
+
+/// What an auditor learns about one output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DisclosedOutput {
+    pub amount: u64,
+    pub pubkey: Fr,
+    pub blinding: Fr,
+}
+
+impl DisclosedOutput {
+    pub fn from_utxo(utxo: &Utxo) -> Self {
+        DisclosedOutput {
+            amount: utxo.amount,
+            pubkey: utxo.keypair.pubkey,
+            blinding: utxo.blinding,
+        }
+    }
+
+    /// The commitment of a SOL output, as in `Utxo::commitment_bytes`.
+    pub fn commitment_bytes(&self) -> Result<[u8; 32], ClientError> {
+        poseidon(&[Fr::from(self.amount), self.pubkey, self.blinding, sol_mint_address()]).map(to_be_bytes)
+    }
+
+    fn to_bytes(self) -> [u8; PLAINTEXT_LEN] {
+        let mut bytes = [0u8; PLAINTEXT_LEN];
+        bytes[..8].copy_from_slice(&self.amount.to_be_bytes());
+        bytes[8..40].copy_from_slice(&to_be_bytes(self.pubkey));
+        bytes[40..].copy_from_slice(&to_be_bytes(self.blinding));
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, DisclosureError> {
+        if bytes.len() != PLAINTEXT_LEN {
+            return Err(DisclosureError::InvalidPlaintext);
+        }
+        Ok(DisclosedOutput {
+            amount: u64::from_be_bytes(bytes[..8].try_into().unwrap()),
+            pubkey: Fr::from_be_bytes_mod_order(&bytes[8..40]),
+            blinding: Fr::from_be_bytes_mod_order(&bytes[40..]),
+        })
+    }
+}
+
+fn cipher(shared_secret: &[u8; 32], ephemeral_key: &[u8; 32], viewing_key: &[u8; 32]) -> ChaCha20Poly1305 {
+    let key = Sha256::new()
+        .chain_update(shared_secret)
+        .chain_update(ephemeral_key)
+        .chain_update(viewing_key)
+        .finalize();
+    ChaCha20Poly1305::new(Key::from_slice(&key))
+}
+
+/// Encrypts `output` to the auditor `viewing_key` as an envelope.
+pub fn encrypt_output(
+    rng: &mut impl CryptoRngCore,
+    viewing_key: &[u8; 32],
+    output: &DisclosedOutput,
+) -> Result<Vec<u8>, DisclosureError> {
+    let ephemeral_secret = EphemeralSecret::random_from_rng(rng);
+    let ephemeral_key = PublicKey::from(&ephemeral_secret).to_bytes();
+    let shared_secret = ephemeral_secret.diffie_hellman(&PublicKey::from(*viewing_key));
+
+    let commitment = output.commitment_bytes()?;
+    let ciphertext = cipher(shared_secret.as_bytes(), &ephemeral_key, viewing_key)
+        .encrypt(Nonce::from_slice(&[0u8; 12]), Payload { msg: &output.to_bytes(), aad: &commitment })
+        .expect("plaintext is far below the ChaCha20-Poly1305 message limit");
+
+    Ok(Envelope::new(Scheme::X25519ChaCha20Poly1305, ephemeral_key, &ciphertext)?.encode())
+}
+
+/// Decrypts an auditor copy and checks it against the on-chain `commitment`.
+pub fn decrypt_output(
+    viewing_key: &ViewingKey,
+    commitment: &[u8; 32],
+    encrypted_output: &[u8],
+) -> Result<DisclosedOutput, DisclosureError> {
+    let envelope = Envelope::decode(encrypted_output)?;
+    if envelope.scheme != Scheme::X25519ChaCha20Poly1305 {
+        return Err(DisclosureError::UnexpectedScheme);
+    }
+
+    let shared_secret = viewing_key.secret.diffie_hellman(&PublicKey::from(envelope.ephemeral_key));
+    let plaintext = cipher(shared_secret.as_bytes(), &envelope.ephemeral_key, &viewing_key.public_key())
+        .decrypt(Nonce::from_slice(&[0u8; 12]), Payload { msg: envelope.ciphertext, aad: commitment })
+        .map_err(|_| DisclosureError::DecryptionFailed)?;
+
+    let output = DisclosedOutput::from_bytes(&plaintext)?;
+    if output.commitment_bytes()? != *commitment {
+        return Err(DisclosureError::CommitmentMismatch);
+    }
+    Ok(output)
+}
+
+/// The `ExtData::auditor` extension for a transaction creating `outputs`.
+pub fn auditor_disclosure(
+    rng: &mut impl CryptoRngCore,
+    viewing_key: &[u8; 32],
+    outputs: [&Utxo; 2],
+) -> Result<AuditorDisclosure, DisclosureError> {
+    Ok(AuditorDisclosure {
+        viewing_key: *viewing_key,
+        encrypted_outputs: [
+            encrypt_output(rng, viewing_key, &DisclosedOutput::from_utxo(outputs[0]))?,
+            encrypt_output(rng, viewing_key, &DisclosedOutput::from_utxo(outputs[1]))?,
+        ],
+    })
+}
+
+/// Decodes the data of a `Program data:` log line, if it is an `AuditorDisclosureEvent`.
+pub fn decode_event(data: &[u8]) -> Option<AuditorDisclosureEvent> {
+    let mut event_data = data.strip_prefix(AuditorDisclosureEvent::DISCRIMINATOR)?;
+    AuditorDisclosureEvent::deserialize(&mut event_data).ok()
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DisclosedTransaction {
+    pub commitments: [[u8; 32]; 2],
+    pub indices: [u64; 2],
+    pub ext_amount: i64,
+    pub fee: u64,
+    pub outputs: [DisclosedOutput; 2],
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DisclosureReport {
+    pub transactions: Vec<DisclosedTransaction>,
+    pub total_deposited: u64,
+    pub total_withdrawn: u64,
+    pub total_fees: u64,
+}
+
+/**
+ * Decrypts every event addressed to `viewing_key` into a report, skipping events addressed to
+ * other keys. Fails if any output disclosed to this key doesn't match its commitment.
+ */
+pub fn assemble_report<'a>(
+    viewing_key: &ViewingKey,
+    events: impl IntoIterator<Item = &'a AuditorDisclosureEvent>,
+) -> Result<DisclosureReport, DisclosureError> {
+    let public_key = viewing_key.public_key();
+    let mut report = DisclosureReport::default();
+
+    for event in events.into_iter().filter(|event| event.viewing_key == public_key) {
+        let [output0, output1] =
+            [0, 1].map(|i| decrypt_output(viewing_key, &event.commitments[i], &event.encrypted_outputs[i]));
+        let outputs = [output0?, output1?];
+
+        if event.ext_amount > 0 {
+            report.total_deposited = report.total_deposited.saturating_add(event.ext_amount as u64);
+        } else {
+            report.total_withdrawn = report.total_withdrawn.saturating_add(event.ext_amount.unsigned_abs());
+        }
+        report.total_fees = report.total_fees.saturating_add(event.fee);
+        report.transactions.push(DisclosedTransaction {
+            commitments: event.commitments,
+            indices: event.indices,
+            ext_amount: event.ext_amount,
+            fee: event.fee,
+            outputs,
+        });
+    }
+
+    Ok(report)
+}
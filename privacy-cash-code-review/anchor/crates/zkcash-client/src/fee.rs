@@ -0,0 +1,42 @@
+use anchor_lang::prelude::Rent;
+use zkcash::GlobalConfig;
+
+use crate::ClientError;
+
+pub use zkcash::utils::{transact_rent, TransactPdas};
+
+/**
+ * Lowest fee `transact` accepts under `config`, see `zkcash::utils::validate_fee`.
+ * When the pool requires the fee to cover rent, relayed transactions (anything but a deposit
+ * funded by the signer itself) must also pay `transact_rent` for the PDAs the signer creates.
+ * The rent depends on the lengths of the encrypted outputs, so quote with the final ones, and
+ * on `pdas`: a delayed withdrawal's pending withdrawal, or the stats of a referrer that has no
+ * `ReferrerStats` account yet.
+ */
+pub fn quote_fee(
+    config: &GlobalConfig,
+    rent: &Rent,
+    ext_amount: i64,
+    self_funded_deposit: bool,
+    encrypted_output1_len: usize,
+    encrypted_output2_len: usize,
+    pdas: TransactPdas,
+) -> Result<u64, ClientError> {
+    let minimum_fee = zkcash::utils::minimum_fee(
+        ext_amount,
+        config.deposit_fee_rate,
+        config.withdrawal_fee_rate,
+        config.fee_error_margin,
+        config.transfer_fee,
+    )
+    .map_err(|_| ClientError::FeeOverflow)?;
+
+    if !config.require_rent_in_fee || (self_funded_deposit && ext_amount > 0) {
+        return Ok(minimum_fee);
+    }
+
+    transact_rent(rent, config.nullifier_mode, encrypted_output1_len, encrypted_output2_len, pdas)
+        .ok()
+        .and_then(|rent_lamports| minimum_fee.checked_add(rent_lamports))
+        .ok_or(ClientError::FeeOverflow)
+}
@@ -0,0 +1,262 @@
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::system_program;
+use anchor_lang::{InstructionData, ToAccountMetas};
+
+use crate::{pda, ClientError};
+use zkcash::nullifier_tree::{NullifierInsertProof, NullifierMode};
+use zkcash::swap::{TOKEN_2022_PROGRAM_ID, TOKEN_PROGRAM_ID};
+use zkcash::utils::is_delayed_withdrawal;
+use zkcash::{ExtData, Proof};
+
+/**
+ * Builds `transact` instructions, deriving every PDA from the proof and ext data.
+ *
+ * `remaining_accounts` are laid out as `transact` expects: the recipients of a multi-recipient
+ * withdrawal, their blocklist entries if the pool's `screen_withdrawals` is set, then the callback
+ * program and its accounts. Callback accounts are passed writable; the program never grants them
+ * more than their owner allows.
+ * A swap takes the callback's place with the pool's `swap_program` and its accounts, also
+ * writable except for the system and token programs. Building a swap without `swap_program` set
+ * fails with `MissingSwapProgram`.
+ *
+ * The nullifier accounts follow the pool's `nullifier_mode`, Pda unless set otherwise. In Dual
+ * and Compressed mode, the insert proofs must first be stored with `write_nullifier_proof`.
+ *
+ * Withdrawals above the pool's `withdrawal_delay_threshold`, if set, get a pending withdrawal
+ * account and are paid out later by `finalize_withdrawal`.
+ */
+pub struct TransactBuilder {
+    proof: Proof,
+    ext_data: ExtData,
+    encrypted_output1: Vec<u8>,
+    encrypted_output2: Vec<u8>,
+    signer: Pubkey,
+    depositor: Option<Pubkey>,
+    nullifier_mode: NullifierMode,
+    withdrawal_delay_threshold: u64,
+    screen_withdrawals: bool,
+    swap_program: Option<Pubkey>,
+}
+
+impl TransactBuilder {
+    pub fn new(
+        proof: Proof,
+        ext_data: ExtData,
+        encrypted_output1: Vec<u8>,
+        encrypted_output2: Vec<u8>,
+        signer: Pubkey,
+    ) -> Self {
+        TransactBuilder {
+            proof,
+            ext_data,
+            encrypted_output1,
+            encrypted_output2,
+            signer,
+            depositor: None,
+            nullifier_mode: NullifierMode::Pda,
+            withdrawal_delay_threshold: 0,
+            screen_withdrawals: false,
+            swap_program: None,
+        }
+    }
+
+    pub fn nullifier_mode(mut self, nullifier_mode: NullifierMode) -> Self {
+        self.nullifier_mode = nullifier_mode;
+        self
+    }
+
+    pub fn withdrawal_delay_threshold(mut self, withdrawal_delay_threshold: u64) -> Self {
+        self.withdrawal_delay_threshold = withdrawal_delay_threshold;
+        self
+    }
+
+    /// The pool's `screen_withdrawals`, which adds the blocklist entries of listed recipients.
+    pub fn screen_withdrawals(mut self, screen_withdrawals: bool) -> Self {
+        self.screen_withdrawals = screen_withdrawals;
+        self
+    }
+
+    /// The pool's `swap_program`, needed to build withdrawals with `ext_data.swap`.
+    pub fn swap_program(mut self, swap_program: Pubkey) -> Self {
+        self.swap_program = Some(swap_program);
+        self
+    }
+
+    /// Funds a deposit from `depositor` instead of the signer, e.g. a PDA signing via CPI.
+    pub fn depositor(mut self, depositor: Pubkey) -> Self {
+        self.depositor = Some(depositor);
+        self
+    }
+
+    pub fn accounts(&self) -> zkcash::accounts::Transact {
+        let [nullifier0, nullifier1] = pda::nullifiers(&self.proof.input_nullifiers);
+        let [commitment0, commitment1] = pda::commitments(&self.proof.output_commitments);
+        let funding_account = self.depositor.unwrap_or(self.signer);
+
+        zkcash::accounts::Transact {
+            tree_account: pda::tree_account(),
+            nullifier0: self.nullifier_mode.uses_pdas().then_some(nullifier0),
+            nullifier1: self.nullifier_mode.uses_pdas().then_some(nullifier1),
+            commitment0,
+            commitment1,
+            tree_token_account: pda::tree_token_account(),
+            global_config: pda::global_config(),
+            recipient: self.ext_data.recipient,
+            fee_recipient_account: self.ext_data.fee_recipient,
+            signer: self.signer,
+            depositor: self.depositor,
+            depositor_blocklist_entry: pda::blocklist_entry(&funding_account),
+            recipient_blocklist_entry: pda::blocklist_entry(&self.ext_data.recipient),
+            nullifier_tree: self.nullifier_mode.uses_tree().then(pda::nullifier_tree),
+            nullifier_proofs: self
+                .nullifier_mode
+                .uses_tree()
+                .then(|| pda::nullifier_proofs(&self.signer)),
+            viewing_key_registry: self
+                .ext_data
+                .auditor
+                .as_ref()
+                .map(|auditor| pda::viewing_key_registry(&auditor.viewing_key)),
+            pending_withdrawal: is_delayed_withdrawal(self.ext_data.ext_amount, self.withdrawal_delay_threshold)
+                .then(|| pda::pending_withdrawal(&self.proof.input_nullifiers[0])),
+            referrer_stats: self.ext_data.referrer.as_ref().map(pda::referrer_stats),
+            swap_authority: self.ext_data.swap.as_ref().map(|_| pda::swap_authority()),
+            system_program: system_program::ID,
+        }
+    }
+
+    pub fn remaining_accounts(&self) -> Result<Vec<AccountMeta>, ClientError> {
+        let mut remaining_accounts = Vec::new();
+
+        if let Some(recipients) = &self.ext_data.recipients {
+            for withdrawal_recipient in recipients {
+                remaining_accounts.push(AccountMeta::new(withdrawal_recipient.recipient, false));
+            }
+            if self.screen_withdrawals {
+                for withdrawal_recipient in recipients {
+                    remaining_accounts.push(AccountMeta::new_readonly(
+                        pda::blocklist_entry(&withdrawal_recipient.recipient),
+                        false,
+                    ));
+                }
+            }
+        }
+
+        if let Some(callback) = &self.ext_data.callback {
+            remaining_accounts.push(AccountMeta::new_readonly(callback.program_id, false));
+            for account in &callback.accounts {
+                remaining_accounts.push(AccountMeta::new(*account, false));
+            }
+        }
+
+        if let Some(swap) = &self.ext_data.swap {
+            let swap_program = self.swap_program.ok_or(ClientError::MissingSwapProgram)?;
+            remaining_accounts.push(AccountMeta::new_readonly(swap_program, false));
+            for account in &swap.accounts {
+                remaining_accounts.push(
+                    if [system_program::ID, TOKEN_PROGRAM_ID, TOKEN_2022_PROGRAM_ID].contains(account) {
+                        AccountMeta::new_readonly(*account, false)
+                    } else {
+                        AccountMeta::new(*account, false)
+                    },
+                );
+            }
+        }
+
+        Ok(remaining_accounts)
+    }
+
+    pub fn instruction(&self) -> Result<Instruction, ClientError> {
+        let mut accounts = self.accounts().to_account_metas(None);
+        accounts.extend(self.remaining_accounts()?);
+
+        Ok(Instruction {
+            program_id: zkcash::ID,
+            accounts,
+            data: zkcash::instruction::Transact {
+                proof: self.proof.clone(),
+                ext_data: self.ext_data.clone(),
+                encrypted_output1: self.encrypted_output1.clone(),
+                encrypted_output2: self.encrypted_output2.clone(),
+            }
+            .data(),
+        })
+    }
+}
+
+/// Stores the insert proof for input `position` of the next transact of `signer`.
+pub fn write_nullifier_proof(signer: Pubkey, position: u8, insert_proof: NullifierInsertProof) -> Instruction {
+    Instruction {
+        program_id: zkcash::ID,
+        accounts: zkcash::accounts::WriteNullifierProof {
+            nullifier_proofs: pda::nullifier_proofs(&signer),
+            signer,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: zkcash::instruction::WriteNullifierProof {
+            position,
+            insert_proof,
+        }
+        .data(),
+    }
+}
+
+/**
+ * Inserts a nullifier spent under the PDA scheme into the nullifier tree. `nullifier_account` is
+ * its nullifier PDA, of whichever input position it was spent at, see `pda::nullifiers`.
+ */
+pub fn backfill_nullifier(
+    nullifier_account: Pubkey,
+    nullifier: [u8; 32],
+    insert_proof: NullifierInsertProof,
+    signer: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: zkcash::ID,
+        accounts: zkcash::accounts::BackfillNullifier {
+            nullifier_tree: pda::nullifier_tree(),
+            nullifier_account,
+            signer,
+        }
+        .to_account_metas(None),
+        data: zkcash::instruction::BackfillNullifier {
+            nullifier,
+            insert_proof,
+        }
+        .data(),
+    }
+}
+
+/// Closes the commitment PDA of the leaf at `index`, refunding its rent to `payer`, the payer
+/// recorded in it.
+pub fn reclaim_commitment(commitment: &[u8; 32], index: u64, payer: Pubkey) -> Instruction {
+    Instruction {
+        program_id: zkcash::ID,
+        accounts: zkcash::accounts::ReclaimCommitment {
+            commitment: pda::commitment(commitment, index),
+            tree_account: pda::tree_account(),
+            global_config: pda::global_config(),
+            payer,
+        }
+        .to_account_metas(None),
+        data: zkcash::instruction::ReclaimCommitment {}.data(),
+    }
+}
+
+/// Pays out the pending withdrawal of the transact whose first input nullifier is `input_nullifier`.
+pub fn finalize_withdrawal(input_nullifier: &[u8; 32], recipient: Pubkey, payer: Pubkey) -> Instruction {
+    Instruction {
+        program_id: zkcash::ID,
+        accounts: zkcash::accounts::FinalizeWithdrawal {
+            pending_withdrawal: pda::pending_withdrawal(input_nullifier),
+            tree_token_account: pda::tree_token_account(),
+            global_config: pda::global_config(),
+            recipient,
+            payer,
+        }
+        .to_account_metas(None),
+        data: zkcash::instruction::FinalizeWithdrawal {}.data(),
+    }
+}
@@ -0,0 +1,58 @@
+//! Client-side counterpart of the zkcash program: PDA derivation, ext data hashing,
+//! public amount encoding and instruction building, authority-only ones included, all reusing the
+//! program's own types so that off-chain encoding can't drift from what `transact` checks.
+//!
+//! With the `prover` feature, proofs for `transact` can be generated natively from the circuit
+//! wasm and zkey, without a JS toolchain.
+pub mod admin;
+pub mod amount;
+pub mod disclosure;
+pub mod fee;
+pub mod instructions;
+pub mod nullifier_tree;
+pub mod pda;
+#[cfg(feature = "prover")]
+pub mod prover;
+pub mod tree;
+pub mod utxo;
+
+pub use amount::{ext_data_hash_public_input, public_amount};
+pub use fee::quote_fee;
+pub use instructions::TransactBuilder;
+pub use nullifier_tree::IndexedMerkleTree;
+pub use tree::{leaves_since_root, OffchainMerkleTree, TreeError};
+pub use utxo::{Keypair, Utxo};
+pub use zkcash::{AuditorDisclosure, ExtData, Proof, SwapParams, WithdrawCallback, WithdrawalRecipient};
+
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ClientError {
+    #[error("i64::MIN can't be used as ext_amount")]
+    InvalidExtAmount,
+
+    #[error("Deposit amount must be greater than the fee")]
+    FeeExceedsDeposit,
+
+    #[error("Failed to serialize ext data: {0}")]
+    Serialization(String),
+
+    #[error("Poseidon hashing failed")]
+    HashFailed,
+
+    #[error("Fee computation overflowed")]
+    FeeOverflow,
+
+    #[error("A swap needs the pool's swap_program")]
+    MissingSwapProgram,
+}
+
+/// Hash of the ext data as computed by `transact`, see `zkcash::utils::calculate_ext_data_hash`.
+pub fn ext_data_hash(
+    ext_data: &ExtData,
+    encrypted_output1: &[u8],
+    encrypted_output2: &[u8],
+) -> Result<[u8; 32], ClientError> {
+    zkcash::utils::calculate_ext_data_hash(ext_data, encrypted_output1, encrypted_output2)
+        .map_err(|e| ClientError::Serialization(e.to_string()))
+}
@@ -0,0 +1,131 @@
+//! Off-chain indexed Merkle tree matching `zkcash::nullifier_tree::NullifierTree`.
+//!
+//! Keeps every leaf, so it can produce the low leaf openings `transact` and `backfill_nullifier`
+//! need to insert a nullifier. Leaf indices depend on insertion order, so the tree must be
+//! rebuilt by replaying inserts in the order they happened on chain.
+use std::collections::BTreeMap;
+
+use light_hasher::Poseidon;
+use zkcash::nullifier_tree::{IndexedLeaf, NullifierInsertProof, NULLIFIER_TREE_HEIGHT};
+use zkcash::NullifierTreeAccount;
+
+use crate::tree::{OffchainMerkleTree, TreeError};
+
+pub struct IndexedMerkleTree {
+    leaves: Vec<IndexedLeaf>,
+    // value -> leaf index, to find low leaves
+    indices: BTreeMap<[u8; 32], u64>,
+    tree: OffchainMerkleTree<Poseidon>,
+}
+
+impl IndexedMerkleTree {
+    /// An empty tree, holding only the sentinel leaf like a freshly initialized account.
+    pub fn new() -> Result<Self, TreeError> {
+        let sentinel = IndexedLeaf::default();
+        let mut tree = OffchainMerkleTree::new(NULLIFIER_TREE_HEIGHT);
+        tree.append(hash_leaf(&sentinel)?)?;
+        Ok(IndexedMerkleTree {
+            leaves: vec![sentinel],
+            indices: BTreeMap::from([(sentinel.value, 0)]),
+            tree,
+        })
+    }
+
+    /// Replays inserts in on-chain order.
+    pub fn from_values(values: impl IntoIterator<Item = [u8; 32]>) -> Result<Self, TreeError> {
+        let mut tree = Self::new()?;
+        for value in values {
+            tree.insert(value)?;
+        }
+        Ok(tree)
+    }
+
+    pub fn len(&self) -> u64 {
+        self.leaves.len() as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        // The sentinel is always there
+        false
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        self.tree.root()
+    }
+
+    pub fn contains(&self, value: &[u8; 32]) -> bool {
+        self.indices.contains_key(value)
+    }
+
+    pub fn leaf(&self, index: u64) -> Option<IndexedLeaf> {
+        self.leaves.get(index as usize).copied()
+    }
+
+    /// Opening of the low leaf of `value` against the current root.
+    pub fn insert_proof(&self, value: [u8; 32]) -> Result<NullifierInsertProof, TreeError> {
+        if self.contains(&value) {
+            return Err(TreeError::ValueExists);
+        }
+        // The sentinel holds 0, so every other value has a smaller one
+        let (_, &low_leaf_index) = self
+            .indices
+            .range(..value)
+            .next_back()
+            .ok_or(TreeError::ValueExists)?;
+
+        let siblings = self.tree.path(low_leaf_index)?;
+        Ok(NullifierInsertProof {
+            low_leaf: self.leaves[low_leaf_index as usize],
+            low_leaf_index,
+            siblings: siblings.try_into().expect("path has one sibling per level"),
+        })
+    }
+
+    /**
+     * Inserts `value` as `NullifierTree::insert` does and returns the proof to send along,
+     * which opens the state before the insert.
+     */
+    pub fn insert(&mut self, value: [u8; 32]) -> Result<NullifierInsertProof, TreeError> {
+        let proof = self.insert_proof(value)?;
+        let low_leaf_index = proof.low_leaf_index as usize;
+        let new_index = self.len();
+
+        let low_leaf = self.leaves[low_leaf_index];
+        let new_leaf = IndexedLeaf {
+            value,
+            next_index: low_leaf.next_index,
+            next_value: low_leaf.next_value,
+        };
+        let updated_low_leaf = IndexedLeaf {
+            next_index: new_index,
+            next_value: value,
+            ..low_leaf
+        };
+
+        self.tree.update(proof.low_leaf_index, hash_leaf(&updated_low_leaf)?)?;
+        self.tree.append(hash_leaf(&new_leaf)?)?;
+        self.leaves[low_leaf_index] = updated_low_leaf;
+        self.leaves.push(new_leaf);
+        self.indices.insert(value, new_index);
+
+        Ok(proof)
+    }
+
+    /// Checks this tree against the on-chain account, which must be fully synced.
+    pub fn verify_against(&self, tree_account: &NullifierTreeAccount) -> Result<(), TreeError> {
+        if self.len() != tree_account.next_index {
+            return Err(TreeError::LeafCountMismatch {
+                leaves: self.len(),
+                next_index: tree_account.next_index,
+            });
+        }
+        if self.root() != tree_account.root {
+            return Err(TreeError::RootMismatch);
+        }
+        Ok(())
+    }
+}
+
+fn hash_leaf(leaf: &IndexedLeaf) -> Result<[u8; 32], TreeError> {
+    leaf.hash::<Poseidon>().map_err(|_| TreeError::HashFailed)
+}
@@ -0,0 +1,85 @@
+//! PDA derivation for every seed used by the zkcash program.
+use anchor_lang::prelude::Pubkey;
+
+pub const MERKLE_TREE_SEED: &[u8] = b"merkle_tree";
+pub const TREE_TOKEN_SEED: &[u8] = b"tree_token";
+pub const GLOBAL_CONFIG_SEED: &[u8] = b"global_config";
+pub const NULLIFIER0_SEED: &[u8] = b"nullifier0";
+pub const NULLIFIER1_SEED: &[u8] = b"nullifier1";
+pub const COMMITMENT0_SEED: &[u8] = b"commitment0";
+pub const COMMITMENT1_SEED: &[u8] = b"commitment1";
+pub const BLOCKLIST_SEED: &[u8] = zkcash::blocklist::BLOCKLIST_SEED;
+pub const NULLIFIER_TREE_SEED: &[u8] = b"nullifier_tree";
+pub const NULLIFIER_PROOFS_SEED: &[u8] = b"nullifier_proofs";
+pub const VIEWING_KEY_SEED: &[u8] = zkcash::disclosure::VIEWING_KEY_SEED;
+pub const PENDING_WITHDRAWAL_SEED: &[u8] = b"pending_withdrawal";
+pub const REFERRER_SEED: &[u8] = zkcash::referral::REFERRER_SEED;
+pub const SWAP_AUTHORITY_SEED: &[u8] = zkcash::swap::SWAP_AUTHORITY_SEED;
+
+fn find(seeds: &[&[u8]]) -> Pubkey {
+    Pubkey::find_program_address(seeds, &zkcash::ID).0
+}
+
+pub fn tree_account() -> Pubkey {
+    find(&[MERKLE_TREE_SEED])
+}
+
+pub fn tree_token_account() -> Pubkey {
+    find(&[TREE_TOKEN_SEED])
+}
+
+pub fn global_config() -> Pubkey {
+    find(&[GLOBAL_CONFIG_SEED])
+}
+
+/// Nullifier PDAs for the two input nullifiers of a proof.
+pub fn nullifiers(input_nullifiers: &[[u8; 32]; 2]) -> [Pubkey; 2] {
+    [
+        find(&[NULLIFIER0_SEED, &input_nullifiers[0]]),
+        find(&[NULLIFIER1_SEED, &input_nullifiers[1]]),
+    ]
+}
+
+/// Commitment PDAs for the two output commitments of a proof.
+pub fn commitments(output_commitments: &[[u8; 32]; 2]) -> [Pubkey; 2] {
+    [
+        find(&[COMMITMENT0_SEED, &output_commitments[0]]),
+        find(&[COMMITMENT1_SEED, &output_commitments[1]]),
+    ]
+}
+
+/// Commitment PDA of the leaf at `index`, as `reclaim_commitment` expects it.
+pub fn commitment(commitment: &[u8; 32], index: u64) -> Pubkey {
+    find(&[zkcash::utils::commitment_seed_prefix(index), commitment])
+}
+
+pub fn blocklist_entry(address: &Pubkey) -> Pubkey {
+    find(&[BLOCKLIST_SEED, address.as_ref()])
+}
+
+pub fn nullifier_tree() -> Pubkey {
+    find(&[NULLIFIER_TREE_SEED])
+}
+
+/// Buffer holding the nullifier insert proofs for the next transact of `signer`.
+pub fn nullifier_proofs(signer: &Pubkey) -> Pubkey {
+    find(&[NULLIFIER_PROOFS_SEED, signer.as_ref()])
+}
+
+pub fn viewing_key_registry(viewing_key: &[u8; 32]) -> Pubkey {
+    find(&[VIEWING_KEY_SEED, viewing_key])
+}
+
+/// Pending withdrawal of a delayed withdrawal, seeded with its first input nullifier.
+pub fn pending_withdrawal(input_nullifier: &[u8; 32]) -> Pubkey {
+    find(&[PENDING_WITHDRAWAL_SEED, input_nullifier])
+}
+
+pub fn referrer_stats(referrer: &[u8; 32]) -> Pubkey {
+    find(&[REFERRER_SEED, referrer])
+}
+
+/// Signs swaps for the swap program, and must be listed in the accounts of every `SwapParams`.
+pub fn swap_authority() -> Pubkey {
+    find(&[SWAP_AUTHORITY_SEED])
+}
@@ -0,0 +1,247 @@
+//! Native Groth16 prover for the transaction circuit (`artifacts/circuits/transaction2`).
+//!
+//! Runs the circom witness calculator from the circuit wasm and proves with the zkey, then
+//! lays the proof out the way `zkcash::utils::verify_proof` reads it: big-endian field
+//! elements, G2 coordinates as (c1, c0), and `proof_a` not negated since the program
+//! negates it itself.
+use std::fs::File;
+use std::path::Path;
+
+use ark_bn254::{Bn254, Fr, G1Affine, G2Affine};
+use ark_circom::{read_zkey, CircomReduction, WitnessCalculator};
+use ark_ff::{BigInteger, PrimeField, UniformRand};
+use ark_groth16::{Groth16, ProvingKey};
+use ark_relations::r1cs::ConstraintMatrices;
+use num_bigint::{BigInt, BigUint};
+use rand::{CryptoRng, RngCore};
+use thiserror::Error;
+use wasmer::Store;
+use zkcash::Proof;
+
+use crate::amount::to_be_bytes;
+use crate::utxo::Utxo;
+use crate::ClientError;
+
+/// Height of the tree the circuit was compiled for, see `circuits/transaction2.circom`.
+pub const CIRCUIT_LEVELS: usize = 26;
+
+#[derive(Error, Debug)]
+pub enum ProverError {
+    #[error("Failed to load circuit artifacts: {0}")]
+    Artifacts(String),
+
+    #[error("Witness calculation failed: {0}")]
+    Witness(String),
+
+    #[error("Proof generation failed: {0}")]
+    Proving(String),
+
+    #[error("Inputs and outputs must use the same mint")]
+    MintMismatch,
+
+    #[error("Merkle path must have {CIRCUIT_LEVELS} elements")]
+    InvalidPathLength,
+
+    #[error(transparent)]
+    Client(#[from] ClientError),
+}
+
+/**
+ * Everything the circuit needs for one 2-in/2-out transaction. `public_amount` and
+ * `ext_data_hash` are the big-endian public inputs, as returned by `crate::public_amount`
+ * and `crate::ext_data_hash_public_input`. Dummy inputs have a zero amount and any path,
+ * since the circuit only checks the Merkle root for non-zero inputs.
+ */
+#[derive(Clone, Debug)]
+pub struct TransactionInputs {
+    pub root: [u8; 32],
+    pub public_amount: [u8; 32],
+    pub ext_data_hash: [u8; 32],
+    pub inputs: [Utxo; 2],
+    pub input_paths: [Vec<[u8; 32]>; 2],
+    pub outputs: [Utxo; 2],
+}
+
+impl TransactionInputs {
+    /// Public inputs in the order the circuit declares them, which is the order `verify_proof` uses.
+    pub fn public_inputs(&self) -> Result<[[u8; 32]; 7], ProverError> {
+        Ok([
+            self.root,
+            self.public_amount,
+            self.ext_data_hash,
+            self.inputs[0].nullifier_bytes()?,
+            self.inputs[1].nullifier_bytes()?,
+            self.outputs[0].commitment_bytes()?,
+            self.outputs[1].commitment_bytes()?,
+        ])
+    }
+
+    /// Named signals for the witness calculator, matching `circuits/transaction.circom`.
+    pub fn circuit_inputs(&self) -> Result<Vec<(String, Vec<BigInt>)>, ProverError> {
+        let mint_address = self.inputs[0].mint_address;
+        if self
+            .inputs
+            .iter()
+            .chain(self.outputs.iter())
+            .any(|utxo| utxo.mint_address != mint_address)
+        {
+            return Err(ProverError::MintMismatch);
+        }
+        if self.input_paths.iter().any(|path| path.len() != CIRCUIT_LEVELS) {
+            return Err(ProverError::InvalidPathLength);
+        }
+
+        let mut path_elements = Vec::with_capacity(2 * CIRCUIT_LEVELS);
+        for path in &self.input_paths {
+            path_elements.extend(path.iter().map(be_bytes_to_bigint));
+        }
+
+        Ok(vec![
+            ("root".to_string(), vec![be_bytes_to_bigint(&self.root)]),
+            ("publicAmount".to_string(), vec![be_bytes_to_bigint(&self.public_amount)]),
+            ("extDataHash".to_string(), vec![be_bytes_to_bigint(&self.ext_data_hash)]),
+            ("mintAddress".to_string(), vec![fr_to_bigint(mint_address)]),
+            (
+                "inputNullifier".to_string(),
+                self.inputs
+                    .iter()
+                    .map(|utxo| utxo.nullifier().map(fr_to_bigint))
+                    .collect::<Result<_, _>>()?,
+            ),
+            (
+                "inAmount".to_string(),
+                self.inputs.iter().map(|utxo| BigInt::from(utxo.amount)).collect(),
+            ),
+            (
+                "inPrivateKey".to_string(),
+                self.inputs.iter().map(|utxo| fr_to_bigint(utxo.keypair.privkey)).collect(),
+            ),
+            (
+                "inBlinding".to_string(),
+                self.inputs.iter().map(|utxo| fr_to_bigint(utxo.blinding)).collect(),
+            ),
+            (
+                "inPathIndices".to_string(),
+                self.inputs.iter().map(|utxo| BigInt::from(utxo.index)).collect(),
+            ),
+            ("inPathElements".to_string(), path_elements),
+            (
+                "outputCommitment".to_string(),
+                self.outputs
+                    .iter()
+                    .map(|utxo| utxo.commitment().map(fr_to_bigint))
+                    .collect::<Result<_, _>>()?,
+            ),
+            (
+                "outAmount".to_string(),
+                self.outputs.iter().map(|utxo| BigInt::from(utxo.amount)).collect(),
+            ),
+            (
+                "outPubkey".to_string(),
+                self.outputs.iter().map(|utxo| fr_to_bigint(utxo.keypair.pubkey)).collect(),
+            ),
+            (
+                "outBlinding".to_string(),
+                self.outputs.iter().map(|utxo| fr_to_bigint(utxo.blinding)).collect(),
+            ),
+        ])
+    }
+}
+
+pub struct Prover {
+    store: Store,
+    witness_calculator: WitnessCalculator,
+    proving_key: ProvingKey<Bn254>,
+    matrices: ConstraintMatrices<Fr>,
+}
+
+impl Prover {
+    /// Loads `<base_path>.wasm` and `<base_path>.zkey`, e.g. `artifacts/circuits/transaction2`.
+    pub fn from_base_path(base_path: impl AsRef<Path>) -> Result<Self, ProverError> {
+        let base_path = base_path.as_ref();
+        Self::new(base_path.with_extension("wasm"), base_path.with_extension("zkey"))
+    }
+
+    pub fn new(wasm_path: impl AsRef<Path>, zkey_path: impl AsRef<Path>) -> Result<Self, ProverError> {
+        let mut store = Store::default();
+        let witness_calculator = WitnessCalculator::new(&mut store, wasm_path)
+            .map_err(|e| ProverError::Artifacts(e.to_string()))?;
+
+        let mut zkey_file =
+            File::open(zkey_path).map_err(|e| ProverError::Artifacts(e.to_string()))?;
+        let (proving_key, matrices) =
+            read_zkey(&mut zkey_file).map_err(|e| ProverError::Artifacts(e.to_string()))?;
+
+        Ok(Prover {
+            store,
+            witness_calculator,
+            proving_key,
+            matrices,
+        })
+    }
+
+    pub fn prove<R: RngCore + CryptoRng>(
+        &mut self,
+        inputs: &TransactionInputs,
+        rng: &mut R,
+    ) -> Result<Proof, ProverError> {
+        let witness = self
+            .witness_calculator
+            .calculate_witness_element::<Fr, _>(&mut self.store, inputs.circuit_inputs()?, true)
+            .map_err(|e| ProverError::Witness(e.to_string()))?;
+
+        let r = Fr::rand(rng);
+        let s = Fr::rand(rng);
+        let proof = Groth16::<Bn254, CircomReduction>::create_proof_with_reduction_and_matrices(
+            &self.proving_key,
+            r,
+            s,
+            &self.matrices,
+            self.matrices.num_instance_variables,
+            self.matrices.num_constraints,
+            &witness,
+        )
+        .map_err(|e| ProverError::Proving(e.to_string()))?;
+
+        let public_inputs = inputs.public_inputs()?;
+        Ok(Proof {
+            proof_a: g1_to_be_bytes(&proof.a),
+            proof_b: g2_to_be_bytes(&proof.b),
+            proof_c: g1_to_be_bytes(&proof.c),
+            root: public_inputs[0],
+            public_amount: public_inputs[1],
+            ext_data_hash: public_inputs[2],
+            input_nullifiers: [public_inputs[3], public_inputs[4]],
+            output_commitments: [public_inputs[5], public_inputs[6]],
+        })
+    }
+}
+
+fn be_bytes_to_bigint(bytes: &[u8; 32]) -> BigInt {
+    BigInt::from(BigUint::from_bytes_be(bytes))
+}
+
+fn fr_to_bigint(value: Fr) -> BigInt {
+    be_bytes_to_bigint(&to_be_bytes(value))
+}
+
+fn fq_to_be_bytes<F: PrimeField>(value: F, out: &mut [u8]) {
+    out.copy_from_slice(&value.into_bigint().to_bytes_be());
+}
+
+fn g1_to_be_bytes(point: &G1Affine) -> [u8; 64] {
+    let mut bytes = [0u8; 64];
+    fq_to_be_bytes(point.x, &mut bytes[..32]);
+    fq_to_be_bytes(point.y, &mut bytes[32..]);
+    bytes
+}
+
+// Same layout as the G2 points in the verifying key: x.c1 || x.c0 || y.c1 || y.c0
+fn g2_to_be_bytes(point: &G2Affine) -> [u8; 128] {
+    let mut bytes = [0u8; 128];
+    fq_to_be_bytes(point.x.c1, &mut bytes[..32]);
+    fq_to_be_bytes(point.x.c0, &mut bytes[32..64]);
+    fq_to_be_bytes(point.y.c1, &mut bytes[64..96]);
+    fq_to_be_bytes(point.y.c0, &mut bytes[96..]);
+    bytes
+}
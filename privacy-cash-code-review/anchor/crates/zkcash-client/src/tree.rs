@@ -0,0 +1,271 @@
+//! Off-chain incremental Merkle tree matching `zkcash::merkle_tree::MerkleTree`.
+//!
+//! Unlike the on-chain tree, which only keeps the right-most subtrees, this keeps every node,
+//! so it can produce inclusion paths for any leaf. Wallets use it to build spend proofs and the
+//! indexer uses it to check that its view of the commitments matches the on-chain root.
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+use std::ops::Range;
+
+use anchor_lang::AccountDeserialize;
+use light_hasher::Hasher;
+use thiserror::Error;
+use zkcash::merkle_tree::MerkleTree;
+use zkcash::{CommitmentAccount, MerkleTreeAccount};
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum TreeError {
+    #[error("Merkle tree is full")]
+    TreeFull,
+
+    #[error("Leaf index {0} is out of range")]
+    IndexOutOfRange(u64),
+
+    #[error("Leaf index {0} appears more than once")]
+    DuplicateIndex(u64),
+
+    #[error("Leaf index {0} is missing")]
+    MissingIndex(u64),
+
+    #[error("Failed to decode commitment account: {0}")]
+    InvalidCommitmentAccount(String),
+
+    #[error("Hashing failed")]
+    HashFailed,
+
+    #[error("Tree has {leaves} leaves but the account has {next_index}")]
+    LeafCountMismatch { leaves: u64, next_index: u64 },
+
+    #[error("Computed root does not match the account root")]
+    RootMismatch,
+
+    #[error("Computed root is not in the account root history")]
+    UnknownRoot,
+
+    #[error("Account did not record the leaf range of this root")]
+    UnknownRootRange,
+
+    #[error("Value is already in the indexed tree")]
+    ValueExists,
+}
+
+pub struct OffchainMerkleTree<H: Hasher> {
+    height: usize,
+    // layers[0] holds the leaves and layers[height] the root, missing nodes are zero subtrees
+    layers: Vec<Vec<[u8; 32]>>,
+    _hasher: PhantomData<H>,
+}
+
+impl<H: Hasher> OffchainMerkleTree<H> {
+    pub fn new(height: usize) -> Self {
+        OffchainMerkleTree {
+            height,
+            layers: vec![Vec::new(); height + 1],
+            _hasher: PhantomData,
+        }
+    }
+
+    pub fn from_leaves(height: usize, leaves: &[[u8; 32]]) -> Result<Self, TreeError> {
+        let mut tree = Self::new(height);
+        for leaf in leaves {
+            tree.append(*leaf)?;
+        }
+        Ok(tree)
+    }
+
+    /**
+     * Builds the tree from leaves tagged with their index, e.g. taken from commitment accounts or
+     * events, in any order. Indices must be exactly 0..n, gaps and duplicates are reported.
+     */
+    pub fn from_indexed_leaves(
+        height: usize,
+        leaves: impl IntoIterator<Item = (u64, [u8; 32])>,
+    ) -> Result<Self, TreeError> {
+        let mut sorted_leaves = BTreeMap::new();
+        for (index, leaf) in leaves {
+            if sorted_leaves.insert(index, leaf).is_some() {
+                return Err(TreeError::DuplicateIndex(index));
+            }
+        }
+
+        let mut tree = Self::new(height);
+        for (expected_index, (index, leaf)) in sorted_leaves.into_iter().enumerate() {
+            if index != expected_index as u64 {
+                return Err(TreeError::MissingIndex(expected_index as u64));
+            }
+            tree.append(leaf)?;
+        }
+        Ok(tree)
+    }
+
+    pub fn from_commitment_accounts<'a>(
+        height: usize,
+        accounts: impl IntoIterator<Item = &'a CommitmentAccount>,
+    ) -> Result<Self, TreeError> {
+        Self::from_indexed_leaves(
+            height,
+            accounts.into_iter().map(|account| (account.index, account.commitment)),
+        )
+    }
+
+    /// Builds the tree from raw `CommitmentAccount` data, discriminator included.
+    pub fn from_commitment_account_data<'a>(
+        height: usize,
+        accounts_data: impl IntoIterator<Item = &'a [u8]>,
+    ) -> Result<Self, TreeError> {
+        let accounts = accounts_data
+            .into_iter()
+            .map(|mut data| {
+                CommitmentAccount::try_deserialize(&mut data)
+                    .map_err(|e| TreeError::InvalidCommitmentAccount(e.to_string()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::from_commitment_accounts(height, &accounts)
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn len(&self) -> u64 {
+        self.layers[0].len() as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers[0].is_empty()
+    }
+
+    pub fn leaf(&self, index: u64) -> Option<[u8; 32]> {
+        self.layers[0].get(index as usize).copied()
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        self.layers[self.height]
+            .first()
+            .copied()
+            .unwrap_or(H::zero_bytes()[self.height])
+    }
+
+    /// Appends a leaf and returns its index.
+    pub fn append(&mut self, leaf: [u8; 32]) -> Result<u64, TreeError> {
+        let index = self.len();
+        if index >= 1u64 << self.height {
+            return Err(TreeError::TreeFull);
+        }
+
+        self.layers[0].push(leaf);
+        self.rehash_path(index)?;
+
+        Ok(index)
+    }
+
+    /// Replaces the leaf at `index`, e.g. a low leaf of an indexed tree.
+    pub fn update(&mut self, index: u64, leaf: [u8; 32]) -> Result<(), TreeError> {
+        if index >= self.len() {
+            return Err(TreeError::IndexOutOfRange(index));
+        }
+        self.layers[0][index as usize] = leaf;
+        self.rehash_path(index)
+    }
+
+    fn rehash_path(&mut self, index: u64) -> Result<(), TreeError> {
+        let zero_bytes = H::zero_bytes();
+        let mut current_index = index as usize;
+        let mut current_hash = self.layers[0][current_index];
+
+        for (level, &zero_byte) in zero_bytes.iter().enumerate().take(self.height) {
+            let sibling = self.layers[level]
+                .get(current_index ^ 1)
+                .copied()
+                .unwrap_or(zero_byte);
+            let (left, right) = if current_index % 2 == 0 {
+                (current_hash, sibling)
+            } else {
+                (sibling, current_hash)
+            };
+            current_hash = H::hashv(&[&left, &right]).map_err(|_| TreeError::HashFailed)?;
+            current_index /= 2;
+
+            let parent_layer = &mut self.layers[level + 1];
+            if current_index < parent_layer.len() {
+                parent_layer[current_index] = current_hash;
+            } else {
+                parent_layer.push(current_hash);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sibling hashes from the leaf up to the root, in the order the circuit expects.
+    pub fn path(&self, index: u64) -> Result<Vec<[u8; 32]>, TreeError> {
+        if index >= self.len() {
+            return Err(TreeError::IndexOutOfRange(index));
+        }
+
+        let zero_bytes = H::zero_bytes();
+        let mut current_index = index as usize;
+        let mut path = Vec::with_capacity(self.height);
+        for (level, &zero_byte) in zero_bytes.iter().enumerate().take(self.height) {
+            let sibling_index = current_index ^ 1;
+            path.push(
+                self.layers[level]
+                    .get(sibling_index)
+                    .copied()
+                    .unwrap_or(zero_byte),
+            );
+            current_index /= 2;
+        }
+        Ok(path)
+    }
+
+    /// Recomputes the root from a leaf and its inclusion path.
+    pub fn compute_root(leaf: [u8; 32], index: u64, path: &[[u8; 32]]) -> Result<[u8; 32], TreeError> {
+        let mut current_index = index;
+        let mut current_hash = leaf;
+        for sibling in path {
+            current_hash = if current_index % 2 == 0 {
+                H::hashv(&[&current_hash, sibling])
+            } else {
+                H::hashv(&[sibling, &current_hash])
+            }
+            .map_err(|_| TreeError::HashFailed)?;
+            current_index /= 2;
+        }
+        Ok(current_hash)
+    }
+
+    /**
+     * Checks this tree against the on-chain account. A fully synced tree must have the account's
+     * current root, a tree that is behind must have a root that is still in the root history.
+     */
+    pub fn verify_against(&self, tree_account: &MerkleTreeAccount) -> Result<(), TreeError> {
+        let root = self.root();
+        if self.len() > tree_account.next_index {
+            return Err(TreeError::LeafCountMismatch {
+                leaves: self.len(),
+                next_index: tree_account.next_index,
+            });
+        }
+        if self.len() == tree_account.next_index && root != tree_account.root {
+            return Err(TreeError::RootMismatch);
+        }
+        if !MerkleTree::is_known_root(tree_account, root) {
+            return Err(TreeError::UnknownRoot);
+        }
+        Ok(())
+    }
+}
+
+/**
+ * Leaf indices a client synced up to `root` is missing: from the end of the range `root`
+ * covers to the account's `next_index`. Light wallets that only keep their last root fetch
+ * the commitments at these indices instead of every commitment.
+ */
+pub fn leaves_since_root(tree_account: &MerkleTreeAccount, root: [u8; 32]) -> Result<Range<u64>, TreeError> {
+    if !MerkleTree::is_known_root(tree_account, root) {
+        return Err(TreeError::UnknownRoot);
+    }
+    let covered = MerkleTree::root_leaf_range(tree_account, root).ok_or(TreeError::UnknownRootRange)?;
+    Ok(covered.end..tree_account.next_index)
+}
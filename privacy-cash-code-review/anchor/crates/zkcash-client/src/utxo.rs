@@ -0,0 +1,107 @@
+//! Keypairs and UTXOs as defined by the transaction circuit (see `circuits/keypair.circom`).
+//!
+//! ```text
+//! pubkey     = Poseidon(privkey)
+//! commitment = Poseidon(amount, pubkey, blinding, mintAddress)
+//! signature  = Poseidon(privkey, commitment, pathIndex)
+//! nullifier  = Poseidon(commitment, pathIndex, signature)
+//! ```
+use std::str::FromStr;
+
+use ark_bn254::Fr;
+use ark_ff::PrimeField;
+use light_hasher::{Hasher, Poseidon};
+
+use crate::amount::to_be_bytes;
+use crate::ClientError;
+
+/// The circuit's mint address for SOL. It is the decimal field element 11111111111111111111111111111112,
+/// not the base58 decoding of `zkcash::utils::SOL_ADDRESS`.
+pub const SOL_MINT_ADDRESS: &str = "11111111111111111111111111111112";
+
+pub fn sol_mint_address() -> Fr {
+    Fr::from_str(SOL_MINT_ADDRESS).expect("SOL mint address is a valid field element")
+}
+
+pub(crate) fn poseidon(inputs: &[Fr]) -> Result<Fr, ClientError> {
+    let inputs: Vec<[u8; 32]> = inputs.iter().map(|input| to_be_bytes(*input)).collect();
+    let inputs: Vec<&[u8]> = inputs.iter().map(|input| input.as_slice()).collect();
+    let hash = Poseidon::hashv(&inputs).map_err(|_| ClientError::HashFailed)?;
+    Ok(Fr::from_be_bytes_mod_order(&hash))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Keypair {
+    pub privkey: Fr,
+    pub pubkey: Fr,
+}
+
+impl Keypair {
+    pub fn new(privkey: Fr) -> Result<Self, ClientError> {
+        Ok(Keypair {
+            privkey,
+            pubkey: poseidon(&[privkey])?,
+        })
+    }
+
+    /// Private keys in the TS client are 32 random bytes reduced into the field.
+    pub fn from_be_bytes(privkey: &[u8; 32]) -> Result<Self, ClientError> {
+        Self::new(Fr::from_be_bytes_mod_order(privkey))
+    }
+
+    pub fn sign(&self, commitment: Fr, path_index: u64) -> Result<Fr, ClientError> {
+        poseidon(&[self.privkey, commitment, Fr::from(path_index)])
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Utxo {
+    pub amount: u64,
+    pub blinding: Fr,
+    pub keypair: Keypair,
+    /// Leaf index in the Merkle tree, only meaningful for UTXOs being spent.
+    pub index: u64,
+    pub mint_address: Fr,
+}
+
+impl Utxo {
+    pub fn new(amount: u64, keypair: Keypair, blinding: Fr) -> Self {
+        Utxo {
+            amount,
+            blinding,
+            keypair,
+            index: 0,
+            mint_address: sol_mint_address(),
+        }
+    }
+
+    pub fn with_index(mut self, index: u64) -> Self {
+        self.index = index;
+        self
+    }
+
+    pub fn commitment(&self) -> Result<Fr, ClientError> {
+        poseidon(&[
+            Fr::from(self.amount),
+            self.keypair.pubkey,
+            self.blinding,
+            self.mint_address,
+        ])
+    }
+
+    pub fn nullifier(&self) -> Result<Fr, ClientError> {
+        let commitment = self.commitment()?;
+        let signature = self.keypair.sign(commitment, self.index)?;
+        poseidon(&[commitment, Fr::from(self.index), signature])
+    }
+
+    /// Commitment as stored in the tree and in `Proof::output_commitments`.
+    pub fn commitment_bytes(&self) -> Result<[u8; 32], ClientError> {
+        self.commitment().map(to_be_bytes)
+    }
+
+    /// Nullifier as seeded into the nullifier PDA and in `Proof::input_nullifiers`.
+    pub fn nullifier_bytes(&self) -> Result<[u8; 32], ClientError> {
+        self.nullifier().map(to_be_bytes)
+    }
+}
@@ -0,0 +1,352 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::{Discriminator, InstructionData};
+use ark_bn254::Fr;
+use ark_ff::PrimeField;
+use zkcash::nullifier_tree::NullifierMode;
+use zkcash::utils::{calculate_complete_ext_data_hash, check_public_amount, SOL_ADDRESS};
+use zkcash_client::{
+    admin, ext_data_hash, ext_data_hash_public_input, pda, public_amount, AuditorDisclosure, ClientError, ExtData, Proof,
+    SwapParams, TransactBuilder, WithdrawCallback, WithdrawalRecipient,
+};
+
+fn test_proof() -> Proof {
+    Proof {
+        proof_a: [1u8; 64],
+        proof_b: [2u8; 128],
+        proof_c: [3u8; 64],
+        root: [4u8; 32],
+        public_amount: [5u8; 32],
+        ext_data_hash: [6u8; 32],
+        input_nullifiers: [[7u8; 32], [8u8; 32]],
+        output_commitments: [[9u8; 32], [10u8; 32]],
+    }
+}
+
+fn test_ext_data(ext_amount: i64) -> ExtData {
+    ExtData {
+        recipient: Pubkey::new_unique(),
+        ext_amount,
+        fee: 100,
+        fee_recipient: Pubkey::new_unique(),
+        mint_address: SOL_ADDRESS,
+        callback: None,
+        recipients: None,
+        auditor: None,
+        memo: None,
+        referrer: None,
+        swap: None,
+    }
+}
+
+#[test]
+fn public_amount_is_accepted_by_program() {
+    for (ext_amount, fee) in [(1_000_000i64, 0u64), (1_000_000, 999_999), (-1_000_000, 0), (-1_000_000, 2_500), (-1, u64::MAX), (i64::MAX, 1), (0, 0), (0, 5_000)] {
+        let encoded = public_amount(ext_amount, fee).unwrap();
+        assert!(
+            check_public_amount(ext_amount, fee, encoded),
+            "program rejected public amount for ext_amount {} and fee {}",
+            ext_amount,
+            fee
+        );
+    }
+}
+
+#[test]
+fn public_amount_rejects_what_program_rejects() {
+    assert_eq!(public_amount(i64::MIN, 0), Err(ClientError::InvalidExtAmount));
+    assert_eq!(public_amount(100, 100), Err(ClientError::FeeExceedsDeposit));
+}
+
+#[test]
+fn ext_data_hash_matches_program() {
+    let ext_data = test_ext_data(-1_000_000);
+    let hash = ext_data_hash(&ext_data, b"output1", b"output2").unwrap();
+    let program_hash = calculate_complete_ext_data_hash(
+        ext_data.recipient,
+        ext_data.ext_amount,
+        b"output1",
+        b"output2",
+        ext_data.fee,
+        ext_data.fee_recipient,
+        ext_data.mint_address,
+    )
+    .unwrap();
+    assert_eq!(hash, program_hash);
+
+    // The public input is what `transact` compares the hash against
+    let public_input = ext_data_hash_public_input(&hash);
+    assert_eq!(Fr::from_le_bytes_mod_order(&hash), Fr::from_be_bytes_mod_order(&public_input));
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Same vectors as `getExtDataHash` in scripts/__tests__/utils.test.ts, so the TS client can't drift.
+#[test]
+fn ext_data_hash_vectors() {
+    let mut ext_data = test_ext_data(-1_000_000);
+    ext_data.recipient = Pubkey::new_from_array([1u8; 32]);
+    ext_data.fee = 2_500;
+    ext_data.fee_recipient = Pubkey::new_from_array([2u8; 32]);
+    ext_data.mint_address = Pubkey::new_from_array([3u8; 32]);
+    assert_eq!(
+        hex(&ext_data_hash(&ext_data, b"output1", b"output2").unwrap()),
+        "04d4f0746d7fd0c115455aebc70dd19c1a7d0c098f87d60ceb5bb9e5d6c5e8d5"
+    );
+
+    ext_data.callback = Some(WithdrawCallback {
+        program_id: Pubkey::new_from_array([4u8; 32]),
+        accounts: vec![Pubkey::new_from_array([5u8; 32]), Pubkey::new_from_array([6u8; 32])],
+        data: vec![7, 8, 9],
+    });
+    assert_eq!(
+        hex(&ext_data_hash(&ext_data, b"output1", b"output2").unwrap()),
+        "5dc03d82a7148b79aa1e2fd975668644112ad2d45cab94a01549c636b39c3e78"
+    );
+
+    ext_data.recipients = Some(vec![
+        WithdrawalRecipient { recipient: Pubkey::new_from_array([10u8; 32]), amount: 400_000 },
+        WithdrawalRecipient { recipient: Pubkey::new_from_array([11u8; 32]), amount: 600_000 },
+    ]);
+    ext_data.auditor = Some(AuditorDisclosure {
+        viewing_key: [12u8; 32],
+        encrypted_outputs: [vec![13, 14], vec![15]],
+    });
+    ext_data.memo = Some(vec![16, 17]);
+    ext_data.referrer = Some([18u8; 32]);
+    ext_data.swap = Some(SwapParams {
+        output_mint: Pubkey::new_from_array([19u8; 32]),
+        recipient_token_account: Pubkey::new_from_array([20u8; 32]),
+        min_out: 5,
+        accounts: vec![Pubkey::new_from_array([21u8; 32])],
+        data: vec![22],
+    });
+    assert_eq!(
+        hex(&ext_data_hash(&ext_data, b"output1", b"output2").unwrap()),
+        "272e22e4286d610bb28f88b93975bf173406cadc100ba9b1470005051889c607"
+    );
+}
+
+#[test]
+fn pdas_match_program_derivation() {
+    let address = Pubkey::new_unique();
+    assert_eq!(pda::blocklist_entry(&address), zkcash::blocklist::find_blocklist_entry_address(&address).0);
+    assert_eq!(
+        pda::tree_account(),
+        Pubkey::find_program_address(&[b"merkle_tree"], &zkcash::ID).0
+    );
+
+    // Both inputs and outputs use a distinct seed per position
+    let [nullifier0, nullifier1] = pda::nullifiers(&[[1u8; 32], [1u8; 32]]);
+    assert_ne!(nullifier0, nullifier1);
+    let [commitment0, commitment1] = pda::commitments(&[[1u8; 32], [1u8; 32]]);
+    assert_ne!(commitment0, commitment1);
+
+    // The first output of a transact lands at an even leaf index
+    assert_eq!(pda::commitment(&[1u8; 32], 6), commitment0);
+    assert_eq!(pda::commitment(&[1u8; 32], 7), commitment1);
+}
+
+#[test]
+fn transact_instruction_roundtrips() {
+    let proof = test_proof();
+    let ext_data = test_ext_data(-1_000_000);
+    let signer = Pubkey::new_unique();
+    let instruction = TransactBuilder::new(proof.clone(), ext_data.clone(), b"output1".to_vec(), b"output2".to_vec(), signer)
+        .instruction()
+        .unwrap();
+
+    assert_eq!(instruction.program_id, zkcash::ID);
+    assert_eq!(&instruction.data[..8], zkcash::instruction::Transact::DISCRIMINATOR);
+
+    let decoded = zkcash::instruction::Transact::deserialize(&mut &instruction.data[8..]).unwrap();
+    assert_eq!(decoded.proof.input_nullifiers, proof.input_nullifiers);
+    assert_eq!(decoded.ext_data.recipient, ext_data.recipient);
+    assert_eq!(decoded.encrypted_output1, b"output1");
+    assert_eq!(decoded.data(), instruction.data);
+
+    let [nullifier0, nullifier1] = pda::nullifiers(&proof.input_nullifiers);
+    assert!(instruction.accounts.iter().any(|meta| meta.pubkey == nullifier0 && meta.is_writable));
+    assert!(instruction.accounts.iter().any(|meta| meta.pubkey == nullifier1 && meta.is_writable));
+    assert!(instruction.accounts.iter().any(|meta| meta.pubkey == signer && meta.is_signer));
+}
+
+#[test]
+fn transact_instruction_follows_nullifier_mode() {
+    let proof = test_proof();
+    let signer = Pubkey::new_unique();
+    let builder = TransactBuilder::new(proof.clone(), test_ext_data(-1_000_000), vec![], vec![], signer);
+    let [nullifier0, nullifier1] = pda::nullifiers(&proof.input_nullifiers);
+
+    let accounts = builder.accounts();
+    assert_eq!(accounts.nullifier0, Some(nullifier0));
+    assert_eq!(accounts.nullifier1, Some(nullifier1));
+    assert_eq!(accounts.nullifier_tree, None);
+
+    let accounts = builder.nullifier_mode(NullifierMode::Compressed).accounts();
+    assert_eq!(accounts.nullifier0, None);
+    assert_eq!(accounts.nullifier1, None);
+    assert_eq!(accounts.nullifier_tree, Some(pda::nullifier_tree()));
+    assert_eq!(accounts.nullifier_proofs, Some(pda::nullifier_proofs(&signer)));
+}
+
+#[test]
+fn transact_instruction_lays_out_remaining_accounts() {
+    let mut ext_data = test_ext_data(-1_000_000);
+    let second_recipient = Pubkey::new_unique();
+    let callback_program = Pubkey::new_unique();
+    let callback_account = Pubkey::new_unique();
+    ext_data.recipients = Some(vec![
+        WithdrawalRecipient { recipient: ext_data.recipient, amount: 600_000 },
+        WithdrawalRecipient { recipient: second_recipient, amount: 400_000 },
+    ]);
+    ext_data.callback = Some(WithdrawCallback {
+        program_id: callback_program,
+        accounts: vec![callback_account],
+        data: vec![],
+    });
+
+    let builder = TransactBuilder::new(test_proof(), ext_data.clone(), vec![], vec![], Pubkey::new_unique());
+    let remaining_accounts: Vec<Pubkey> = builder.remaining_accounts().unwrap().iter().map(|meta| meta.pubkey).collect();
+    assert_eq!(
+        remaining_accounts,
+        vec![ext_data.recipient, second_recipient, callback_program, callback_account]
+    );
+
+    // Screening pools also get the blocklist entries of the recipients
+    let remaining_accounts: Vec<Pubkey> = builder
+        .screen_withdrawals(true)
+        .remaining_accounts()
+        .unwrap()
+        .iter()
+        .map(|meta| meta.pubkey)
+        .collect();
+    assert_eq!(
+        remaining_accounts,
+        vec![
+            ext_data.recipient,
+            second_recipient,
+            pda::blocklist_entry(&ext_data.recipient),
+            pda::blocklist_entry(&second_recipient),
+            callback_program,
+            callback_account,
+        ]
+    );
+}
+
+#[test]
+fn depositor_is_screened_instead_of_signer() {
+    let signer = Pubkey::new_unique();
+    let depositor = Pubkey::new_unique();
+    let accounts = TransactBuilder::new(test_proof(), test_ext_data(1_000_000), vec![], vec![], signer)
+        .depositor(depositor)
+        .accounts();
+
+    assert_eq!(accounts.depositor, Some(depositor));
+    assert_eq!(accounts.depositor_blocklist_entry, pda::blocklist_entry(&depositor));
+}
+
+#[test]
+fn withdrawals_above_the_delay_threshold_get_a_pending_withdrawal() {
+    let proof = test_proof();
+    let accounts = |ext_amount, threshold| {
+        TransactBuilder::new(proof.clone(), test_ext_data(ext_amount), vec![], vec![], Pubkey::new_unique())
+            .withdrawal_delay_threshold(threshold)
+            .accounts()
+    };
+
+    assert_eq!(
+        accounts(-1_000_001, 1_000_000).pending_withdrawal,
+        Some(pda::pending_withdrawal(&proof.input_nullifiers[0]))
+    );
+    assert_eq!(accounts(-1_000_000, 1_000_000).pending_withdrawal, None);
+    assert_eq!(accounts(-1_000_001, 0).pending_withdrawal, None);
+    assert_eq!(accounts(1_000_001, 1_000_000).pending_withdrawal, None);
+}
+
+#[test]
+fn referrer_gets_its_stats_account() {
+    let mut ext_data = test_ext_data(1_000_000);
+    let accounts = TransactBuilder::new(test_proof(), ext_data.clone(), vec![], vec![], Pubkey::new_unique()).accounts();
+    assert_eq!(accounts.referrer_stats, None);
+
+    ext_data.referrer = Some([7u8; 32]);
+    let accounts = TransactBuilder::new(test_proof(), ext_data, vec![], vec![], Pubkey::new_unique()).accounts();
+    assert_eq!(accounts.referrer_stats, Some(pda::referrer_stats(&[7u8; 32])));
+}
+
+#[test]
+fn swap_gets_the_swap_authority_and_program() {
+    let swap_program = Pubkey::new_unique();
+    let token_program = zkcash::swap::TOKEN_PROGRAM_ID;
+    let token_2022_program = zkcash::swap::TOKEN_2022_PROGRAM_ID;
+    let recipient_token_account = Pubkey::new_unique();
+    let mut ext_data = test_ext_data(-1_000_000);
+    ext_data.swap = Some(SwapParams {
+        output_mint: Pubkey::new_unique(),
+        recipient_token_account,
+        min_out: 1,
+        accounts: vec![pda::swap_authority(), recipient_token_account, token_program, token_2022_program],
+        data: vec![],
+    });
+
+    let builder = TransactBuilder::new(test_proof(), ext_data, vec![], vec![], Pubkey::new_unique());
+    assert_eq!(builder.remaining_accounts(), Err(ClientError::MissingSwapProgram));
+    assert_eq!(builder.instruction(), Err(ClientError::MissingSwapProgram));
+
+    let builder = builder.swap_program(swap_program);
+    assert_eq!(builder.accounts().swap_authority, Some(pda::swap_authority()));
+    assert_eq!(
+        pda::swap_authority(),
+        Pubkey::find_program_address(&[b"swap_authority"], &zkcash::ID).0
+    );
+
+    let remaining_accounts = builder.remaining_accounts().unwrap();
+    let keys: Vec<Pubkey> = remaining_accounts.iter().map(|meta| meta.pubkey).collect();
+    assert_eq!(
+        keys,
+        vec![swap_program, pda::swap_authority(), recipient_token_account, token_program, token_2022_program]
+    );
+    let writable: Vec<bool> = remaining_accounts.iter().map(|meta| meta.is_writable).collect();
+    assert_eq!(writable, vec![false, true, true, false, false]);
+}
+
+#[test]
+fn admin_instructions_are_signed_by_the_authority() {
+    let authority = Pubkey::new_unique();
+    let address = Pubkey::new_unique();
+    let instruction = admin::add_to_blocklist(authority, address);
+
+    assert_eq!(instruction.program_id, zkcash::ID);
+    assert_eq!(&instruction.data[..8], zkcash::instruction::AddToBlocklist::DISCRIMINATOR);
+    let decoded = zkcash::instruction::AddToBlocklist::deserialize(&mut &instruction.data[8..]).unwrap();
+    assert_eq!(decoded.address, address);
+    assert_eq!(instruction.accounts[0].pubkey, pda::blocklist_entry(&address));
+
+    let instruction = admin::update_deposit_rules(authority, 10, vec![10, 100]);
+    assert_eq!(
+        instruction.accounts.iter().map(|meta| (meta.pubkey, meta.is_signer, meta.is_writable)).collect::<Vec<_>>(),
+        vec![(pda::global_config(), false, true), (authority, true, false)]
+    );
+    let decoded = zkcash::instruction::UpdateDepositRules::deserialize(&mut &instruction.data[8..]).unwrap();
+    assert_eq!(decoded.denominations, vec![10, 100]);
+}
+
+#[test]
+fn admin_instructions_follow_nullifier_mode() {
+    let authority = Pubkey::new_unique();
+    let has_nullifier_tree =
+        |instruction: &Instruction| instruction.accounts.iter().any(|meta| meta.pubkey == pda::nullifier_tree());
+
+    assert!(!has_nullifier_tree(&admin::initialize(authority, NullifierMode::Pda)));
+    assert!(has_nullifier_tree(&admin::initialize(authority, NullifierMode::Dual)));
+    assert!(!has_nullifier_tree(&admin::set_nullifier_mode(authority, NullifierMode::Pda)));
+    assert!(has_nullifier_tree(&admin::set_nullifier_mode(authority, NullifierMode::Compressed)));
+
+    // Optional accounts that are left out still take their position, as the program id
+    let instruction = admin::set_nullifier_mode(authority, NullifierMode::Pda);
+    assert_eq!(instruction.accounts.len(), 4);
+    assert_eq!(instruction.accounts[2].pubkey, zkcash::ID);
+}
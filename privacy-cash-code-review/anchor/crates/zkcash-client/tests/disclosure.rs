@@ -0,0 +1,117 @@
+use anchor_lang::{AnchorSerialize, Discriminator};
+use ark_bn254::Fr;
+use rand::rngs::OsRng;
+use zkcash::disclosure::AuditorDisclosureEvent;
+use zkcash_client::disclosure::{
+    assemble_report, auditor_disclosure, decode_event, decrypt_output, encrypt_output, DisclosedOutput,
+    DisclosureError, ViewingKey,
+};
+use zkcash_client::{Keypair, Utxo};
+
+fn outputs() -> [Utxo; 2] {
+    let keypair = Keypair::new(Fr::from(42u64)).unwrap();
+    [
+        Utxo::new(700, keypair, Fr::from(7u64)),
+        Utxo::new(0, keypair, Fr::from(8u64)),
+    ]
+}
+
+fn event(viewing_key: &ViewingKey, outputs: &[Utxo; 2], ext_amount: i64, fee: u64) -> AuditorDisclosureEvent {
+    let disclosure = auditor_disclosure(&mut OsRng, &viewing_key.public_key(), [&outputs[0], &outputs[1]]).unwrap();
+    AuditorDisclosureEvent {
+        viewing_key: disclosure.viewing_key,
+        commitments: [outputs[0].commitment_bytes().unwrap(), outputs[1].commitment_bytes().unwrap()],
+        indices: [0, 1],
+        ext_amount,
+        fee,
+        encrypted_outputs: disclosure.encrypted_outputs,
+    }
+}
+
+#[test]
+fn test_roundtrip_matches_the_commitment() {
+    let viewing_key = ViewingKey::generate(&mut OsRng);
+    let [output, _] = outputs();
+    let disclosed = DisclosedOutput::from_utxo(&output);
+    let commitment = output.commitment_bytes().unwrap();
+    assert_eq!(disclosed.commitment_bytes().unwrap(), commitment);
+
+    let encrypted = encrypt_output(&mut OsRng, &viewing_key.public_key(), &disclosed).unwrap();
+    assert_eq!(decrypt_output(&viewing_key, &commitment, &encrypted).unwrap(), disclosed);
+}
+
+#[test]
+fn test_decryption_requires_the_viewing_key_and_commitment() {
+    let viewing_key = ViewingKey::generate(&mut OsRng);
+    let [output, other] = outputs();
+    let disclosed = DisclosedOutput::from_utxo(&output);
+    let commitment = output.commitment_bytes().unwrap();
+    let encrypted = encrypt_output(&mut OsRng, &viewing_key.public_key(), &disclosed).unwrap();
+
+    let other_key = ViewingKey::generate(&mut OsRng);
+    assert_eq!(
+        decrypt_output(&other_key, &commitment, &encrypted).unwrap_err(),
+        DisclosureError::DecryptionFailed
+    );
+
+    // The commitment is the AAD, so a copy can't be passed off as another output's
+    assert_eq!(
+        decrypt_output(&viewing_key, &other.commitment_bytes().unwrap(), &encrypted).unwrap_err(),
+        DisclosureError::DecryptionFailed
+    );
+
+    let mut tampered = encrypted.clone();
+    *tampered.last_mut().unwrap() ^= 1;
+    assert_eq!(
+        decrypt_output(&viewing_key, &commitment, &tampered).unwrap_err(),
+        DisclosureError::DecryptionFailed
+    );
+}
+
+#[test]
+fn test_report_totals_events_for_the_key() {
+    let viewing_key = ViewingKey::generate(&mut OsRng);
+    let other_key = ViewingKey::generate(&mut OsRng);
+    let outputs = outputs();
+
+    let events = [
+        event(&viewing_key, &outputs, 1_000, 10),
+        event(&other_key, &outputs, 5_000, 50),
+        event(&viewing_key, &outputs, -300, 5),
+    ];
+    let report = assemble_report(&viewing_key, &events).unwrap();
+
+    assert_eq!(report.transactions.len(), 2);
+    assert_eq!(report.total_deposited, 1_000);
+    assert_eq!(report.total_withdrawn, 300);
+    assert_eq!(report.total_fees, 15);
+    assert_eq!(report.transactions[1].outputs[0], DisclosedOutput::from_utxo(&outputs[0]));
+
+    let other_report = assemble_report(&other_key, &events).unwrap();
+    assert_eq!(other_report.transactions.len(), 1);
+    assert_eq!(other_report.total_deposited, 5_000);
+}
+
+#[test]
+fn test_report_fails_on_swapped_outputs() {
+    let viewing_key = ViewingKey::generate(&mut OsRng);
+    let outputs = outputs();
+
+    let mut swapped = event(&viewing_key, &outputs, 1_000, 10);
+    swapped.encrypted_outputs.swap(0, 1);
+    assert!(assemble_report(&viewing_key, &[swapped]).is_err());
+}
+
+#[test]
+fn test_decode_event() {
+    let viewing_key = ViewingKey::generate(&mut OsRng);
+    let event = event(&viewing_key, &outputs(), 1_000, 10);
+
+    let mut data = AuditorDisclosureEvent::DISCRIMINATOR.to_vec();
+    event.serialize(&mut data).unwrap();
+    let decoded = decode_event(&data).unwrap();
+    assert_eq!(decoded.viewing_key, event.viewing_key);
+    assert_eq!(decoded.encrypted_outputs, event.encrypted_outputs);
+
+    assert!(decode_event(&data[8..]).is_none());
+}
@@ -0,0 +1,95 @@
+use anchor_lang::prelude::{Pubkey, Rent};
+use zkcash::nullifier_tree::NullifierMode;
+use zkcash::GlobalConfig;
+use zkcash_client::fee::{transact_rent, TransactPdas};
+use zkcash_client::quote_fee;
+
+const NO_PDAS: TransactPdas = TransactPdas { pending_withdrawal: false, referrer_stats: false };
+
+fn config(require_rent_in_fee: bool) -> GlobalConfig {
+    GlobalConfig {
+        authority: Pubkey::new_unique(),
+        deposit_fee_rate: 0,
+        withdrawal_fee_rate: 25,
+        fee_error_margin: 500,
+        bump: 255,
+        version: GlobalConfig::VERSION,
+        screen_withdrawals: false,
+        min_encrypted_output_len: 0,
+        max_encrypted_output_len: 1024,
+        require_output_envelope: false,
+        commitment_retention_leaves: 0,
+        commitment_retention_slots: 0,
+        min_deposit_amount: 0,
+        deposit_denominations: [0; 8],
+        require_rent_in_fee,
+        nullifier_mode: NullifierMode::Pda,
+        withdrawal_delay_threshold: 0,
+        withdrawal_delay_slots: 0,
+        emergency: false,
+        swap_program: Pubkey::default(),
+        transfer_fee: 0,
+        max_transfers_per_slot: 0,
+    }
+}
+
+#[test]
+fn test_quote_fee_without_rent() {
+    let rent = Rent::default();
+    assert_eq!(quote_fee(&config(false), &rent, -1_000_000_000, false, 100, 100, NO_PDAS).unwrap(), 2_375_000);
+    assert_eq!(quote_fee(&config(false), &rent, 1_000_000_000, true, 100, 100, NO_PDAS).unwrap(), 0);
+}
+
+#[test]
+fn test_quote_fee_for_internal_transfers() {
+    let rent = Rent::default();
+    let mut config = config(false);
+    assert_eq!(quote_fee(&config, &rent, 0, false, 100, 100, NO_PDAS).unwrap(), 0);
+
+    config.transfer_fee = 5_000;
+    assert_eq!(quote_fee(&config, &rent, 0, false, 100, 100, NO_PDAS).unwrap(), 5_000);
+    // Deposits and withdrawals are still charged by rate
+    assert_eq!(quote_fee(&config, &rent, -1_000_000_000, false, 100, 100, NO_PDAS).unwrap(), 2_375_000);
+
+    config.require_rent_in_fee = true;
+    let rent_lamports = transact_rent(&rent, NullifierMode::Pda, 100, 100, NO_PDAS).unwrap();
+    assert_eq!(quote_fee(&config, &rent, 0, false, 100, 100, NO_PDAS).unwrap(), 5_000 + rent_lamports);
+}
+
+#[test]
+fn test_quote_fee_with_rent() {
+    let rent = Rent::default();
+    let rent_lamports = transact_rent(&rent, NullifierMode::Pda, 100, 120, NO_PDAS).unwrap();
+
+    assert_eq!(
+        quote_fee(&config(true), &rent, -1_000_000_000, false, 100, 120, NO_PDAS).unwrap(),
+        2_375_000 + rent_lamports
+    );
+    // A deposit relayed on behalf of a calling program still reimburses the signer
+    assert_eq!(quote_fee(&config(true), &rent, 1_000_000_000, false, 100, 120, NO_PDAS).unwrap(), rent_lamports);
+    // The signer of a self-funded deposit pays its own rent
+    assert_eq!(quote_fee(&config(true), &rent, 1_000_000_000, true, 100, 120, NO_PDAS).unwrap(), 0);
+}
+
+#[test]
+fn test_quote_fee_with_optional_pdas() {
+    let rent = Rent::default();
+    let rent_lamports = transact_rent(&rent, NullifierMode::Pda, 100, 100, NO_PDAS).unwrap();
+
+    // A delayed withdrawal also creates its pending withdrawal
+    let delayed = TransactPdas { pending_withdrawal: true, ..NO_PDAS };
+    assert_eq!(
+        quote_fee(&config(true), &rent, -1_000_000_000, false, 100, 100, delayed).unwrap(),
+        2_375_000 + rent_lamports + rent.minimum_balance(zkcash::PendingWithdrawal::SPACE)
+    );
+
+    // The first relayed deposit of a referrer creates its stats
+    let referred = TransactPdas { referrer_stats: true, ..NO_PDAS };
+    assert_eq!(
+        quote_fee(&config(true), &rent, 1_000_000_000, false, 100, 100, referred).unwrap(),
+        rent_lamports + rent.minimum_balance(zkcash::ReferrerStats::SPACE)
+    );
+
+    // Nothing to reimburse without the rent requirement
+    assert_eq!(quote_fee(&config(false), &rent, 1_000_000_000, false, 100, 100, referred).unwrap(), 0);
+}
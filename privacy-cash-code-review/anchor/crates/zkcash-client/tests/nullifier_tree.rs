@@ -0,0 +1,63 @@
+use std::mem::MaybeUninit;
+
+use light_hasher::Poseidon;
+use zkcash::nullifier_tree::NullifierTree;
+use zkcash::NullifierTreeAccount;
+use zkcash_client::{IndexedMerkleTree, TreeError};
+
+fn nullifier_tree_account() -> NullifierTreeAccount {
+    let mut uninit: MaybeUninit<NullifierTreeAccount> = MaybeUninit::uninit();
+    unsafe {
+        std::ptr::write_bytes(uninit.as_mut_ptr(), 0, 1);
+        let mut account = uninit.assume_init();
+        NullifierTree::initialize::<Poseidon>(&mut account, 0).unwrap();
+        account
+    }
+}
+
+fn value(n: u64) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    bytes[24..].copy_from_slice(&n.to_be_bytes());
+    bytes
+}
+
+#[test]
+fn test_proofs_are_accepted_by_the_program() {
+    let mut account = nullifier_tree_account();
+    let mut tree = IndexedMerkleTree::new().unwrap();
+    tree.verify_against(&account).unwrap();
+
+    for n in [50, 10, 90, 30, 70, 20, 80, 40, 60, 11, 12, 13] {
+        let proof = tree.insert(value(n)).unwrap();
+        NullifierTree::insert::<Poseidon>(value(n), &proof, &mut account).unwrap();
+        tree.verify_against(&account).unwrap();
+    }
+}
+
+#[test]
+fn test_spent_values_have_no_proof() {
+    let mut tree = IndexedMerkleTree::from_values([value(5), value(3)]).unwrap();
+    assert!(tree.contains(&value(5)));
+    assert_eq!(tree.insert_proof(value(5)).unwrap_err(), TreeError::ValueExists);
+    assert_eq!(tree.insert(value(3)).unwrap_err(), TreeError::ValueExists);
+    // 0 is the sentinel
+    assert_eq!(tree.insert_proof(value(0)).unwrap_err(), TreeError::ValueExists);
+
+    let proof = tree.insert_proof(value(4)).unwrap();
+    assert_eq!(proof.low_leaf.value, value(3));
+    assert_eq!(proof.low_leaf.next_value, value(5));
+}
+
+#[test]
+fn test_replaying_inserts_reproduces_the_tree() {
+    let values = [value(8), value(2), value(6)];
+    let mut tree = IndexedMerkleTree::new().unwrap();
+    for value in values {
+        tree.insert(value).unwrap();
+    }
+
+    let replayed = IndexedMerkleTree::from_values(values).unwrap();
+    assert_eq!(replayed.root(), tree.root());
+    assert_eq!(replayed.len(), 4);
+    assert_eq!(replayed.leaf(0).unwrap().next_value, value(2));
+}
@@ -0,0 +1,589 @@
+//! Authority checks of the admin instructions, nullifier backfilling, pending withdrawal payouts,
+//! and `transact` checks that fail before the proof is verified, against the compiled program in
+//! LiteSVM. Proofs here are never verified, so unlike
+//! `tests/transact.rs` no circuit artifacts are needed.
+//!
+//! Needs the program built for localnet (`anchor build -- --features localnet`, so that any key
+//! can initialize), at `target/deploy/zkcash.so` or `ZKCASH_PROGRAM_SO`. The tests are ignored
+//! by default, run them with `cargo test --test program -- --ignored` after building. Only
+//! `test_litesvm_smoke` runs without the program.
+// LiteSVM's `TransactionResult` carries the whole transaction metadata in its error
+#![allow(clippy::result_large_err)]
+
+use std::path::PathBuf;
+
+use anchor_lang::prelude::{Clock, Pubkey};
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::{AccountDeserialize, AccountSerialize, InstructionData, ToAccountMetas};
+use litesvm::types::{FailedTransactionMetadata, TransactionResult};
+use litesvm::LiteSVM;
+use solana_sdk::account::Account;
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::{Transaction, TransactionError};
+use zkcash::nullifier_tree::{NullifierInsertProof, NullifierMode, NULLIFIER_TREE_HEIGHT};
+use zkcash::utils::{commitment_seed_prefix, SOL_ADDRESS};
+use zkcash::{
+    CommitmentAccount, ErrorCode, MerkleTreeAccount, NullifierAccount, NullifierTreeAccount, PendingWithdrawal,
+    TreeTokenAccount,
+};
+use zkcash_client::{admin, instructions, pda, ExtData, IndexedMerkleTree, Proof, TransactBuilder};
+
+const LAMPORTS_PER_SOL: u64 = 1_000_000_000;
+const ENCRYPTED_OUTPUT: [u8; 64] = [1u8; 64];
+
+fn program_path() -> PathBuf {
+    std::env::var("ZKCASH_PROGRAM_SO")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../target/deploy/zkcash.so"))
+}
+
+struct Harness {
+    svm: LiteSVM,
+    authority: Keypair,
+}
+
+impl Harness {
+    fn setup() -> Self {
+        let program_path = program_path();
+        assert!(program_path.exists(), "need {}", program_path.display());
+        let mut svm = LiteSVM::new();
+        svm.add_program_from_file(zkcash::ID, &program_path).unwrap();
+
+        let authority = Keypair::new();
+        svm.airdrop(&authority.pubkey(), 100 * LAMPORTS_PER_SOL).unwrap();
+        let mut harness = Harness { svm, authority };
+        let authority = harness.authority.insecure_clone();
+        harness
+            .send(admin::initialize(authority.pubkey(), NullifierMode::Pda), &authority)
+            .unwrap();
+        harness
+    }
+
+    fn funded_keypair(&mut self) -> Keypair {
+        let keypair = Keypair::new();
+        self.svm.airdrop(&keypair.pubkey(), 10 * LAMPORTS_PER_SOL).unwrap();
+        keypair
+    }
+
+    fn send(&mut self, instruction: Instruction, signer: &Keypair) -> TransactionResult {
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&signer.pubkey()),
+            &[signer],
+            self.svm.latest_blockhash(),
+        );
+        let result = self.svm.send_transaction(transaction);
+        // Identical retries must not be rejected as already processed
+        self.svm.expire_blockhash();
+        result
+    }
+
+    fn send_as_authority(&mut self, instruction: Instruction) -> TransactionResult {
+        let authority = self.authority.insecure_clone();
+        self.send(instruction, &authority)
+    }
+
+    fn tree_account(&self) -> MerkleTreeAccount {
+        let account = self.svm.get_account(&pda::tree_account()).unwrap();
+        bytemuck::pod_read_unaligned(&account.data[8..8 + std::mem::size_of::<MerkleTreeAccount>()])
+    }
+
+    /// Moves the tree's `next_index`, as if leaves had been appended.
+    fn set_next_index(&mut self, next_index: u64) {
+        let mut account = self.svm.get_account(&pda::tree_account()).unwrap();
+        let mut tree_account = self.tree_account();
+        tree_account.next_index = next_index;
+        account.data[8..8 + std::mem::size_of::<MerkleTreeAccount>()].copy_from_slice(bytemuck::bytes_of(&tree_account));
+        self.svm.set_account(pda::tree_account(), account).unwrap();
+    }
+
+    fn nullifier_tree(&self) -> NullifierTreeAccount {
+        let account = self.svm.get_account(&pda::nullifier_tree()).unwrap();
+        bytemuck::pod_read_unaligned(&account.data[8..8 + std::mem::size_of::<NullifierTreeAccount>()])
+    }
+
+    /// Writes the nullifier PDA `transact` creates under Pda when `nullifier` is spent at input
+    /// `position`, and returns its address.
+    fn set_nullifier(&mut self, nullifier: [u8; 32], position: usize) -> Pubkey {
+        let seed = [pda::NULLIFIER0_SEED, pda::NULLIFIER1_SEED][position];
+        let (address, bump) = Pubkey::find_program_address(&[seed, &nullifier], &zkcash::ID);
+        let mut data = Vec::new();
+        NullifierAccount { bump }.try_serialize(&mut data).unwrap();
+        let account = Account {
+            lamports: self.svm.minimum_balance_for_rent_exemption(data.len()),
+            data,
+            owner: zkcash::ID,
+            executable: false,
+            rent_epoch: 0,
+        };
+        self.svm.set_account(address, account).unwrap();
+        address
+    }
+
+    fn tree_token_account(&self) -> TreeTokenAccount {
+        let account = self.svm.get_account(&pda::tree_token_account()).unwrap();
+        TreeTokenAccount::try_deserialize(&mut &account.data[..]).unwrap()
+    }
+
+    /// Sets the pool's balance and the lamports it has reserved for pending withdrawals.
+    fn set_pool(&mut self, lamports: u64, reserved_lamports: u64) {
+        let mut account = self.svm.get_account(&pda::tree_token_account()).unwrap();
+        let mut tree_token_account = self.tree_token_account();
+        tree_token_account.reserved_lamports = reserved_lamports;
+        let mut data = Vec::new();
+        tree_token_account.try_serialize(&mut data).unwrap();
+        account.data[..data.len()].copy_from_slice(&data);
+        account.lamports = lamports;
+        self.svm.set_account(pda::tree_token_account(), account).unwrap();
+    }
+
+    /// Writes the pending withdrawal a delayed `transact` with first input nullifier `nullifier`
+    /// would have created, releasable from the current slot.
+    fn set_pending_withdrawal(&mut self, nullifier: [u8; 32], recipient: Pubkey, amount: u64, payer: Pubkey) {
+        let (address, bump) = Pubkey::find_program_address(&[pda::PENDING_WITHDRAWAL_SEED, &nullifier], &zkcash::ID);
+        let mut data = Vec::new();
+        PendingWithdrawal {
+            nullifier,
+            recipient,
+            amount,
+            payer,
+            request_slot: self.slot(),
+            release_slot: self.slot(),
+            bump,
+        }
+        .try_serialize(&mut data)
+        .unwrap();
+        let account = Account {
+            lamports: self.svm.minimum_balance_for_rent_exemption(data.len()),
+            data,
+            owner: zkcash::ID,
+            executable: false,
+            rent_epoch: 0,
+        };
+        self.svm.set_account(address, account).unwrap();
+    }
+
+    fn slot(&self) -> u64 {
+        self.svm.get_sysvar::<Clock>().slot
+    }
+
+    /// Writes a commitment account at `address`, as `transact` would have created it at its PDA
+    /// for leaf `index` in the current slot.
+    fn set_commitment(&mut self, address: Pubkey, commitment: [u8; 32], index: u64, payer: Pubkey) {
+        let (_, bump) = Pubkey::find_program_address(&[commitment_seed_prefix(index), &commitment], &zkcash::ID);
+        let mut data = Vec::new();
+        CommitmentAccount {
+            commitment,
+            encrypted_output: ENCRYPTED_OUTPUT.to_vec(),
+            index,
+            bump,
+            payer,
+            slot: self.slot(),
+        }
+        .try_serialize(&mut data)
+        .unwrap();
+        let account = Account {
+            lamports: self.svm.minimum_balance_for_rent_exemption(data.len()),
+            data,
+            owner: zkcash::ID,
+            executable: false,
+            rent_epoch: 0,
+        };
+        self.svm.set_account(address, account).unwrap();
+    }
+
+    /// A transact against the current root whose proof is garbage, for checks that precede
+    /// proof verification. Nullifiers are fresh on every call.
+    fn unverified_transact(&self, ext_amount: i64, signer: &Keypair) -> Instruction {
+        let proof = Proof {
+            proof_a: [0u8; 64],
+            proof_b: [0u8; 128],
+            proof_c: [0u8; 64],
+            root: self.tree_account().root,
+            public_amount: [0u8; 32],
+            ext_data_hash: [0u8; 32],
+            input_nullifiers: [Pubkey::new_unique().to_bytes(), Pubkey::new_unique().to_bytes()],
+            output_commitments: [Pubkey::new_unique().to_bytes(), Pubkey::new_unique().to_bytes()],
+        };
+        let ext_data = ExtData {
+            recipient: Pubkey::new_unique(),
+            ext_amount,
+            fee: 0,
+            fee_recipient: Pubkey::new_unique(),
+            mint_address: SOL_ADDRESS,
+            callback: None,
+            recipients: None,
+            auditor: None,
+            memo: None,
+            referrer: None,
+            swap: None,
+        };
+        TransactBuilder::new(
+            proof,
+            ext_data,
+            ENCRYPTED_OUTPUT.to_vec(),
+            ENCRYPTED_OUTPUT.to_vec(),
+            signer.pubkey(),
+        )
+        .instruction()
+        .unwrap()
+    }
+}
+
+fn assert_program_error(result: TransactionResult, error: ErrorCode) {
+    let FailedTransactionMetadata { err, meta } = result.expect_err("transaction should fail");
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(0, InstructionError::Custom(u32::from(error))),
+        "logs: {:#?}",
+        meta.logs
+    );
+}
+
+#[test]
+fn test_litesvm_smoke() {
+    let mut svm = LiteSVM::new();
+    let payer = Keypair::new();
+    svm.airdrop(&payer.pubkey(), LAMPORTS_PER_SOL).unwrap();
+    assert_eq!(svm.get_balance(&payer.pubkey()), Some(LAMPORTS_PER_SOL));
+
+    // Without the program loaded, its instructions are rejected before touching any account
+    let transaction = Transaction::new_signed_with_payer(
+        &[admin::initialize(payer.pubkey(), NullifierMode::Pda)],
+        Some(&payer.pubkey()),
+        &[&payer],
+        svm.latest_blockhash(),
+    );
+    let FailedTransactionMetadata { err, .. } = svm.send_transaction(transaction).unwrap_err();
+    assert_eq!(err, TransactionError::InvalidProgramForExecution);
+    assert_eq!(svm.get_account(&pda::tree_account()), None);
+}
+
+#[test]
+#[ignore = "needs zkcash.so"]
+fn test_blocklist_is_authority_only() {
+    let mut harness = Harness::setup();
+    let stranger = harness.funded_keypair();
+    let address = Pubkey::new_unique();
+
+    assert_program_error(
+        harness.send(admin::add_to_blocklist(stranger.pubkey(), address), &stranger),
+        ErrorCode::Unauthorized,
+    );
+    harness.send_as_authority(admin::add_to_blocklist(harness.authority.pubkey(), address)).unwrap();
+    assert!(harness.svm.get_account(&pda::blocklist_entry(&address)).is_some());
+
+    assert_program_error(
+        harness.send(admin::remove_from_blocklist(stranger.pubkey(), address), &stranger),
+        ErrorCode::Unauthorized,
+    );
+    harness.send_as_authority(admin::remove_from_blocklist(harness.authority.pubkey(), address)).unwrap();
+    let entry = harness.svm.get_account(&pda::blocklist_entry(&address));
+    assert!(entry.is_none_or(|entry| entry.data.is_empty()));
+
+    assert_program_error(
+        harness.send(admin::set_withdrawal_screening(stranger.pubkey(), true), &stranger),
+        ErrorCode::Unauthorized,
+    );
+}
+
+#[test]
+#[ignore = "needs zkcash.so"]
+fn test_blocked_depositor_is_rejected() {
+    let mut harness = Harness::setup();
+    let depositor = harness.funded_keypair();
+    harness.send_as_authority(admin::add_to_blocklist(harness.authority.pubkey(), depositor.pubkey())).unwrap();
+
+    let deposit = harness.unverified_transact(LAMPORTS_PER_SOL as i64, &depositor);
+    assert_program_error(harness.send(deposit, &depositor), ErrorCode::AddressBlocked);
+
+    // Once unlisted, the same deposit gets past screening and fails on its garbage proof instead
+    harness.send_as_authority(admin::remove_from_blocklist(harness.authority.pubkey(), depositor.pubkey())).unwrap();
+    let deposit = harness.unverified_transact(LAMPORTS_PER_SOL as i64, &depositor);
+    let err = harness.send(deposit, &depositor).expect_err("garbage proof should fail").err;
+    assert_ne!(
+        err,
+        TransactionError::InstructionError(0, InstructionError::Custom(u32::from(ErrorCode::AddressBlocked)))
+    );
+}
+
+#[test]
+#[ignore = "needs zkcash.so"]
+fn test_reclaim_commitment_leaf_retention_boundary() {
+    let mut harness = Harness::setup();
+    harness.send_as_authority(admin::update_commitment_retention(harness.authority.pubkey(), 100, 0)).unwrap();
+    let caller = harness.funded_keypair();
+    let payer = Pubkey::new_unique();
+    let commitment = [1u8; 32];
+    harness.set_commitment(pda::commitment(&commitment, 10), commitment, 10, payer);
+    let rent = harness.svm.get_balance(&pda::commitment(&commitment, 10)).unwrap();
+
+    // 99 leaves appended after leaf 10
+    harness.set_next_index(110);
+    assert_program_error(
+        harness.send(instructions::reclaim_commitment(&commitment, 10, payer), &caller),
+        ErrorCode::CommitmentNotReclaimable,
+    );
+
+    // The 100th makes it reclaimable, refunding the rent to the recorded payer
+    harness.set_next_index(111);
+    harness.send(instructions::reclaim_commitment(&commitment, 10, payer), &caller).unwrap();
+    assert_eq!(harness.svm.get_balance(&payer), Some(rent));
+}
+
+#[test]
+#[ignore = "needs zkcash.so"]
+fn test_reclaim_commitment_slot_retention_boundary() {
+    let mut harness = Harness::setup();
+    harness.send_as_authority(admin::update_commitment_retention(harness.authority.pubkey(), 0, 50)).unwrap();
+    let caller = harness.funded_keypair();
+    let payer = Pubkey::new_unique();
+    let commitment = [2u8; 32];
+    let created_slot = harness.slot();
+    harness.set_commitment(pda::commitment(&commitment, 11), commitment, 11, payer);
+    harness.set_next_index(u64::from(u32::MAX));
+
+    // Leaves don't count while only slot retention is set
+    harness.svm.warp_to_slot(created_slot + 49);
+    assert_program_error(
+        harness.send(instructions::reclaim_commitment(&commitment, 11, payer), &caller),
+        ErrorCode::CommitmentNotReclaimable,
+    );
+
+    harness.svm.warp_to_slot(created_slot + 50);
+    harness.send(instructions::reclaim_commitment(&commitment, 11, payer), &caller).unwrap();
+    assert!(harness.svm.get_balance(&payer).unwrap() > 0);
+}
+
+#[test]
+#[ignore = "needs zkcash.so"]
+fn test_reclaim_commitment_requires_its_pda() {
+    let mut harness = Harness::setup();
+    harness.send_as_authority(admin::update_commitment_retention(harness.authority.pubkey(), 1, 0)).unwrap();
+    harness.set_next_index(100);
+    let caller = harness.funded_keypair();
+    let payer = Pubkey::new_unique();
+    let commitment = [3u8; 32];
+    let reclaim_at = |address: Pubkey| Instruction {
+        program_id: zkcash::ID,
+        accounts: zkcash::accounts::ReclaimCommitment {
+            commitment: address,
+            tree_account: pda::tree_account(),
+            global_config: pda::global_config(),
+            payer,
+        }
+        .to_account_metas(None),
+        data: zkcash::instruction::ReclaimCommitment {}.data(),
+    };
+    let constraint_seeds = TransactionError::InstructionError(
+        0,
+        InstructionError::Custom(anchor_lang::error::ErrorCode::ConstraintSeeds as u32),
+    );
+
+    // A valid commitment account at an address that is not its PDA
+    let address = Pubkey::new_unique();
+    harness.set_commitment(address, commitment, 10, payer);
+    assert_eq!(harness.send(reclaim_at(address), &caller).unwrap_err().err, constraint_seeds);
+
+    // The PDA of the other output position
+    let address = pda::commitment(&commitment, 11);
+    harness.set_commitment(address, commitment, 10, payer);
+    assert_eq!(harness.send(reclaim_at(address), &caller).unwrap_err().err, constraint_seeds);
+
+    let address = pda::commitment(&commitment, 10);
+    harness.set_commitment(address, commitment, 10, payer);
+    harness.send(reclaim_at(address), &caller).unwrap();
+}
+
+/**
+ * Insert proof of an already spent nullifier, opening its predecessor, the only leaf it falls
+ * after. The program rejects it before checking the opening, so the siblings are left zero.
+ */
+fn spent_low_leaf_proof(tree: &IndexedMerkleTree, nullifier: &[u8; 32]) -> NullifierInsertProof {
+    let low_leaf_index = (0..tree.len())
+        .find(|&index| tree.leaf(index).unwrap().next_value == *nullifier)
+        .expect("nullifier is in the tree");
+    NullifierInsertProof {
+        low_leaf: tree.leaf(low_leaf_index).unwrap(),
+        low_leaf_index,
+        siblings: [[0u8; 32]; NULLIFIER_TREE_HEIGHT],
+    }
+}
+
+fn nullifier(n: u64) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    bytes[24..].copy_from_slice(&n.to_be_bytes());
+    bytes
+}
+
+#[test]
+#[ignore = "needs zkcash.so"]
+fn test_backfill_then_switch_to_compressed() {
+    let mut harness = Harness::setup();
+    // Two transacts under Pda: four leaves, and a nullifier PDA for each
+    harness.set_next_index(4);
+    let nullifiers = [nullifier(40), nullifier(10), nullifier(30), nullifier(20)];
+    let nullifier_accounts: Vec<Pubkey> = nullifiers
+        .iter()
+        .enumerate()
+        .map(|(i, nullifier)| harness.set_nullifier(*nullifier, i % 2))
+        .collect();
+
+    let authority = harness.authority.pubkey();
+    harness.send_as_authority(admin::initialize_nullifier_tree(authority)).unwrap();
+    harness.send_as_authority(admin::set_nullifier_mode(authority, NullifierMode::Dual)).unwrap();
+    assert_eq!(harness.nullifier_tree().legacy_nullifiers, 4);
+
+    let backfiller = harness.funded_keypair();
+    let mut tree = IndexedMerkleTree::new().unwrap();
+    for (nullifier, nullifier_account) in nullifiers.iter().zip(&nullifier_accounts) {
+        assert_program_error(
+            harness.send_as_authority(admin::set_nullifier_mode(authority, NullifierMode::Compressed)),
+            ErrorCode::NullifierBackfillIncomplete,
+        );
+        let insert_proof = tree.insert(*nullifier).unwrap();
+        let instruction =
+            instructions::backfill_nullifier(*nullifier_account, *nullifier, insert_proof, backfiller.pubkey());
+        harness.send(instruction, &backfiller).unwrap();
+    }
+    tree.verify_against(&harness.nullifier_tree()).unwrap();
+    assert_eq!(harness.nullifier_tree().backfilled_nullifiers, 4);
+
+    // A nullifier can't be counted twice
+    let instruction = instructions::backfill_nullifier(
+        nullifier_accounts[0],
+        nullifiers[0],
+        spent_low_leaf_proof(&tree, &nullifiers[0]),
+        backfiller.pubkey(),
+    );
+    assert_program_error(harness.send(instruction, &backfiller), ErrorCode::NullifierAlreadySpent);
+
+    harness.send_as_authority(admin::set_nullifier_mode(authority, NullifierMode::Compressed)).unwrap();
+}
+
+#[test]
+#[ignore = "needs zkcash.so"]
+fn test_backfill_requires_a_nullifier_pda() {
+    let mut harness = Harness::setup();
+    harness.set_next_index(2);
+    let authority = harness.authority.pubkey();
+    harness.send_as_authority(admin::initialize_nullifier_tree(authority)).unwrap();
+
+    // A nullifier account, but of another nullifier
+    let other_account = harness.set_nullifier(nullifier(7), 0);
+    let insert_proof = IndexedMerkleTree::new().unwrap().insert(nullifier(8)).unwrap();
+    assert_program_error(
+        harness.send_as_authority(instructions::backfill_nullifier(other_account, nullifier(8), insert_proof, authority)),
+        ErrorCode::InvalidNullifierProof,
+    );
+    assert_eq!(harness.nullifier_tree().backfilled_nullifiers, 0);
+}
+
+#[test]
+#[ignore = "needs zkcash.so"]
+fn test_finalize_fails_once_the_pool_is_drained() {
+    let mut harness = Harness::setup();
+    let payer = harness.funded_keypair();
+    let recipient = Pubkey::new_unique();
+    let nullifier = nullifier(1);
+    let amount = LAMPORTS_PER_SOL;
+    harness.set_pending_withdrawal(nullifier, recipient, amount, payer.pubkey());
+    let rent_exempt_minimum = harness.svm.minimum_balance_for_rent_exemption(TreeTokenAccount::SPACE);
+
+    // One lamport of the reserved amount is gone
+    harness.set_pool(rent_exempt_minimum + amount - 1, amount);
+    assert_program_error(
+        harness.send(instructions::finalize_withdrawal(&nullifier, recipient, payer.pubkey()), &payer),
+        ErrorCode::InsufficientFundsForWithdrawal,
+    );
+    assert_eq!(harness.tree_token_account().reserved_lamports, amount);
+
+    harness.set_pool(rent_exempt_minimum + amount, amount);
+    harness.send(instructions::finalize_withdrawal(&nullifier, recipient, payer.pubkey()), &payer).unwrap();
+    assert_eq!(harness.svm.get_balance(&recipient), Some(amount));
+    assert_eq!(harness.svm.get_balance(&pda::tree_token_account()), Some(rent_exempt_minimum));
+    assert_eq!(harness.tree_token_account().reserved_lamports, 0);
+}
+
+#[test]
+#[ignore = "needs zkcash.so"]
+fn test_finalize_leaves_other_reservations() {
+    let mut harness = Harness::setup();
+    let payer = harness.funded_keypair();
+    let recipient = Pubkey::new_unique();
+    let nullifier = nullifier(1);
+    let amount = LAMPORTS_PER_SOL;
+    harness.set_pending_withdrawal(nullifier, recipient, amount, payer.pubkey());
+    let rent_exempt_minimum = harness.svm.minimum_balance_for_rent_exemption(TreeTokenAccount::SPACE);
+
+    // Another pending withdrawal reserved as much, but the pool only holds one of them
+    harness.set_pool(rent_exempt_minimum + amount, 2 * amount);
+    assert_program_error(
+        harness.send(instructions::finalize_withdrawal(&nullifier, recipient, payer.pubkey()), &payer),
+        ErrorCode::InsufficientFundsForWithdrawal,
+    );
+
+    harness.set_pool(rent_exempt_minimum + 2 * amount, 2 * amount);
+    harness.send(instructions::finalize_withdrawal(&nullifier, recipient, payer.pubkey()), &payer).unwrap();
+    assert_eq!(harness.tree_token_account().reserved_lamports, amount);
+}
+
+#[test]
+#[ignore = "needs zkcash.so"]
+fn test_veto_moves_the_reservation_to_the_vetoed_funds() {
+    let mut harness = Harness::setup();
+    let payer = harness.funded_keypair();
+    let recipient = Pubkey::new_unique();
+    let nullifier = nullifier(1);
+    let amount = LAMPORTS_PER_SOL;
+    harness.set_pending_withdrawal(nullifier, recipient, amount, payer.pubkey());
+    let rent_exempt_minimum = harness.svm.minimum_balance_for_rent_exemption(TreeTokenAccount::SPACE);
+    harness.set_pool(rent_exempt_minimum + amount, amount);
+
+    let authority = harness.authority.pubkey();
+    harness.send_as_authority(admin::set_emergency(authority, true)).unwrap();
+    let payer_balance = harness.svm.get_balance(&payer.pubkey()).unwrap();
+    harness.send_as_authority(admin::veto_withdrawal(authority, &nullifier, payer.pubkey())).unwrap();
+
+    let pending_withdrawal = harness.svm.get_account(&pda::pending_withdrawal(&nullifier));
+    assert!(pending_withdrawal.is_none_or(|account| account.data.is_empty()));
+    assert!(harness.svm.get_balance(&payer.pubkey()).unwrap() > payer_balance);
+    assert_eq!(harness.svm.get_balance(&recipient), None);
+    // Nothing is owed to the recipient anymore, yet the lamports stay reserved in the pool
+    assert_eq!(harness.tree_token_account().reserved_lamports, amount);
+    assert_eq!(harness.tree_token_account().vetoed_lamports, amount);
+    assert_eq!(harness.svm.get_balance(&pda::tree_token_account()), Some(rent_exempt_minimum + amount));
+}
+
+#[test]
+#[ignore = "needs zkcash.so"]
+fn test_release_vetoed_funds() {
+    let mut harness = Harness::setup();
+    let payer = harness.funded_keypair();
+    let nullifier = nullifier(1);
+    let amount = LAMPORTS_PER_SOL;
+    harness.set_pending_withdrawal(nullifier, Pubkey::new_unique(), amount, payer.pubkey());
+    let rent_exempt_minimum = harness.svm.minimum_balance_for_rent_exemption(TreeTokenAccount::SPACE);
+    harness.set_pool(rent_exempt_minimum + amount, amount);
+    let authority = harness.authority.pubkey();
+    harness.send_as_authority(admin::set_emergency(authority, true)).unwrap();
+    harness.send_as_authority(admin::veto_withdrawal(authority, &nullifier, payer.pubkey())).unwrap();
+
+    let stranger = harness.funded_keypair();
+    let recipient = Pubkey::new_unique();
+    assert_program_error(
+        harness.send(admin::release_vetoed_funds(stranger.pubkey(), amount, stranger.pubkey()), &stranger),
+        ErrorCode::Unauthorized,
+    );
+    assert_program_error(
+        harness.send_as_authority(admin::release_vetoed_funds(authority, amount + 1, recipient)),
+        ErrorCode::InsufficientVetoedFunds,
+    );
+
+    harness.send_as_authority(admin::release_vetoed_funds(authority, amount, recipient)).unwrap();
+    assert_eq!(harness.svm.get_balance(&recipient), Some(amount));
+    assert_eq!(harness.svm.get_balance(&pda::tree_token_account()), Some(rent_exempt_minimum));
+    assert_eq!(harness.tree_token_account().reserved_lamports, 0);
+    assert_eq!(harness.tree_token_account().vetoed_lamports, 0);
+}
@@ -0,0 +1,148 @@
+#![cfg(feature = "prover")]
+
+use std::path::PathBuf;
+
+use anchor_lang::prelude::*;
+use ark_bn254::Fr;
+use light_hasher::Poseidon;
+use rand::rngs::OsRng;
+use zkcash::utils::{verify_proof, SOL_ADDRESS, VERIFYING_KEY};
+use zkcash_client::prover::{Prover, TransactionInputs, CIRCUIT_LEVELS};
+use zkcash_client::{
+    ext_data_hash, ext_data_hash_public_input, public_amount, ExtData, Keypair, OffchainMerkleTree,
+    Utxo,
+};
+
+/**
+ * The zkey is not checked in, point ZKCASH_CIRCUIT_PATH at `<dir>/transaction2` or drop
+ * transaction2.zkey next to the wasm in artifacts/circuits. Tests that prove are ignored by
+ * default, run them with `-- --ignored` once the zkey is in place.
+ */
+fn load_prover() -> Prover {
+    let base_path = std::env::var("ZKCASH_CIRCUIT_PATH")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| {
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../../artifacts/circuits/transaction2")
+        });
+    assert!(
+        base_path.with_extension("wasm").exists() && base_path.with_extension("zkey").exists(),
+        "circuit artifacts not found at {}",
+        base_path.display()
+    );
+    Prover::from_base_path(base_path).unwrap()
+}
+
+fn test_ext_data(ext_amount: i64, fee: u64) -> ExtData {
+    ExtData {
+        recipient: Pubkey::new_unique(),
+        ext_amount,
+        fee,
+        fee_recipient: Pubkey::new_unique(),
+        mint_address: SOL_ADDRESS,
+        callback: None,
+        recipients: None,
+        auditor: None,
+        memo: None,
+        referrer: None,
+        swap: None,
+    }
+}
+
+fn ext_data_public_input(ext_data: &ExtData) -> [u8; 32] {
+    ext_data_hash_public_input(&ext_data_hash(ext_data, &[1u8; 64], &[2u8; 64]).unwrap())
+}
+
+fn dummy_utxo(seed: u64) -> Utxo {
+    Utxo::new(0, Keypair::new(Fr::from(seed)).unwrap(), Fr::from(seed + 1000))
+}
+
+#[test]
+#[ignore = "needs transaction2.zkey"]
+fn test_deposit_then_withdraw_proofs_verify() {
+    let mut prover = load_prover();
+    let keypair = Keypair::new(Fr::from(123_456u64)).unwrap();
+    let mut tree = OffchainMerkleTree::<Poseidon>::new(CIRCUIT_LEVELS);
+
+    // Deposit 1_000_000 with a fee of 1_000 into a single output
+    let deposit_ext_data = test_ext_data(1_000_000, 1_000);
+    let deposit_outputs = [Utxo::new(999_000, keypair, Fr::from(11u64)), dummy_utxo(1)];
+    let deposit = TransactionInputs {
+        root: tree.root(),
+        public_amount: public_amount(1_000_000, 1_000).unwrap(),
+        ext_data_hash: ext_data_public_input(&deposit_ext_data),
+        inputs: [dummy_utxo(2), dummy_utxo(3)],
+        input_paths: [vec![[0u8; 32]; CIRCUIT_LEVELS], vec![[0u8; 32]; CIRCUIT_LEVELS]],
+        outputs: deposit_outputs,
+    };
+    let proof = prover.prove(&deposit, &mut OsRng).unwrap();
+    assert!(verify_proof(proof.clone(), VERIFYING_KEY));
+
+    for output in &deposit_outputs {
+        tree.append(output.commitment_bytes().unwrap()).unwrap();
+    }
+
+    // Withdraw 500_000 of it with a fee of 500, keeping the change
+    let spent = deposit_outputs[0].with_index(0);
+    let withdraw_ext_data = test_ext_data(-500_000, 500);
+    let withdraw = TransactionInputs {
+        root: tree.root(),
+        public_amount: public_amount(-500_000, 500).unwrap(),
+        ext_data_hash: ext_data_public_input(&withdraw_ext_data),
+        inputs: [spent, dummy_utxo(4)],
+        input_paths: [tree.path(0).unwrap(), vec![[0u8; 32]; CIRCUIT_LEVELS]],
+        outputs: [Utxo::new(498_500, keypair, Fr::from(12u64)), dummy_utxo(5)],
+    };
+    let proof = prover.prove(&withdraw, &mut OsRng).unwrap();
+    assert_eq!(proof.root, tree.root());
+    assert_eq!(proof.input_nullifiers[0], spent.nullifier_bytes().unwrap());
+    assert!(verify_proof(proof.clone(), VERIFYING_KEY));
+
+    // Any change to a public input must invalidate the proof
+    let mut tampered = proof.clone();
+    tampered.public_amount = public_amount(-500_001, 500).unwrap();
+    assert!(!verify_proof(tampered, VERIFYING_KEY));
+
+    let mut tampered = proof;
+    tampered.output_commitments.swap(0, 1);
+    assert!(!verify_proof(tampered, VERIFYING_KEY));
+}
+
+#[test]
+#[ignore = "needs transaction2.zkey"]
+fn test_unbalanced_transaction_fails_witness_generation() {
+    let mut prover = load_prover();
+    let keypair = Keypair::new(Fr::from(1u64)).unwrap();
+    let tree = OffchainMerkleTree::<Poseidon>::new(CIRCUIT_LEVELS);
+    let ext_data = test_ext_data(1_000, 0);
+
+    let inputs = TransactionInputs {
+        root: tree.root(),
+        public_amount: public_amount(1_000, 0).unwrap(),
+        ext_data_hash: ext_data_public_input(&ext_data),
+        inputs: [dummy_utxo(2), dummy_utxo(3)],
+        input_paths: [vec![[0u8; 32]; CIRCUIT_LEVELS], vec![[0u8; 32]; CIRCUIT_LEVELS]],
+        // Outputs are worth more than what is deposited
+        outputs: [Utxo::new(1_001, keypair, Fr::from(1u64)), dummy_utxo(4)],
+    };
+    assert!(prover.prove(&inputs, &mut OsRng).is_err());
+}
+
+#[test]
+fn test_circuit_inputs_reject_bad_paths_and_mixed_mints() {
+    let keypair = Keypair::new(Fr::from(1u64)).unwrap();
+    let mut inputs = TransactionInputs {
+        root: [0u8; 32],
+        public_amount: public_amount(1_000, 0).unwrap(),
+        ext_data_hash: [0u8; 32],
+        inputs: [dummy_utxo(2), dummy_utxo(3)],
+        input_paths: [vec![[0u8; 32]; CIRCUIT_LEVELS], vec![[0u8; 32]; CIRCUIT_LEVELS - 1]],
+        outputs: [Utxo::new(1_000, keypair, Fr::from(1u64)), dummy_utxo(4)],
+    };
+    assert!(inputs.circuit_inputs().is_err());
+
+    inputs.input_paths[1].push([0u8; 32]);
+    assert!(inputs.circuit_inputs().is_ok());
+
+    inputs.outputs[1].mint_address = Fr::from(5u64);
+    assert!(inputs.circuit_inputs().is_err());
+}
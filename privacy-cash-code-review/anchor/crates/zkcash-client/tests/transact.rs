@@ -0,0 +1,965 @@
+//! End-to-end `transact` flows against the compiled program in LiteSVM, with real proofs.
+//!
+//! Needs the program built for localnet (`anchor build -- --features localnet`, so that any
+//! key can initialize) and the circuit artifacts described in `tests/prover.rs`, so the tests are
+//! ignored by default. Run them with `cargo test --features prover --test transact -- --ignored`,
+//! a missing artifact then fails the test instead of passing it.
+//!
+//! Swap tests also need the mock AMM (`target/deploy/mock_amm.so`, or `ZKCASH_MOCK_AMM_SO`).
+//!
+//! `test_transact_compute_units` asserts that a deposit and a withdrawal each stay within
+//! `TRANSACT_CU_BUDGET`. Given a baseline build of the program in `ZKCASH_BASELINE_PROGRAM_SO`, it
+//! also checks that neither uses more CU than the baseline. The baseline must share the current
+//! instruction layout, e.g. a build of the parent commit.
+#![cfg(feature = "prover")]
+// LiteSVM's `TransactionResult` carries the whole transaction metadata in its error
+#![allow(clippy::result_large_err)]
+
+use std::path::PathBuf;
+
+use anchor_lang::prelude::{Pubkey, Rent};
+use anchor_lang::solana_program::clock::Clock;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::system_program;
+use anchor_lang::{AccountDeserialize, AccountSerialize, InstructionData, ToAccountMetas};
+use ark_bn254::Fr;
+use light_hasher::Poseidon;
+use litesvm::types::{FailedTransactionMetadata, TransactionResult};
+use litesvm::LiteSVM;
+use rand::rngs::OsRng;
+use solana_sdk::account::Account;
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::signature::{Keypair as SolanaKeypair, Signer};
+use solana_sdk::system_instruction::SystemError;
+use solana_sdk::transaction::{Transaction, TransactionError};
+use zkcash::nullifier_tree::{NullifierInsertProof, NullifierMode, NULLIFIER_TREE_HEIGHT};
+use zkcash::swap::TOKEN_PROGRAM_ID;
+use zkcash::utils::{transact_rent, TransactPdas, SOL_ADDRESS};
+use zkcash::{ErrorCode, MerkleTreeAccount, TreeTokenAccount};
+use zkcash_client::prover::{Prover, TransactionInputs, CIRCUIT_LEVELS};
+use zkcash_client::{
+    admin, ext_data_hash, ext_data_hash_public_input, instructions, pda, public_amount, ExtData, IndexedMerkleTree,
+    Keypair, OffchainMerkleTree, Proof, SwapParams, TransactBuilder, Utxo, WithdrawCallback,
+};
+
+const LAMPORTS_PER_SOL: u64 = 1_000_000_000;
+const ENCRYPTED_OUTPUT: [u8; 64] = [1u8; 64];
+// Keeps 400k CU of the 1.4M maximum free for future transact features
+const TRANSACT_CU_BUDGET: u64 = 1_000_000;
+// LiteSVM's default lamports per signature
+const SIGNATURE_FEE: u64 = 5_000;
+// SPL Memo, which LiteSVM loads by default
+const MEMO_PROGRAM_ID: Pubkey = anchor_lang::pubkey!("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
+
+fn program_path() -> PathBuf {
+    std::env::var("ZKCASH_PROGRAM_SO")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../target/deploy/zkcash.so"))
+}
+
+fn mock_amm_path() -> PathBuf {
+    std::env::var("ZKCASH_MOCK_AMM_SO")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../target/deploy/mock_amm.so"))
+}
+
+fn circuit_path() -> PathBuf {
+    std::env::var("ZKCASH_CIRCUIT_PATH")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| {
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../../artifacts/circuits/transaction2")
+        })
+}
+
+struct Harness {
+    svm: LiteSVM,
+    prover: Prover,
+    authority: SolanaKeypair,
+    relayer: SolanaKeypair,
+    fee_recipient: Pubkey,
+    /// Off-chain mirror of the commitment tree, kept in sync after every successful transact.
+    tree: OffchainMerkleTree<Poseidon>,
+    next_blinding: u64,
+    /// Set once `setup_mock_amm` has configured the pool's swap program.
+    swap_program: Option<Pubkey>,
+    /// The pool's nullifier mode, which `transact` builds its accounts for.
+    nullifier_mode: NullifierMode,
+    /// The pool's withdrawal delay threshold, which `transact` builds its accounts for.
+    withdrawal_delay_threshold: u64,
+}
+
+impl Harness {
+    fn setup() -> Self {
+        Self::setup_with_program(program_path())
+    }
+
+    fn setup_with_program(program_path: PathBuf) -> Self {
+        let circuit_path = circuit_path();
+        assert!(
+            program_path.exists()
+                && circuit_path.with_extension("wasm").exists()
+                && circuit_path.with_extension("zkey").exists(),
+            "need {} and {}.{{wasm,zkey}}",
+            program_path.display(),
+            circuit_path.display()
+        );
+
+        let mut svm = LiteSVM::new();
+        svm.add_program_from_file(zkcash::ID, &program_path).unwrap();
+
+        let authority = SolanaKeypair::new();
+        let relayer = SolanaKeypair::new();
+        svm.airdrop(&authority.pubkey(), 100 * LAMPORTS_PER_SOL).unwrap();
+        svm.airdrop(&relayer.pubkey(), 100 * LAMPORTS_PER_SOL).unwrap();
+
+        let mut harness = Harness {
+            svm,
+            prover: Prover::from_base_path(circuit_path).unwrap(),
+            authority,
+            relayer,
+            fee_recipient: Pubkey::new_unique(),
+            tree: OffchainMerkleTree::new(CIRCUIT_LEVELS),
+            next_blinding: 1,
+            swap_program: None,
+            nullifier_mode: NullifierMode::Pda,
+            withdrawal_delay_threshold: 0,
+        };
+        harness.initialize();
+        harness
+    }
+
+    fn send(&mut self, instruction: Instruction, signer: &SolanaKeypair) -> TransactionResult {
+        let instructions = [
+            ComputeBudgetInstruction::set_compute_unit_limit(1_400_000),
+            instruction,
+        ];
+        let transaction = Transaction::new_signed_with_payer(
+            &instructions,
+            Some(&signer.pubkey()),
+            &[signer],
+            self.svm.latest_blockhash(),
+        );
+        let result = self.svm.send_transaction(transaction);
+        // Identical retries must not be rejected as already processed
+        self.svm.expire_blockhash();
+        result
+    }
+
+    fn initialize(&mut self) {
+        let instruction = Instruction {
+            program_id: zkcash::ID,
+            accounts: zkcash::accounts::Initialize {
+                tree_account: pda::tree_account(),
+                tree_token_account: pda::tree_token_account(),
+                global_config: pda::global_config(),
+                nullifier_tree: None,
+                authority: self.authority.pubkey(),
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: zkcash::instruction::Initialize {
+                nullifier_mode: NullifierMode::Pda,
+            }
+            .data(),
+        };
+        let authority = self.authority.insecure_clone();
+        self.send(instruction, &authority).unwrap();
+    }
+
+    fn update_deposit_limit(&mut self, new_limit: u64) {
+        let instruction = Instruction {
+            program_id: zkcash::ID,
+            accounts: zkcash::accounts::UpdateDepositLimit {
+                tree_account: pda::tree_account(),
+                authority: self.authority.pubkey(),
+            }
+            .to_account_metas(None),
+            data: zkcash::instruction::UpdateDepositLimit { new_limit }.data(),
+        };
+        let authority = self.authority.insecure_clone();
+        self.send(instruction, &authority).unwrap();
+    }
+
+    fn update_withdrawal_delay(&mut self, threshold: u64, delay_slots: u64) {
+        let authority = self.authority.insecure_clone();
+        self.send(admin::update_withdrawal_delay(authority.pubkey(), threshold, delay_slots), &authority).unwrap();
+        self.withdrawal_delay_threshold = threshold;
+    }
+
+    fn update_transfer_rules(&mut self, transfer_fee: u64, max_transfers_per_slot: u16) {
+        let instruction = Instruction {
+            program_id: zkcash::ID,
+            accounts: zkcash::accounts::UpdateGlobalConfig {
+                global_config: pda::global_config(),
+                authority: self.authority.pubkey(),
+            }
+            .to_account_metas(None),
+            data: zkcash::instruction::UpdateTransferRules {
+                transfer_fee,
+                max_transfers_per_slot,
+            }
+            .data(),
+        };
+        let authority = self.authority.insecure_clone();
+        self.send(instruction, &authority).unwrap();
+    }
+
+    /**
+     * Loads the mock AMM with a pool minting a fresh token at `rate` base units per SOL, and
+     * configures it as the pool's swap program. Returns the token mint.
+     */
+    fn setup_mock_amm(&mut self, rate: u64) -> Pubkey {
+        let mock_amm_path = mock_amm_path();
+        assert!(mock_amm_path.exists(), "need {}", mock_amm_path.display());
+        self.svm.add_program_from_file(mock_amm::ID, &mock_amm_path).unwrap();
+
+        let mint = Pubkey::new_unique();
+        let pool = mock_amm_pool(&mint);
+        self.set_token_program_account(mint, mint_data(&pool));
+
+        let authority = self.authority.insecure_clone();
+        let instruction = Instruction {
+            program_id: mock_amm::ID,
+            accounts: mock_amm::accounts::InitializePool {
+                pool,
+                mint,
+                authority: authority.pubkey(),
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: mock_amm::instruction::InitializePool { rate }.data(),
+        };
+        self.send(instruction, &authority).unwrap();
+
+        let instruction = Instruction {
+            program_id: zkcash::ID,
+            accounts: zkcash::accounts::UpdateGlobalConfig {
+                global_config: pda::global_config(),
+                authority: authority.pubkey(),
+            }
+            .to_account_metas(None),
+            data: zkcash::instruction::UpdateSwapProgram { swap_program: mock_amm::ID }.data(),
+        };
+        self.send(instruction, &authority).unwrap();
+        self.swap_program = Some(mock_amm::ID);
+        mint
+    }
+
+    fn set_mock_amm_rate(&mut self, mint: &Pubkey, rate: u64) {
+        let authority = self.authority.insecure_clone();
+        let instruction = Instruction {
+            program_id: mock_amm::ID,
+            accounts: mock_amm::accounts::SetRate {
+                pool: mock_amm_pool(mint),
+                authority: authority.pubkey(),
+            }
+            .to_account_metas(None),
+            data: mock_amm::instruction::SetRate { rate }.data(),
+        };
+        self.send(instruction, &authority).unwrap();
+    }
+
+    fn set_token_program_account(&mut self, address: Pubkey, data: Vec<u8>) {
+        let account = Account {
+            lamports: self.svm.minimum_balance_for_rent_exemption(data.len()),
+            data,
+            owner: TOKEN_PROGRAM_ID,
+            executable: false,
+            rent_epoch: 0,
+        };
+        self.svm.set_account(address, account).unwrap();
+    }
+
+    /// Creates an empty token account of `mint` owned by `owner`.
+    fn token_account(&mut self, mint: &Pubkey, owner: &Pubkey) -> Pubkey {
+        let address = Pubkey::new_unique();
+        self.set_token_program_account(address, token_account_data(mint, owner));
+        address
+    }
+
+    fn token_balance(&self, token_account: &Pubkey) -> u64 {
+        let account = self.svm.get_account(token_account).unwrap();
+        u64::from_le_bytes(account.data[64..72].try_into().unwrap())
+    }
+
+    /// Sets the lamports the pool has reserved for pending withdrawals.
+    fn set_reserved_lamports(&mut self, reserved_lamports: u64) {
+        let mut account = self.svm.get_account(&pda::tree_token_account()).unwrap();
+        let mut tree_token_account = TreeTokenAccount::try_deserialize(&mut &account.data[..]).unwrap();
+        tree_token_account.reserved_lamports = reserved_lamports;
+        let mut data = Vec::new();
+        tree_token_account.try_serialize(&mut data).unwrap();
+        account.data[..data.len()].copy_from_slice(&data);
+        self.svm.set_account(pda::tree_token_account(), account).unwrap();
+    }
+
+    fn tree_account(&self) -> MerkleTreeAccount {
+        let account = self.svm.get_account(&pda::tree_account()).unwrap();
+        bytemuck::pod_read_unaligned(&account.data[8..8 + std::mem::size_of::<MerkleTreeAccount>()])
+    }
+
+    fn balance(&self, address: &Pubkey) -> u64 {
+        self.svm.get_balance(address).unwrap_or(0)
+    }
+
+    fn utxo(&mut self, amount: u64, keypair: Keypair) -> Utxo {
+        self.next_blinding += 1;
+        Utxo::new(amount, keypair, Fr::from(self.next_blinding))
+    }
+
+    fn dummy_utxo(&mut self) -> Utxo {
+        let keypair = Keypair::new(Fr::from(self.next_blinding + 1_000_000)).unwrap();
+        self.utxo(0, keypair)
+    }
+
+    /**
+     * Proves a transaction spending `inputs` (already indexed, or dummies) into `outputs`
+     * against `root`, which defaults to the current root of the off-chain tree.
+     */
+    fn prove(
+        &mut self,
+        inputs: [Utxo; 2],
+        outputs: [Utxo; 2],
+        ext_amount: i64,
+        fee: u64,
+        recipient: Pubkey,
+        root: Option<[u8; 32]>,
+    ) -> (Proof, ExtData) {
+        let ext_data = self.ext_data(ext_amount, fee, recipient);
+        (self.prove_ext_data(inputs, outputs, &ext_data, root), ext_data)
+    }
+
+    fn ext_data(&self, ext_amount: i64, fee: u64, recipient: Pubkey) -> ExtData {
+        ExtData {
+            recipient,
+            ext_amount,
+            fee,
+            fee_recipient: self.fee_recipient,
+            mint_address: SOL_ADDRESS,
+            callback: None,
+            recipients: None,
+            auditor: None,
+            memo: None,
+            referrer: None,
+            swap: None,
+        }
+    }
+
+    /// Proves a transaction with the given ext data, as `prove`.
+    fn prove_ext_data(
+        &mut self,
+        inputs: [Utxo; 2],
+        outputs: [Utxo; 2],
+        ext_data: &ExtData,
+        root: Option<[u8; 32]>,
+    ) -> Proof {
+        let hash = ext_data_hash(ext_data, &ENCRYPTED_OUTPUT, &ENCRYPTED_OUTPUT).unwrap();
+
+        let input_paths = inputs.map(|utxo| {
+            if utxo.amount == 0 {
+                vec![[0u8; 32]; CIRCUIT_LEVELS]
+            } else {
+                self.tree.path(utxo.index).unwrap()
+            }
+        });
+        let transaction = TransactionInputs {
+            root: root.unwrap_or(self.tree.root()),
+            public_amount: public_amount(ext_data.ext_amount, ext_data.fee).unwrap(),
+            ext_data_hash: ext_data_hash_public_input(&hash),
+            inputs,
+            input_paths,
+            outputs,
+        };
+        self.prover.prove(&transaction, &mut OsRng).unwrap()
+    }
+
+    /// Submits a transact signed by the relayer and mirrors the new leaves on success.
+    fn transact(&mut self, proof: Proof, ext_data: ExtData, depositor: Option<&SolanaKeypair>) -> TransactionResult {
+        let signer = depositor.map_or(self.relayer.insecure_clone(), |d| d.insecure_clone());
+        let mut builder = TransactBuilder::new(
+            proof.clone(),
+            ext_data,
+            ENCRYPTED_OUTPUT.to_vec(),
+            ENCRYPTED_OUTPUT.to_vec(),
+            signer.pubkey(),
+        );
+        if let Some(swap_program) = self.swap_program {
+            builder = builder.swap_program(swap_program);
+        }
+        let instruction = builder
+            .nullifier_mode(self.nullifier_mode)
+            .withdrawal_delay_threshold(self.withdrawal_delay_threshold)
+            .instruction()
+            .unwrap();
+
+        let result = self.send(instruction, &signer);
+        if result.is_ok() {
+            for commitment in proof.output_commitments {
+                self.tree.append(commitment).unwrap();
+            }
+            self.tree.verify_against(&self.tree_account()).unwrap();
+        }
+        result
+    }
+
+    /// Lamports the signer, as in `transact`, spends on a successful transact besides the
+    /// signature fee.
+    fn signer_spend(&mut self, proof: Proof, ext_data: ExtData, depositor: Option<&SolanaKeypair>) -> u64 {
+        let signer = depositor.map_or(self.relayer.pubkey(), |d| d.pubkey());
+        let balance_before = self.balance(&signer);
+        self.transact(proof, ext_data, depositor).unwrap();
+        balance_before - self.balance(&signer) - SIGNATURE_FEE
+    }
+
+    /// Deposits `amount` into a fresh UTXO owned by `owner` and returns it with its leaf index.
+    fn deposit(&mut self, depositor: &SolanaKeypair, amount: u64, owner: Keypair) -> Utxo {
+        let output = self.utxo(amount, owner);
+        let inputs = [self.dummy_utxo(), self.dummy_utxo()];
+        let change = self.dummy_utxo();
+        let (proof, ext_data) =
+            self.prove(inputs, [output, change], amount as i64, 0, depositor.pubkey(), None);
+
+        let index = self.tree.len();
+        self.transact(proof, ext_data, Some(depositor)).unwrap();
+        output.with_index(index)
+    }
+}
+
+fn mock_amm_pool(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[mock_amm::POOL_SEED, mint.as_ref()], &mock_amm::ID).0
+}
+
+/// An initialized SPL Token mint with 6 decimals, packed by hand.
+fn mint_data(mint_authority: &Pubkey) -> Vec<u8> {
+    let mut data = vec![0u8; 82];
+    data[0..4].copy_from_slice(&1u32.to_le_bytes());
+    data[4..36].copy_from_slice(mint_authority.as_ref());
+    data[44] = 6;
+    data[45] = 1;
+    data
+}
+
+/// An initialized, empty SPL Token account, packed by hand.
+fn token_account_data(mint: &Pubkey, owner: &Pubkey) -> Vec<u8> {
+    let mut data = vec![0u8; 165];
+    data[0..32].copy_from_slice(mint.as_ref());
+    data[32..64].copy_from_slice(owner.as_ref());
+    data[108] = 1;
+    data
+}
+
+/// Swap of `amount_in` withdrawn lamports through the mock AMM pool of `mint`.
+fn mock_amm_swap(mint: Pubkey, recipient_token_account: Pubkey, amount_in: u64, min_out: u64) -> SwapParams {
+    let accounts = mock_amm::accounts::Swap {
+        user: pda::swap_authority(),
+        pool: mock_amm_pool(&mint),
+        mint,
+        destination: recipient_token_account,
+        token_program: TOKEN_PROGRAM_ID,
+        system_program: system_program::ID,
+    }
+    .to_account_metas(None);
+
+    SwapParams {
+        output_mint: mint,
+        recipient_token_account,
+        min_out,
+        accounts: accounts.iter().map(|meta| meta.pubkey).collect(),
+        data: mock_amm::instruction::Swap { amount_in }.data(),
+    }
+}
+
+fn withdrawal_fee(amount: u64) -> u64 {
+    // Default withdrawal fee rate of initialize, 25 basis points
+    amount * 25 / 10_000
+}
+
+fn assert_program_error(result: TransactionResult, error: ErrorCode) {
+    let FailedTransactionMetadata { err, meta } = result.expect_err("transaction should fail");
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(1, InstructionError::Custom(u32::from(error))),
+        "logs: {:#?}",
+        meta.logs
+    );
+}
+
+#[test]
+#[ignore = "needs zkcash.so + transaction2.zkey"]
+fn test_deposit_transfer_withdraw() {
+    let mut harness = Harness::setup();
+    let alice = Keypair::new(Fr::from(1u64)).unwrap();
+    let bob = Keypair::new(Fr::from(2u64)).unwrap();
+    let depositor = SolanaKeypair::new();
+    harness.svm.airdrop(&depositor.pubkey(), 10 * LAMPORTS_PER_SOL).unwrap();
+
+    // Deposit
+    let deposited = harness.deposit(&depositor, 2 * LAMPORTS_PER_SOL, alice);
+    assert!(harness.balance(&pda::tree_token_account()) >= 2 * LAMPORTS_PER_SOL);
+    assert!(harness.balance(&depositor.pubkey()) < 8 * LAMPORTS_PER_SOL);
+
+    // Transfer 0.5 SOL from alice to bob, free under initialize's default transfer fee
+    let to_bob = harness.utxo(LAMPORTS_PER_SOL / 2, bob);
+    let alice_change = harness.utxo(deposited.amount - LAMPORTS_PER_SOL / 2, alice);
+    let dummy = harness.dummy_utxo();
+    let (proof, ext_data) =
+        harness.prove([deposited, dummy], [to_bob, alice_change], 0, 0, depositor.pubkey(), None);
+    let bob_index = harness.tree.len();
+    harness.transact(proof, ext_data, Some(&depositor)).unwrap();
+    let to_bob = to_bob.with_index(bob_index);
+
+    // Bob withdraws everything to a fresh address through the relayer
+    let recipient = Pubkey::new_unique();
+    let fee = withdrawal_fee(to_bob.amount);
+    let withdraw_amount = to_bob.amount - fee;
+    let outputs = [harness.dummy_utxo(), harness.dummy_utxo()];
+    let dummy = harness.dummy_utxo();
+    let (proof, ext_data) =
+        harness.prove([to_bob, dummy], outputs, -(withdraw_amount as i64), fee, recipient, None);
+    let fee_recipient_before = harness.balance(&harness.fee_recipient);
+    harness.transact(proof, ext_data, None).unwrap();
+
+    assert_eq!(harness.balance(&recipient), withdraw_amount);
+    assert_eq!(harness.balance(&harness.fee_recipient) - fee_recipient_before, fee);
+}
+
+#[test]
+#[ignore = "needs zkcash.so + transaction2.zkey"]
+fn test_double_spend_fails() {
+    let mut harness = Harness::setup();
+    let alice = Keypair::new(Fr::from(1u64)).unwrap();
+    let depositor = SolanaKeypair::new();
+    harness.svm.airdrop(&depositor.pubkey(), 10 * LAMPORTS_PER_SOL).unwrap();
+    let deposited = harness.deposit(&depositor, LAMPORTS_PER_SOL, alice);
+
+    let fee = withdrawal_fee(deposited.amount);
+    let amount = (deposited.amount - fee) as i64;
+    for attempt in 0..2 {
+        let outputs = [harness.dummy_utxo(), harness.dummy_utxo()];
+        let dummy = harness.dummy_utxo();
+        let (proof, ext_data) =
+            harness.prove([deposited, dummy], outputs, -amount, fee, Pubkey::new_unique(), None);
+        let result = harness.transact(proof, ext_data, None);
+        if attempt == 0 {
+            result.unwrap();
+        } else {
+            // The nullifier PDA already exists, so its `init` fails in the system program
+            let failed = result.expect_err("double spend should fail");
+            assert_eq!(
+                failed.err,
+                TransactionError::InstructionError(
+                    1,
+                    InstructionError::Custom(SystemError::AccountAlreadyInUse as u32)
+                )
+            );
+            assert!(failed.meta.logs.iter().any(|log| log.contains("already in use")), "{:?}", failed.meta.logs);
+        }
+    }
+}
+
+#[test]
+#[ignore = "needs zkcash.so + transaction2.zkey"]
+fn test_legacy_nullifier_respent_under_compressed_fails() {
+    let mut harness = Harness::setup();
+    let alice = Keypair::new(Fr::from(1u64)).unwrap();
+    let depositor = SolanaKeypair::new();
+    harness.svm.airdrop(&depositor.pubkey(), 10 * LAMPORTS_PER_SOL).unwrap();
+
+    // Deposit and spend a note under Pda, so its nullifier only exists as a PDA
+    let output = harness.utxo(LAMPORTS_PER_SOL, alice);
+    let inputs = [harness.dummy_utxo(), harness.dummy_utxo()];
+    let change = harness.dummy_utxo();
+    let (deposit_proof, ext_data) =
+        harness.prove(inputs, [output, change], LAMPORTS_PER_SOL as i64, 0, depositor.pubkey(), None);
+    let index = harness.tree.len();
+    harness.transact(deposit_proof.clone(), ext_data, Some(&depositor)).unwrap();
+    let note = output.with_index(index);
+
+    let fee = withdrawal_fee(note.amount);
+    let amount = (note.amount - fee) as i64;
+    let outputs = [harness.dummy_utxo(), harness.dummy_utxo()];
+    let dummy = harness.dummy_utxo();
+    let (withdrawal_proof, ext_data) = harness.prove([note, dummy], outputs, -amount, fee, Pubkey::new_unique(), None);
+    harness.transact(withdrawal_proof.clone(), ext_data, None).unwrap();
+
+    // Backfill all four nullifier PDAs and move to Compressed
+    let authority = harness.authority.insecure_clone();
+    harness.send(admin::initialize_nullifier_tree(authority.pubkey()), &authority).unwrap();
+    harness.send(admin::set_nullifier_mode(authority.pubkey(), NullifierMode::Dual), &authority).unwrap();
+    let relayer = harness.relayer.insecure_clone();
+    let mut nullifier_tree = IndexedMerkleTree::new().unwrap();
+    for proof in [&deposit_proof, &withdrawal_proof] {
+        let nullifier_accounts = pda::nullifiers(&proof.input_nullifiers);
+        for (nullifier, nullifier_account) in proof.input_nullifiers.into_iter().zip(nullifier_accounts) {
+            let insert_proof = nullifier_tree.insert(nullifier).unwrap();
+            let instruction =
+                instructions::backfill_nullifier(nullifier_account, nullifier, insert_proof, relayer.pubkey());
+            harness.send(instruction, &relayer).unwrap();
+        }
+    }
+    harness.send(admin::set_nullifier_mode(authority.pubkey(), NullifierMode::Compressed), &authority).unwrap();
+    harness.nullifier_mode = NullifierMode::Compressed;
+
+    // Spend the note again. Its nullifier has no insert proof, the closest is its predecessor's leaf,
+    // which the program rejects before checking the opening.
+    let outputs = [harness.dummy_utxo(), harness.dummy_utxo()];
+    let dummy = harness.dummy_utxo();
+    let (proof, ext_data) = harness.prove([note, dummy], outputs, -amount, fee, Pubkey::new_unique(), None);
+    let [spent, fresh] = proof.input_nullifiers;
+    assert_eq!(spent, withdrawal_proof.input_nullifiers[0]);
+    let low_leaf_index = (0..nullifier_tree.len())
+        .find(|&index| nullifier_tree.leaf(index).unwrap().next_value == spent)
+        .unwrap();
+    let spent_proof = NullifierInsertProof {
+        low_leaf: nullifier_tree.leaf(low_leaf_index).unwrap(),
+        low_leaf_index,
+        siblings: [[0u8; 32]; NULLIFIER_TREE_HEIGHT],
+    };
+    let fresh_proof = nullifier_tree.insert_proof(fresh).unwrap();
+    for (position, insert_proof) in [spent_proof, fresh_proof].into_iter().enumerate() {
+        let instruction = instructions::write_nullifier_proof(relayer.pubkey(), position as u8, insert_proof);
+        harness.send(instruction, &relayer).unwrap();
+    }
+    assert_program_error(harness.transact(proof, ext_data, None), ErrorCode::NullifierAlreadySpent);
+}
+
+#[test]
+#[ignore = "needs zkcash.so + transaction2.zkey"]
+fn test_withdrawal_cannot_spend_reserved_lamports() {
+    let mut harness = Harness::setup();
+    let alice = Keypair::new(Fr::from(1u64)).unwrap();
+    let depositor = SolanaKeypair::new();
+    harness.svm.airdrop(&depositor.pubkey(), 10 * LAMPORTS_PER_SOL).unwrap();
+    let deposited = harness.deposit(&depositor, LAMPORTS_PER_SOL, alice);
+
+    // The pool holds exactly its rent plus the deposit, one lamport of which a pending withdrawal is owed
+    harness.set_reserved_lamports(1);
+    let fee = withdrawal_fee(deposited.amount);
+    let amount = (deposited.amount - fee) as i64;
+    let outputs = [harness.dummy_utxo(), harness.dummy_utxo()];
+    let dummy = harness.dummy_utxo();
+    let (proof, ext_data) = harness.prove([deposited, dummy], outputs, -amount, fee, Pubkey::new_unique(), None);
+    assert_program_error(
+        harness.transact(proof.clone(), ext_data.clone(), None),
+        ErrorCode::InsufficientFundsForWithdrawal,
+    );
+
+    harness.set_reserved_lamports(0);
+    harness.transact(proof, ext_data, None).unwrap();
+}
+
+#[test]
+#[ignore = "needs zkcash.so + transaction2.zkey"]
+fn test_unknown_root_fails() {
+    let mut harness = Harness::setup();
+    let alice = Keypair::new(Fr::from(1u64)).unwrap();
+    let depositor = SolanaKeypair::new();
+    harness.svm.airdrop(&depositor.pubkey(), 10 * LAMPORTS_PER_SOL).unwrap();
+
+    let output = harness.utxo(LAMPORTS_PER_SOL, alice);
+    let inputs = [harness.dummy_utxo(), harness.dummy_utxo()];
+    let change = harness.dummy_utxo();
+    let (proof, ext_data) = harness.prove(
+        inputs,
+        [output, change],
+        LAMPORTS_PER_SOL as i64,
+        0,
+        depositor.pubkey(),
+        Some([7u8; 32]),
+    );
+    assert_program_error(harness.transact(proof, ext_data, Some(&depositor)), ErrorCode::UnknownRoot);
+}
+
+#[test]
+#[ignore = "needs zkcash.so + transaction2.zkey"]
+fn test_withdrawal_fee_mismatch_fails() {
+    let mut harness = Harness::setup();
+    let alice = Keypair::new(Fr::from(1u64)).unwrap();
+    let depositor = SolanaKeypair::new();
+    harness.svm.airdrop(&depositor.pubkey(), 10 * LAMPORTS_PER_SOL).unwrap();
+    let deposited = harness.deposit(&depositor, LAMPORTS_PER_SOL, alice);
+
+    // No fee, where initialize's default rate expects 0.25%
+    let outputs = [harness.dummy_utxo(), harness.dummy_utxo()];
+    let dummy = harness.dummy_utxo();
+    let (proof, ext_data) = harness.prove(
+        [deposited, dummy],
+        outputs,
+        -(deposited.amount as i64),
+        0,
+        Pubkey::new_unique(),
+        None,
+    );
+    assert_program_error(harness.transact(proof, ext_data, None), ErrorCode::InvalidFeeAmount);
+}
+
+#[test]
+#[ignore = "needs zkcash.so + transaction2.zkey"]
+fn test_deposit_limit_exceeded_fails() {
+    let mut harness = Harness::setup();
+    harness.update_deposit_limit(LAMPORTS_PER_SOL);
+    let alice = Keypair::new(Fr::from(1u64)).unwrap();
+    let depositor = SolanaKeypair::new();
+    harness.svm.airdrop(&depositor.pubkey(), 10 * LAMPORTS_PER_SOL).unwrap();
+
+    let output = harness.utxo(2 * LAMPORTS_PER_SOL, alice);
+    let inputs = [harness.dummy_utxo(), harness.dummy_utxo()];
+    let change = harness.dummy_utxo();
+    let (proof, ext_data) = harness.prove(
+        inputs,
+        [output, change],
+        2 * LAMPORTS_PER_SOL as i64,
+        0,
+        depositor.pubkey(),
+        None,
+    );
+    assert_program_error(
+        harness.transact(proof, ext_data, Some(&depositor)),
+        ErrorCode::DepositLimitExceeded,
+    );
+
+    // At the limit the deposit goes through
+    harness.deposit(&depositor, LAMPORTS_PER_SOL, alice);
+}
+
+#[test]
+#[ignore = "needs zkcash.so + transaction2.zkey"]
+fn test_transfer_fee_and_slot_cap() {
+    let mut harness = Harness::setup();
+    let transfer_fee = 5_000;
+    harness.update_transfer_rules(transfer_fee, 1);
+    let alice = Keypair::new(Fr::from(1u64)).unwrap();
+    let depositor = SolanaKeypair::new();
+    harness.svm.airdrop(&depositor.pubkey(), 10 * LAMPORTS_PER_SOL).unwrap();
+    let mut note = harness.deposit(&depositor, LAMPORTS_PER_SOL, alice);
+
+    // A free transfer falls short of the transfer fee
+    let outputs = [harness.utxo(note.amount, alice), harness.dummy_utxo()];
+    let dummy = harness.dummy_utxo();
+    let (proof, ext_data) = harness.prove([note, dummy], outputs, 0, 0, Pubkey::new_unique(), None);
+    assert_program_error(harness.transact(proof, ext_data, None), ErrorCode::InvalidFeeAmount);
+
+    // The fee is paid out of the shielded amount, and the second transfer of the slot is over the cap
+    for attempt in 0..2 {
+        let output = harness.utxo(note.amount - transfer_fee, alice);
+        let dummy = harness.dummy_utxo();
+        let outputs = [output, harness.dummy_utxo()];
+        let (proof, ext_data) =
+            harness.prove([note, dummy], outputs, 0, transfer_fee, Pubkey::new_unique(), None);
+        let fee_recipient_before = harness.balance(&harness.fee_recipient);
+        let index = harness.tree.len();
+        let result = harness.transact(proof, ext_data, None);
+        if attempt == 0 {
+            result.unwrap();
+            assert_eq!(harness.balance(&harness.fee_recipient) - fee_recipient_before, transfer_fee);
+            note = output.with_index(index);
+        } else {
+            assert_program_error(result, ErrorCode::TransferLimitExceeded);
+        }
+    }
+
+    // The cap resets in the next slot
+    let slot = harness.svm.get_sysvar::<Clock>().slot;
+    harness.svm.warp_to_slot(slot + 1);
+    let output = harness.utxo(note.amount - transfer_fee, alice);
+    let dummy = harness.dummy_utxo();
+    let outputs = [output, harness.dummy_utxo()];
+    let (proof, ext_data) = harness.prove([note, dummy], outputs, 0, transfer_fee, Pubkey::new_unique(), None);
+    harness.transact(proof, ext_data, None).unwrap();
+}
+
+#[test]
+#[ignore = "needs zkcash.so, mock_amm.so + transaction2.zkey"]
+fn test_withdraw_with_swap() {
+    let mut harness = Harness::setup();
+    // 150 tokens of 6 decimals per SOL
+    let rate = 150_000_000;
+    let mint = harness.setup_mock_amm(rate);
+    let alice = Keypair::new(Fr::from(1u64)).unwrap();
+    let depositor = SolanaKeypair::new();
+    harness.svm.airdrop(&depositor.pubkey(), 10 * LAMPORTS_PER_SOL).unwrap();
+    let deposited = harness.deposit(&depositor, 2 * LAMPORTS_PER_SOL, alice);
+
+    let recipient = Pubkey::new_unique();
+    let recipient_token_account = harness.token_account(&mint, &recipient);
+    let fee = withdrawal_fee(deposited.amount);
+    let withdraw_amount = deposited.amount - fee;
+    let expected_out = withdraw_amount * rate / LAMPORTS_PER_SOL;
+
+    let mut ext_data = harness.ext_data(-(withdraw_amount as i64), fee, recipient);
+    ext_data.swap = Some(mock_amm_swap(mint, recipient_token_account, withdraw_amount, expected_out));
+    let outputs = [harness.dummy_utxo(), harness.dummy_utxo()];
+    let dummy = harness.dummy_utxo();
+    let proof = harness.prove_ext_data([deposited, dummy], outputs, &ext_data, None);
+
+    let pool = mock_amm_pool(&mint);
+    let pool_balance_before = harness.balance(&pool);
+    let tree_balance_before = harness.balance(&pda::tree_token_account());
+    harness.transact(proof, ext_data, None).unwrap();
+
+    // The recipient gets tokens only, the pool the withdrawn SOL, and nothing stays with the authority
+    assert_eq!(harness.token_balance(&recipient_token_account), expected_out);
+    assert_eq!(harness.balance(&recipient), 0);
+    assert_eq!(harness.balance(&pool) - pool_balance_before, withdraw_amount);
+    assert_eq!(tree_balance_before - harness.balance(&pda::tree_token_account()), withdraw_amount + fee);
+    assert_eq!(harness.balance(&pda::swap_authority()), 0);
+}
+
+#[test]
+#[ignore = "needs zkcash.so, mock_amm.so + transaction2.zkey"]
+fn test_swap_below_min_out_fails() {
+    let mut harness = Harness::setup();
+    let rate = 150_000_000;
+    let mint = harness.setup_mock_amm(rate);
+    let alice = Keypair::new(Fr::from(1u64)).unwrap();
+    let depositor = SolanaKeypair::new();
+    harness.svm.airdrop(&depositor.pubkey(), 10 * LAMPORTS_PER_SOL).unwrap();
+    let deposited = harness.deposit(&depositor, LAMPORTS_PER_SOL, alice);
+
+    let recipient = Pubkey::new_unique();
+    let recipient_token_account = harness.token_account(&mint, &recipient);
+    let fee = withdrawal_fee(deposited.amount);
+    let withdraw_amount = deposited.amount - fee;
+    let min_out = withdraw_amount * rate / LAMPORTS_PER_SOL;
+
+    let mut ext_data = harness.ext_data(-(withdraw_amount as i64), fee, recipient);
+    ext_data.swap = Some(mock_amm_swap(mint, recipient_token_account, withdraw_amount, min_out));
+    let outputs = [harness.dummy_utxo(), harness.dummy_utxo()];
+    let dummy = harness.dummy_utxo();
+    let proof = harness.prove_ext_data([deposited, dummy], outputs, &ext_data, None);
+
+    // The price moves against the withdrawal after it was proven
+    harness.set_mock_amm_rate(&mint, rate / 2);
+    assert_program_error(
+        harness.transact(proof.clone(), ext_data.clone(), None),
+        ErrorCode::SlippageExceeded,
+    );
+    assert_eq!(harness.token_balance(&recipient_token_account), 0);
+
+    // Nothing was spent, so the same withdrawal goes through once the price recovers
+    harness.set_mock_amm_rate(&mint, rate);
+    harness.transact(proof, ext_data, None).unwrap();
+    assert_eq!(harness.token_balance(&recipient_token_account), min_out);
+}
+
+#[test]
+#[ignore = "needs zkcash.so, mock_amm.so + transaction2.zkey"]
+fn test_signer_spend_matches_transact_rent() {
+    let mut harness = Harness::setup();
+    let mint = harness.setup_mock_amm(150_000_000);
+    let alice = Keypair::new(Fr::from(1u64)).unwrap();
+    let depositor = SolanaKeypair::new();
+    harness.svm.airdrop(&depositor.pubkey(), 10 * LAMPORTS_PER_SOL).unwrap();
+    let small = harness.deposit(&depositor, LAMPORTS_PER_SOL, alice);
+    let medium = harness.deposit(&depositor, LAMPORTS_PER_SOL / 2, alice);
+    let large = harness.deposit(&depositor, 2 * LAMPORTS_PER_SOL, alice);
+    let rent: Rent = harness.svm.get_sysvar();
+    let output_len = ENCRYPTED_OUTPUT.len();
+
+    // The swap runs with the swap authority's signature only, so it costs the relayer nothing more
+    let recipient = Pubkey::new_unique();
+    let recipient_token_account = harness.token_account(&mint, &recipient);
+    let fee = withdrawal_fee(small.amount);
+    let withdraw_amount = small.amount - fee;
+    let mut ext_data = harness.ext_data(-(withdraw_amount as i64), fee, recipient);
+    ext_data.swap = Some(mock_amm_swap(mint, recipient_token_account, withdraw_amount, 0));
+    let outputs = [harness.dummy_utxo(), harness.dummy_utxo()];
+    let dummy = harness.dummy_utxo();
+    let proof = harness.prove_ext_data([small, dummy], outputs, &ext_data, None);
+    assert_eq!(
+        harness.signer_spend(proof, ext_data, None),
+        transact_rent(&rent, NullifierMode::Pda, output_len, output_len, TransactPdas::default()).unwrap()
+    );
+
+    // Neither does a callback, which never gets signer privileges
+    let fee = withdrawal_fee(medium.amount);
+    let mut ext_data = harness.ext_data(-((medium.amount - fee) as i64), fee, Pubkey::new_unique());
+    ext_data.callback = Some(WithdrawCallback {
+        program_id: MEMO_PROGRAM_ID,
+        accounts: vec![],
+        data: b"zkcash".to_vec(),
+    });
+    let outputs = [harness.dummy_utxo(), harness.dummy_utxo()];
+    let dummy = harness.dummy_utxo();
+    let proof = harness.prove_ext_data([medium, dummy], outputs, &ext_data, None);
+    assert_eq!(
+        harness.signer_spend(proof, ext_data, None),
+        transact_rent(&rent, NullifierMode::Pda, output_len, output_len, TransactPdas::default()).unwrap()
+    );
+
+    // A referred deposit makes its signer pay for the referrer's stats, on its first deposit only
+    let first = TransactPdas { referrer_stats: true, ..TransactPdas::default() };
+    for pdas in [first, TransactPdas::default()] {
+        let output = harness.utxo(LAMPORTS_PER_SOL, alice);
+        let inputs = [harness.dummy_utxo(), harness.dummy_utxo()];
+        let change = harness.dummy_utxo();
+        let mut ext_data = harness.ext_data(LAMPORTS_PER_SOL as i64, 0, depositor.pubkey());
+        ext_data.referrer = Some([7u8; 32]);
+        let proof = harness.prove_ext_data(inputs, [output, change], &ext_data, None);
+        assert_eq!(
+            harness.signer_spend(proof, ext_data, Some(&depositor)),
+            LAMPORTS_PER_SOL + transact_rent(&rent, NullifierMode::Pda, output_len, output_len, pdas).unwrap()
+        );
+    }
+
+    // A delayed withdrawal also makes the relayer pay for its pending withdrawal
+    harness.update_withdrawal_delay(LAMPORTS_PER_SOL, 10);
+    let fee = withdrawal_fee(large.amount);
+    let outputs = [harness.dummy_utxo(), harness.dummy_utxo()];
+    let dummy = harness.dummy_utxo();
+    let (proof, ext_data) =
+        harness.prove([large, dummy], outputs, -((large.amount - fee) as i64), fee, Pubkey::new_unique(), None);
+    let pdas = TransactPdas { pending_withdrawal: true, ..TransactPdas::default() };
+    assert_eq!(
+        harness.signer_spend(proof, ext_data, None),
+        transact_rent(&rent, NullifierMode::Pda, output_len, output_len, pdas).unwrap()
+    );
+}
+
+/// Compute units of a deposit and of a withdrawal spending it.
+fn deposit_and_withdraw_compute_units(harness: &mut Harness) -> (u64, u64) {
+    let owner = Keypair::new(Fr::from(1u64)).unwrap();
+    let depositor = SolanaKeypair::new();
+    harness.svm.airdrop(&depositor.pubkey(), 10 * LAMPORTS_PER_SOL).unwrap();
+
+    let output = harness.utxo(LAMPORTS_PER_SOL, owner);
+    let inputs = [harness.dummy_utxo(), harness.dummy_utxo()];
+    let change = harness.dummy_utxo();
+    let (proof, ext_data) =
+        harness.prove(inputs, [output, change], LAMPORTS_PER_SOL as i64, 0, depositor.pubkey(), None);
+    let index = harness.tree.len();
+    let deposit_units = harness.transact(proof, ext_data, Some(&depositor)).unwrap().compute_units_consumed;
+
+    let fee = withdrawal_fee(output.amount);
+    let outputs = [harness.dummy_utxo(), harness.dummy_utxo()];
+    let dummy = harness.dummy_utxo();
+    let (proof, ext_data) = harness.prove(
+        [output.with_index(index), dummy],
+        outputs,
+        -((output.amount - fee) as i64),
+        fee,
+        Pubkey::new_unique(),
+        None,
+    );
+    let withdrawal_units = harness.transact(proof, ext_data, None).unwrap().compute_units_consumed;
+
+    (deposit_units, withdrawal_units)
+}
+
+#[test]
+#[ignore = "needs zkcash.so + transaction2.zkey"]
+fn test_transact_compute_units() {
+    let mut harness = Harness::setup();
+    let (deposit_units, withdrawal_units) = deposit_and_withdraw_compute_units(&mut harness);
+    assert!(deposit_units <= TRANSACT_CU_BUDGET, "deposit used {deposit_units} CU");
+    assert!(withdrawal_units <= TRANSACT_CU_BUDGET, "withdrawal used {withdrawal_units} CU");
+
+    let Some(baseline_path) = std::env::var_os("ZKCASH_BASELINE_PROGRAM_SO").map(PathBuf::from) else {
+        return;
+    };
+    let mut baseline = Harness::setup_with_program(baseline_path);
+    let (baseline_deposit_units, baseline_withdrawal_units) = deposit_and_withdraw_compute_units(&mut baseline);
+    assert!(deposit_units <= baseline_deposit_units, "deposit regressed");
+    assert!(withdrawal_units <= baseline_withdrawal_units, "withdrawal regressed");
+}
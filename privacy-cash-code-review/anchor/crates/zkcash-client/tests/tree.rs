@@ -0,0 +1,205 @@
+use anchor_lang::prelude::*;
+use anchor_lang::AccountSerialize;
+use light_hasher::{Hasher, Poseidon};
+use std::mem::MaybeUninit;
+use zkcash::{merkle_tree::MerkleTree, CommitmentAccount, MerkleTreeAccount};
+use zkcash_client::{leaves_since_root, OffchainMerkleTree, TreeError};
+
+const HEIGHT: usize = 26;
+
+fn create_test_account() -> MerkleTreeAccount {
+    let mut uninit: MaybeUninit<MerkleTreeAccount> = MaybeUninit::uninit();
+
+    unsafe {
+        let ptr = uninit.as_mut_ptr();
+        std::ptr::write_bytes(ptr, 0, 1);
+        (*ptr).height = HEIGHT as u8;
+        (*ptr).root_history_size = 100;
+        uninit.assume_init()
+    }
+}
+
+fn leaf(i: u64) -> [u8; 32] {
+    let mut leaf = [0u8; 32];
+    leaf[24..].copy_from_slice(&(i + 1).to_be_bytes());
+    leaf
+}
+
+fn commitment_account(index: u64) -> CommitmentAccount {
+    CommitmentAccount {
+        commitment: leaf(index),
+        encrypted_output: vec![1, 2, 3],
+        index,
+        bump: 255,
+        payer: Pubkey::new_unique(),
+        slot: 0,
+    }
+}
+
+#[test]
+fn test_empty_tree_matches_initialized_account() {
+    let mut account = create_test_account();
+    MerkleTree::initialize::<Poseidon>(&mut account).unwrap();
+
+    let tree = OffchainMerkleTree::<Poseidon>::new(HEIGHT);
+    assert!(tree.is_empty());
+    assert_eq!(tree.root(), account.root);
+    assert_eq!(tree.root(), Poseidon::zero_bytes()[HEIGHT]);
+    tree.verify_against(&account).unwrap();
+}
+
+#[test]
+fn test_roots_and_paths_match_onchain_append() {
+    let mut account = create_test_account();
+    MerkleTree::initialize::<Poseidon>(&mut account).unwrap();
+    let mut tree = OffchainMerkleTree::<Poseidon>::new(HEIGHT);
+
+    for i in 0..9 {
+        let onchain_path = MerkleTree::append::<Poseidon>(leaf(i), &mut account).unwrap();
+        assert_eq!(tree.append(leaf(i)).unwrap(), i);
+
+        assert_eq!(tree.root(), account.root, "root mismatch after leaf {}", i);
+        assert_eq!(tree.path(i).unwrap(), onchain_path, "path mismatch for leaf {}", i);
+        tree.verify_against(&account).unwrap();
+    }
+
+    // Paths of earlier leaves must reflect the leaves appended after them
+    for i in 0..9 {
+        let path = tree.path(i).unwrap();
+        assert_eq!(path.len(), HEIGHT);
+        let root = OffchainMerkleTree::<Poseidon>::compute_root(leaf(i), i, &path).unwrap();
+        assert_eq!(root, account.root, "stale path for leaf {}", i);
+    }
+}
+
+#[test]
+fn test_path_out_of_range() {
+    let tree = OffchainMerkleTree::<Poseidon>::from_leaves(HEIGHT, &[leaf(0), leaf(1)]).unwrap();
+    assert_eq!(tree.path(2), Err(TreeError::IndexOutOfRange(2)));
+}
+
+#[test]
+fn test_tree_full() {
+    let mut tree = OffchainMerkleTree::<Poseidon>::new(2);
+    for i in 0..4 {
+        tree.append(leaf(i)).unwrap();
+    }
+    assert_eq!(tree.append(leaf(4)), Err(TreeError::TreeFull));
+}
+
+#[test]
+fn test_from_commitment_accounts_in_any_order() {
+    let accounts: Vec<_> = [3, 0, 2, 1].into_iter().map(commitment_account).collect();
+    let tree = OffchainMerkleTree::<Poseidon>::from_commitment_accounts(HEIGHT, &accounts).unwrap();
+
+    let expected =
+        OffchainMerkleTree::<Poseidon>::from_leaves(HEIGHT, &[leaf(0), leaf(1), leaf(2), leaf(3)])
+            .unwrap();
+    assert_eq!(tree.len(), 4);
+    assert_eq!(tree.root(), expected.root());
+}
+
+#[test]
+fn test_from_commitment_account_data() {
+    let accounts_data: Vec<Vec<u8>> = (0..3)
+        .map(|i| {
+            let mut data = Vec::new();
+            commitment_account(i).try_serialize(&mut data).unwrap();
+            data
+        })
+        .collect();
+
+    let tree = OffchainMerkleTree::<Poseidon>::from_commitment_account_data(
+        HEIGHT,
+        accounts_data.iter().map(|data| data.as_slice()),
+    )
+    .unwrap();
+    assert_eq!(tree.len(), 3);
+    assert_eq!(tree.leaf(2), Some(leaf(2)));
+
+    let result = OffchainMerkleTree::<Poseidon>::from_commitment_account_data(HEIGHT, [&[0u8; 8][..]]);
+    assert!(matches!(result, Err(TreeError::InvalidCommitmentAccount(_))));
+}
+
+#[test]
+fn test_gaps_and_duplicates_are_rejected() {
+    let accounts: Vec<_> = [0, 1, 3].into_iter().map(commitment_account).collect();
+    assert_eq!(
+        OffchainMerkleTree::<Poseidon>::from_commitment_accounts(HEIGHT, &accounts).err(),
+        Some(TreeError::MissingIndex(2))
+    );
+
+    let accounts: Vec<_> = [0, 1, 1].into_iter().map(commitment_account).collect();
+    assert_eq!(
+        OffchainMerkleTree::<Poseidon>::from_commitment_accounts(HEIGHT, &accounts).err(),
+        Some(TreeError::DuplicateIndex(1))
+    );
+
+    let accounts: Vec<_> = [1, 2].into_iter().map(commitment_account).collect();
+    assert_eq!(
+        OffchainMerkleTree::<Poseidon>::from_commitment_accounts(HEIGHT, &accounts).err(),
+        Some(TreeError::MissingIndex(0))
+    );
+}
+
+#[test]
+fn test_verify_against_partially_synced_and_diverged_trees() {
+    let mut account = create_test_account();
+    MerkleTree::initialize::<Poseidon>(&mut account).unwrap();
+    for i in 0..4 {
+        MerkleTree::append::<Poseidon>(leaf(i), &mut account).unwrap();
+    }
+
+    // Behind the account but at a historical root
+    let behind = OffchainMerkleTree::<Poseidon>::from_leaves(HEIGHT, &[leaf(0), leaf(1)]).unwrap();
+    behind.verify_against(&account).unwrap();
+
+    // Same leaf count, different leaf
+    let diverged =
+        OffchainMerkleTree::<Poseidon>::from_leaves(HEIGHT, &[leaf(0), leaf(1), leaf(2), leaf(9)])
+            .unwrap();
+    assert_eq!(diverged.verify_against(&account), Err(TreeError::RootMismatch));
+
+    // Behind the account at a root that never existed
+    let unknown = OffchainMerkleTree::<Poseidon>::from_leaves(HEIGHT, &[leaf(9)]).unwrap();
+    assert_eq!(unknown.verify_against(&account), Err(TreeError::UnknownRoot));
+
+    // Ahead of the account
+    let ahead = OffchainMerkleTree::<Poseidon>::from_leaves(
+        HEIGHT,
+        &[leaf(0), leaf(1), leaf(2), leaf(3), leaf(4)],
+    )
+    .unwrap();
+    assert_eq!(
+        ahead.verify_against(&account),
+        Err(TreeError::LeafCountMismatch { leaves: 5, next_index: 4 })
+    );
+}
+
+#[test]
+fn test_leaves_since_root_is_the_missing_delta() {
+    let mut account = create_test_account();
+    account.version = MerkleTreeAccount::VERSION;
+    MerkleTree::initialize::<Poseidon>(&mut account).unwrap();
+    for i in 0..2 {
+        MerkleTree::append::<Poseidon>(leaf(i), &mut account).unwrap();
+    }
+    let mut wallet = OffchainMerkleTree::<Poseidon>::from_leaves(HEIGHT, &[leaf(0), leaf(1)]).unwrap();
+    let synced_root = wallet.root();
+    for i in 2..5 {
+        MerkleTree::append::<Poseidon>(leaf(i), &mut account).unwrap();
+    }
+
+    // Appending only the delta brings the wallet back in sync
+    let missing = leaves_since_root(&account, synced_root).unwrap();
+    assert_eq!(missing, 2..5);
+    for i in missing {
+        wallet.append(leaf(i)).unwrap();
+    }
+    assert_eq!(wallet.root(), account.root);
+    assert_eq!(leaves_since_root(&account, account.root), Ok(5..5));
+
+    assert_eq!(leaves_since_root(&account, [9u8; 32]), Err(TreeError::UnknownRoot));
+    account.version = 2;
+    assert_eq!(leaves_since_root(&account, synced_root), Err(TreeError::UnknownRootRange));
+}
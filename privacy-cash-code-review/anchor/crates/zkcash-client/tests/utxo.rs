@@ -0,0 +1,52 @@
+use ark_bn254::Fr;
+use ark_ff::{BigInteger, Field, PrimeField};
+use light_hasher::{Hasher, Poseidon};
+use zkcash_client::utxo::sol_mint_address;
+use zkcash_client::{Keypair, Utxo};
+
+fn be(value: Fr) -> [u8; 32] {
+    value.into_bigint().to_bytes_be().try_into().unwrap()
+}
+
+#[test]
+fn test_sol_mint_address_is_decimal_field_element() {
+    // 11111111111111111111111111111112 = (10^32 - 1) / 9 + 1
+    let expected = Fr::from(10u64).pow([32]) - Fr::from(1u64);
+    let expected = expected * Fr::from(9u64).inverse().unwrap() + Fr::from(1u64);
+    assert_eq!(sol_mint_address(), expected);
+}
+
+#[test]
+fn test_commitment_and_nullifier_follow_the_circuit() {
+    let keypair = Keypair::new(Fr::from(42u64)).unwrap();
+    assert_eq!(keypair.pubkey, Fr::from_be_bytes_mod_order(&Poseidon::hash(&be(Fr::from(42u64))).unwrap()));
+
+    let utxo = Utxo::new(1_000, keypair, Fr::from(7u64)).with_index(3);
+    let commitment = Poseidon::hashv(&[
+        &be(Fr::from(1_000u64)),
+        &be(keypair.pubkey),
+        &be(Fr::from(7u64)),
+        &be(sol_mint_address()),
+    ])
+    .unwrap();
+    assert_eq!(utxo.commitment_bytes().unwrap(), commitment);
+
+    let signature = Poseidon::hashv(&[&be(keypair.privkey), &commitment, &be(Fr::from(3u64))]).unwrap();
+    let nullifier = Poseidon::hashv(&[&commitment, &be(Fr::from(3u64)), &signature]).unwrap();
+    assert_eq!(utxo.nullifier_bytes().unwrap(), nullifier);
+}
+
+#[test]
+fn test_nullifier_depends_on_index_and_key() {
+    let keypair = Keypair::new(Fr::from(1u64)).unwrap();
+    let other = Keypair::new(Fr::from(2u64)).unwrap();
+    let utxo = Utxo::new(5, keypair, Fr::from(9u64));
+
+    assert_ne!(utxo.nullifier().unwrap(), utxo.with_index(1).nullifier().unwrap());
+    assert_ne!(
+        utxo.nullifier().unwrap(),
+        Utxo::new(5, other, Fr::from(9u64)).nullifier().unwrap()
+    );
+    // The commitment doesn't depend on the tree position
+    assert_eq!(utxo.commitment().unwrap(), utxo.with_index(1).commitment().unwrap());
+}
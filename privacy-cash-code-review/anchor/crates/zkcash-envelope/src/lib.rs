@@ -0,0 +1,203 @@
+//! Versioned envelope for the encrypted outputs stored in `CommitmentAccount`s.
+//!
+//! ```text
+//! offset  size  field
+//! 0       1     version          (ENVELOPE_VERSION)
+//! 1       1     scheme id        (see `Scheme`)
+//! 2       32    ephemeral key    (all zeros for symmetric schemes)
+//! 34      2     ciphertext len   (u16, little-endian)
+//! 36      len   ciphertext
+//! ```
+//!
+//! The program validates envelopes on-chain, wallets use the same codec to build them and
+//! to skip outputs they can't decrypt without attempting decryption.
+use thiserror::Error;
+
+pub const ENVELOPE_VERSION: u8 = 1;
+pub const EPHEMERAL_KEY_LEN: usize = 32;
+pub const HEADER_LEN: usize = 2 + EPHEMERAL_KEY_LEN + 2;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum EnvelopeError {
+    #[error("Envelope is shorter than its header")]
+    Truncated,
+
+    #[error("Unsupported envelope version {0}")]
+    UnsupportedVersion(u8),
+
+    #[error("Unknown encryption scheme {0}")]
+    UnknownScheme(u8),
+
+    #[error("Ciphertext length does not match the envelope length")]
+    CiphertextLengthMismatch,
+
+    #[error("Ciphertext is empty")]
+    EmptyCiphertext,
+
+    #[error("Ciphertext is longer than u16::MAX bytes")]
+    CiphertextTooLong,
+}
+
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scheme {
+    /// The wallet's symmetric scheme: aes-128-ctr with a truncated HMAC-SHA256 tag.
+    Aes128CtrHmacSha256 = 1,
+    /// Asymmetric scheme for outputs addressed to someone else's X25519 key.
+    X25519ChaCha20Poly1305 = 2,
+}
+
+impl TryFrom<u8> for Scheme {
+    type Error = EnvelopeError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(Scheme::Aes128CtrHmacSha256),
+            2 => Ok(Scheme::X25519ChaCha20Poly1305),
+            other => Err(EnvelopeError::UnknownScheme(other)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Envelope<'a> {
+    pub scheme: Scheme,
+    pub ephemeral_key: [u8; EPHEMERAL_KEY_LEN],
+    pub ciphertext: &'a [u8],
+}
+
+impl<'a> Envelope<'a> {
+    pub fn new(
+        scheme: Scheme,
+        ephemeral_key: [u8; EPHEMERAL_KEY_LEN],
+        ciphertext: &'a [u8],
+    ) -> Result<Self, EnvelopeError> {
+        if ciphertext.is_empty() {
+            return Err(EnvelopeError::EmptyCiphertext);
+        }
+        if ciphertext.len() > u16::MAX as usize {
+            return Err(EnvelopeError::CiphertextTooLong);
+        }
+
+        Ok(Envelope {
+            scheme,
+            ephemeral_key,
+            ciphertext,
+        })
+    }
+
+    /// Parses and validates an envelope without copying the ciphertext.
+    pub fn decode(bytes: &'a [u8]) -> Result<Self, EnvelopeError> {
+        if bytes.len() < HEADER_LEN {
+            return Err(EnvelopeError::Truncated);
+        }
+
+        if bytes[0] != ENVELOPE_VERSION {
+            return Err(EnvelopeError::UnsupportedVersion(bytes[0]));
+        }
+
+        let scheme = Scheme::try_from(bytes[1])?;
+
+        let mut ephemeral_key = [0u8; EPHEMERAL_KEY_LEN];
+        ephemeral_key.copy_from_slice(&bytes[2..2 + EPHEMERAL_KEY_LEN]);
+
+        let ciphertext_len = u16::from_le_bytes([bytes[HEADER_LEN - 2], bytes[HEADER_LEN - 1]]) as usize;
+        let ciphertext = &bytes[HEADER_LEN..];
+        if ciphertext.len() != ciphertext_len {
+            return Err(EnvelopeError::CiphertextLengthMismatch);
+        }
+
+        Envelope::new(scheme, ephemeral_key, ciphertext)
+    }
+
+    pub fn encoded_len(&self) -> usize {
+        HEADER_LEN + self.ciphertext.len()
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.encoded_len());
+        bytes.push(ENVELOPE_VERSION);
+        bytes.push(self.scheme as u8);
+        bytes.extend_from_slice(&self.ephemeral_key);
+        bytes.extend_from_slice(&(self.ciphertext.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(self.ciphertext);
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_envelope() -> Vec<u8> {
+        Envelope::new(Scheme::X25519ChaCha20Poly1305, [7u8; EPHEMERAL_KEY_LEN], b"ciphertext")
+            .unwrap()
+            .encode()
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let bytes = sample_envelope();
+        assert_eq!(bytes.len(), HEADER_LEN + 10);
+
+        let envelope = Envelope::decode(&bytes).unwrap();
+        assert_eq!(envelope.scheme, Scheme::X25519ChaCha20Poly1305);
+        assert_eq!(envelope.ephemeral_key, [7u8; EPHEMERAL_KEY_LEN]);
+        assert_eq!(envelope.ciphertext, b"ciphertext");
+        assert_eq!(envelope.encode(), bytes);
+    }
+
+    #[test]
+    fn test_truncated_header() {
+        let bytes = sample_envelope();
+        assert_eq!(Envelope::decode(&bytes[..HEADER_LEN - 1]), Err(EnvelopeError::Truncated));
+        assert_eq!(Envelope::decode(&[]), Err(EnvelopeError::Truncated));
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let mut bytes = sample_envelope();
+        bytes[0] = ENVELOPE_VERSION + 1;
+        assert_eq!(Envelope::decode(&bytes), Err(EnvelopeError::UnsupportedVersion(ENVELOPE_VERSION + 1)));
+    }
+
+    #[test]
+    fn test_unknown_scheme() {
+        let mut bytes = sample_envelope();
+        bytes[1] = 0;
+        assert_eq!(Envelope::decode(&bytes), Err(EnvelopeError::UnknownScheme(0)));
+    }
+
+    #[test]
+    fn test_ciphertext_length_mismatch() {
+        let mut bytes = sample_envelope();
+        bytes.push(0);
+        assert_eq!(Envelope::decode(&bytes), Err(EnvelopeError::CiphertextLengthMismatch));
+
+        let bytes = sample_envelope();
+        assert_eq!(Envelope::decode(&bytes[..bytes.len() - 1]), Err(EnvelopeError::CiphertextLengthMismatch));
+    }
+
+    #[test]
+    fn test_empty_ciphertext() {
+        assert_eq!(
+            Envelope::new(Scheme::Aes128CtrHmacSha256, [0u8; EPHEMERAL_KEY_LEN], &[]),
+            Err(EnvelopeError::EmptyCiphertext)
+        );
+
+        let mut bytes = sample_envelope();
+        bytes.truncate(HEADER_LEN);
+        bytes[HEADER_LEN - 2] = 0;
+        bytes[HEADER_LEN - 1] = 0;
+        assert_eq!(Envelope::decode(&bytes), Err(EnvelopeError::EmptyCiphertext));
+    }
+
+    #[test]
+    fn test_ciphertext_too_long() {
+        let ciphertext = vec![0u8; u16::MAX as usize + 1];
+        assert_eq!(
+            Envelope::new(Scheme::Aes128CtrHmacSha256, [0u8; EPHEMERAL_KEY_LEN], &ciphertext),
+            Err(EnvelopeError::CiphertextTooLong)
+        );
+    }
+}
@@ -0,0 +1,170 @@
+//! Constant rate SOL to SPL token AMM, standing in for a real swap program in the zkcash swap
+//! adapter tests. Pools mint their output token at a settable rate, so tests can move the price
+//! between building and submitting a swap to exercise the `min_out` guard.
+//!
+//! Not meant for deployment: anyone can create a pool and the pool authority sets the rate.
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::invoke_signed;
+
+declare_id!("GBrnRyk1v6Y4RtLWCnMenqbcmwCMtUDNRFJCnamE487T");
+
+pub const POOL_SEED: &[u8] = b"pool";
+pub const TOKEN_PROGRAM_ID: Pubkey = anchor_lang::pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+
+const LAMPORTS_PER_SOL: u128 = 1_000_000_000;
+const MINT_TO_INSTRUCTION: u8 = 7;
+
+#[program]
+pub mod mock_amm {
+    use super::*;
+
+    /**
+     * Create the pool of `mint`, whose mint authority must already be the pool PDA.
+     * `rate` is in base units of `mint` per SOL.
+     */
+    pub fn initialize_pool(ctx: Context<InitializePool>, rate: u64) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        pool.authority = ctx.accounts.authority.key();
+        pool.mint = ctx.accounts.mint.key();
+        pool.rate = rate;
+        pool.bump = ctx.bumps.pool;
+        Ok(())
+    }
+
+    /**
+     * Move the price of the pool. Only the pool authority can call this.
+     */
+    pub fn set_rate(ctx: Context<SetRate>, rate: u64) -> Result<()> {
+        ctx.accounts.pool.rate = rate;
+        Ok(())
+    }
+
+    /**
+     * Take `amount_in` lamports from `user` into the pool and mint `amount_in * rate / 1 SOL`
+     * to `destination`, rounded down.
+     */
+    pub fn swap(ctx: Context<Swap>, amount_in: u64) -> Result<()> {
+        let pool = &ctx.accounts.pool;
+        let amount_out: u64 = (amount_in as u128 * pool.rate as u128 / LAMPORTS_PER_SOL)
+            .try_into()
+            .map_err(|_| MockAmmError::AmountOverflow)?;
+
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.user.to_account_info(),
+                    to: ctx.accounts.pool.to_account_info(),
+                },
+            ),
+            amount_in,
+        )?;
+
+        // SPL Token MintTo, built by hand to keep the mock free of token crates
+        let mut data = vec![MINT_TO_INSTRUCTION];
+        data.extend_from_slice(&amount_out.to_le_bytes());
+        let instruction = Instruction {
+            program_id: TOKEN_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(ctx.accounts.mint.key(), false),
+                AccountMeta::new(ctx.accounts.destination.key(), false),
+                AccountMeta::new_readonly(ctx.accounts.pool.key(), true),
+            ],
+            data,
+        };
+        let mint = ctx.accounts.mint.key();
+        invoke_signed(
+            &instruction,
+            &[
+                ctx.accounts.mint.to_account_info(),
+                ctx.accounts.destination.to_account_info(),
+                ctx.accounts.pool.to_account_info(),
+                ctx.accounts.token_program.to_account_info(),
+            ],
+            &[&[POOL_SEED, mint.as_ref(), &[ctx.accounts.pool.bump]]],
+        )?;
+
+        msg!("Swapped {} lamports for {}", amount_in, amount_out);
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct InitializePool<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = Pool::SPACE,
+        seeds = [POOL_SEED, mint.key().as_ref()],
+        bump
+    )]
+    pub pool: Account<'info, Pool>,
+
+    /// CHECK: only used as a seed, MintTo fails unless the pool is its mint authority
+    pub mint: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetRate<'info> {
+    #[account(
+        mut,
+        seeds = [POOL_SEED, pool.mint.as_ref()],
+        bump = pool.bump,
+        has_one = authority
+    )]
+    pub pool: Account<'info, Pool>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Swap<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [POOL_SEED, pool.mint.as_ref()],
+        bump = pool.bump,
+        has_one = mint
+    )]
+    pub pool: Account<'info, Pool>,
+
+    #[account(mut)]
+    /// CHECK: must be the pool mint, enforced by has_one
+    pub mint: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    /// CHECK: a token account of the pool mint, checked by the token program
+    pub destination: UncheckedAccount<'info>,
+
+    /// CHECK: the SPL Token program
+    #[account(address = TOKEN_PROGRAM_ID)]
+    pub token_program: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[account]
+pub struct Pool {
+    pub authority: Pubkey,
+    pub mint: Pubkey,
+    pub rate: u64, // base units of `mint` per SOL
+    pub bump: u8,
+}
+
+impl Pool {
+    pub const SPACE: usize = 8 + 32 + 32 + 8 + 1;
+}
+
+#[error_code]
+pub enum MockAmmError {
+    #[msg("Swap output does not fit in a u64")]
+    AmountOverflow,
+}
@@ -0,0 +1,83 @@
+use anchor_lang::prelude::*;
+use crate::ErrorCode;
+
+pub const BLOCKLIST_SEED: &[u8] = b"blocklist";
+
+/**
+ * The blocklist is sharded into one PDA per address, seeded with [BLOCKLIST_SEED, address].
+ * An address is listed iff its entry PDA holds data, so a lookup is a single
+ * account read with no search, and unlisted addresses cost no rent at all.
+ *
+ * Only this program can allocate data at a PDA it derives, so an attacker can at most
+ * send lamports to an entry address, which does not make it look listed.
+ */
+pub fn is_listed(entry: &AccountInfo) -> bool {
+    !entry.data_is_empty()
+}
+
+pub fn find_blocklist_entry_address(address: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[BLOCKLIST_SEED, address.as_ref()], &crate::ID)
+}
+
+/**
+ * Checks an entry that was not already validated through an Anchor `seeds` constraint,
+ * e.g. when the screened address is only known inside the instruction.
+ */
+pub fn require_not_listed(address: &Pubkey, entry: &AccountInfo) -> Result<()> {
+    let (expected_entry, _) = find_blocklist_entry_address(address);
+    require_keys_eq!(*entry.key, expected_entry, ErrorCode::InvalidBlocklistEntry);
+    require!(!is_listed(entry), ErrorCode::AddressBlocked);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(address: &Pubkey, entry_key: Pubkey, mut data: Vec<u8>) -> Result<()> {
+        let mut lamports = 1_000_000;
+        let owner = crate::ID;
+        let entry = AccountInfo::new(&entry_key, false, false, &mut lamports, &mut data, &owner, false, 0);
+        require_not_listed(address, &entry)
+    }
+
+    #[test]
+    fn test_is_listed_only_with_data() {
+        let key = Pubkey::new_unique();
+        let owner = crate::ID;
+        let mut lamports = 1_000_000;
+        let mut data = vec![];
+        // Lamports sent to an unlisted entry address don't list it
+        let entry = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+        assert!(!is_listed(&entry));
+
+        let mut lamports = 1_000_000;
+        let mut data = vec![0u8; 8 + 33];
+        let entry = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+        assert!(is_listed(&entry));
+    }
+
+    #[test]
+    fn test_require_not_listed() {
+        let address = Pubkey::new_unique();
+        let (entry_key, _) = find_blocklist_entry_address(&address);
+
+        assert!(check(&address, entry_key, vec![]).is_ok());
+
+        let err = check(&address, entry_key, vec![0u8; 8 + 33]).unwrap_err();
+        assert!(err.to_string().contains("AddressBlocked"));
+    }
+
+    #[test]
+    fn test_require_not_listed_checks_entry_address() {
+        let address = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+        let (other_entry, _) = find_blocklist_entry_address(&other);
+
+        // An empty entry of another address can't stand in for a listed one
+        let err = check(&address, other_entry, vec![]).unwrap_err();
+        assert!(err.to_string().contains("InvalidBlocklistEntry"));
+        let err = check(&address, Pubkey::new_unique(), vec![]).unwrap_err();
+        assert!(err.to_string().contains("InvalidBlocklistEntry"));
+    }
+}
@@ -0,0 +1,181 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::invoke;
+use crate::{ErrorCode, WithdrawCallback};
+
+pub const MAX_CALLBACK_ACCOUNTS: usize = 8;
+pub const MAX_CALLBACK_DATA_LEN: usize = 256;
+
+pub fn validate_callback(callback: &WithdrawCallback) -> Result<()> {
+    require!(
+        callback.accounts.len() <= MAX_CALLBACK_ACCOUNTS && callback.data.len() <= MAX_CALLBACK_DATA_LEN,
+        ErrorCode::CallbackTooLarge
+    );
+    Ok(())
+}
+
+/**
+ * Invokes the recipient program after a withdrawal has settled.
+ *
+ * `callback_accounts` must be the callback program followed by exactly the accounts listed
+ * in `callback.accounts`, in order. Since the callback is bound in the ext data hash, a
+ * relayer can neither change the program, the accounts nor the data.
+ *
+ * The callee never receives signer privileges, so a callback cannot act on behalf of the
+ * relayer even if it lists the relayer's key, and accounts owned by this program are
+ * rejected so pool state never leaves the instruction.
+ */
+pub fn invoke_withdraw_callback<'info>(
+    callback: &WithdrawCallback,
+    callback_accounts: &[AccountInfo<'info>],
+) -> Result<()> {
+    validate_callback(callback)?;
+    require!(
+        callback_accounts.len() == callback.accounts.len() + 1,
+        ErrorCode::InvalidCallbackAccounts
+    );
+
+    let program_account = &callback_accounts[0];
+    require!(
+        program_account.key() == callback.program_id && program_account.executable,
+        ErrorCode::InvalidCallbackAccounts
+    );
+    require!(callback.program_id != crate::ID, ErrorCode::InvalidCallbackAccounts);
+
+    let mut account_metas = Vec::with_capacity(callback.accounts.len());
+    for (account_info, expected_key) in callback_accounts[1..].iter().zip(callback.accounts.iter()) {
+        require!(
+            account_info.key() == *expected_key && *account_info.owner != crate::ID,
+            ErrorCode::InvalidCallbackAccounts
+        );
+        account_metas.push(if account_info.is_writable {
+            AccountMeta::new(*expected_key, false)
+        } else {
+            AccountMeta::new_readonly(*expected_key, false)
+        });
+    }
+
+    let instruction = Instruction {
+        program_id: callback.program_id,
+        accounts: account_metas,
+        data: callback.data.clone(),
+    };
+    invoke(&instruction, callback_accounts)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestAccount {
+        key: Pubkey,
+        owner: Pubkey,
+        lamports: u64,
+        data: Vec<u8>,
+        executable: bool,
+    }
+
+    impl TestAccount {
+        fn new(key: Pubkey, owner: Pubkey, executable: bool) -> Self {
+            TestAccount { key, owner, lamports: 1_000_000, data: vec![], executable }
+        }
+
+        fn info(&mut self) -> AccountInfo<'_> {
+            AccountInfo::new(&self.key, false, true, &mut self.lamports, &mut self.data, &self.owner, self.executable, 0)
+        }
+    }
+
+    /// The callback program followed by two accounts, matching `callback()`.
+    fn callback_accounts(callback: &WithdrawCallback) -> Vec<TestAccount> {
+        let mut accounts = vec![TestAccount::new(callback.program_id, Pubkey::new_unique(), true)];
+        for key in &callback.accounts {
+            accounts.push(TestAccount::new(*key, Pubkey::new_unique(), false));
+        }
+        accounts
+    }
+
+    fn callback() -> WithdrawCallback {
+        WithdrawCallback {
+            program_id: Pubkey::new_unique(),
+            accounts: vec![Pubkey::new_unique(), Pubkey::new_unique()],
+            data: vec![1, 2, 3],
+        }
+    }
+
+    fn invoke_with(callback: &WithdrawCallback, accounts: &mut [TestAccount]) -> Result<()> {
+        let infos: Vec<AccountInfo> = accounts.iter_mut().map(TestAccount::info).collect();
+        invoke_withdraw_callback(callback, &infos)
+    }
+
+    fn assert_invalid_accounts(result: Result<()>) {
+        let err = result.unwrap_err();
+        assert!(err.to_string().contains("InvalidCallbackAccounts"), "{}", err);
+    }
+
+    #[test]
+    fn test_validate_callback_limits() {
+        let mut callback = callback();
+        callback.accounts = vec![Pubkey::new_unique(); MAX_CALLBACK_ACCOUNTS];
+        callback.data = vec![0; MAX_CALLBACK_DATA_LEN];
+        assert!(validate_callback(&callback).is_ok());
+
+        callback.data.push(0);
+        assert!(validate_callback(&callback).unwrap_err().to_string().contains("CallbackTooLarge"));
+        callback.data.pop();
+        callback.accounts.push(Pubkey::new_unique());
+        assert!(validate_callback(&callback).unwrap_err().to_string().contains("CallbackTooLarge"));
+    }
+
+    #[test]
+    fn test_rejects_wrong_program() {
+        let callback = callback();
+
+        // Another program than the one bound in the ext data hash
+        let mut accounts = callback_accounts(&callback);
+        accounts[0].key = Pubkey::new_unique();
+        assert_invalid_accounts(invoke_with(&callback, &mut accounts));
+
+        // The right key, but not a program
+        let mut accounts = callback_accounts(&callback);
+        accounts[0].executable = false;
+        assert_invalid_accounts(invoke_with(&callback, &mut accounts));
+
+        // This program can't call itself back
+        let mut callback = callback;
+        callback.program_id = crate::ID;
+        let mut accounts = callback_accounts(&callback);
+        assert_invalid_accounts(invoke_with(&callback, &mut accounts));
+    }
+
+    #[test]
+    fn test_rejects_mismatched_accounts() {
+        let callback = callback();
+
+        // Missing and extra accounts
+        let mut accounts = callback_accounts(&callback);
+        accounts.pop();
+        assert_invalid_accounts(invoke_with(&callback, &mut accounts));
+        let mut accounts = callback_accounts(&callback);
+        accounts.push(TestAccount::new(Pubkey::new_unique(), Pubkey::new_unique(), false));
+        assert_invalid_accounts(invoke_with(&callback, &mut accounts));
+
+        // A substituted account, and the listed accounts out of order
+        let mut accounts = callback_accounts(&callback);
+        accounts[2].key = Pubkey::new_unique();
+        assert_invalid_accounts(invoke_with(&callback, &mut accounts));
+        let mut accounts = callback_accounts(&callback);
+        accounts.swap(1, 2);
+        assert_invalid_accounts(invoke_with(&callback, &mut accounts));
+    }
+
+    #[test]
+    fn test_rejects_program_owned_accounts() {
+        // Even when listed in the callback, pool state is never handed to the callee
+        let callback = callback();
+        let mut accounts = callback_accounts(&callback);
+        accounts[1].owner = crate::ID;
+        assert_invalid_accounts(invoke_with(&callback, &mut accounts));
+    }
+}
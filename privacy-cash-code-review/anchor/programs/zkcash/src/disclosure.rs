@@ -0,0 +1,52 @@
+use anchor_lang::prelude::*;
+use zkcash_envelope::{Envelope, Scheme};
+use crate::{AuditorDisclosure, ErrorCode, ViewingKeyRegistry};
+
+pub const VIEWING_KEY_SEED: &[u8] = b"viewing_key";
+pub const MAX_AUDITOR_CIPHERTEXT_LEN: usize = 256;
+
+/**
+ * Emitted by `transact` when the ext data carries an auditor disclosure, so auditors can
+ * collect the ciphertexts addressed to them from the logs, including for CPI calls.
+ * Nothing is stored on-chain for them, disclosures cost no rent.
+ */
+#[event]
+pub struct AuditorDisclosureEvent {
+    pub viewing_key: [u8; 32],
+    pub commitments: [[u8; 32]; 2],
+    pub indices: [u64; 2],
+    pub ext_amount: i64,
+    pub fee: u64,
+    pub encrypted_outputs: [Vec<u8>; 2],
+}
+
+/**
+ * The auditor ciphertexts are bound by the ext data hash, so a relayer can't strip or swap them.
+ * They only need to be addressed to a registered viewing key and use the asymmetric envelope
+ * scheme. Whether they decrypt to the actual outputs is checked off-chain by the auditor against
+ * the commitments.
+ */
+pub fn validate_auditor_disclosure(
+    disclosure: &AuditorDisclosure,
+    registry: &ViewingKeyRegistry,
+) -> Result<()> {
+    require!(
+        disclosure.viewing_key == registry.viewing_key,
+        ErrorCode::ViewingKeyNotRegistered
+    );
+
+    for encrypted_output in &disclosure.encrypted_outputs {
+        require!(
+            encrypted_output.len() <= MAX_AUDITOR_CIPHERTEXT_LEN,
+            ErrorCode::InvalidAuditorCiphertext
+        );
+        let envelope = Envelope::decode(encrypted_output)
+            .map_err(|_| error!(ErrorCode::InvalidAuditorCiphertext))?;
+        require!(
+            envelope.scheme == Scheme::X25519ChaCha20Poly1305,
+            ErrorCode::InvalidAuditorCiphertext
+        );
+    }
+
+    Ok(())
+}
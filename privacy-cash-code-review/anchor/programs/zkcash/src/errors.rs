@@ -22,4 +22,16 @@ pub enum Groth16Error {
     
     #[error("Proof verification failed")]
     ProofVerificationFailed,
+    
+    #[error("Point coordinate is not below the base field modulus")]
+    NonCanonicalPointEncoding,
+    
+    #[error("Point is the identity")]
+    IdentityPoint,
+    
+    #[error("Point is not on the curve")]
+    PointNotOnCurve,
+    
+    #[error("Point is not in the prime order subgroup")]
+    PointNotInSubgroup,
 } 
\ No newline at end of file
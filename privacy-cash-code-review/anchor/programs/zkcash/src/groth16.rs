@@ -28,7 +28,9 @@
 //! See functional test for a running example how to use this library.
 //!
 use crate::errors::Groth16Error;
+use ark_bn254::{G1Affine, G2Affine};
 use ark_ff::PrimeField;
+use ark_serialize::{CanonicalDeserialize, Compress, Validate};
 use num_bigint::BigUint;
 use solana_bn254::prelude::{alt_bn128_addition, alt_bn128_multiplication, alt_bn128_pairing};
 
@@ -76,6 +78,11 @@ impl<const NR_INPUTS: usize> Groth16Verifier<'_, NR_INPUTS> {
             return Err(Groth16Error::InvalidPublicInputsLength);
         }
 
+        // The syscalls accept some encodings a prover must not be able to use, see validate_g1_point
+        validate_g1_point(proof_a)?;
+        validate_g2_point(proof_b)?;
+        validate_g1_point(proof_c)?;
+
         Ok(Groth16Verifier {
             proof_a,
             proof_b,
@@ -149,4 +156,71 @@ impl<const NR_INPUTS: usize> Groth16Verifier<'_, NR_INPUTS> {
 pub fn is_less_than_bn254_field_size_be(bytes: &[u8; 32]) -> bool {
     let bigint = BigUint::from_bytes_be(bytes);
     bigint < ark_bn254::Fr::MODULUS.into()
+}
+
+/// Whether a big-endian point coordinate is below the base field modulus.
+pub fn is_less_than_bn254_base_field_size_be(bytes: &[u8; 32]) -> bool {
+    let bigint = BigUint::from_bytes_be(bytes);
+    bigint < ark_bn254::Fq::MODULUS.into()
+}
+
+fn validate_encoding(point: &[u8]) -> Result<(), Groth16Error> {
+    // All zeros is how the syscalls encode the identity
+    if point.iter().all(|byte| *byte == 0) {
+        return Err(Groth16Error::IdentityPoint);
+    }
+    for coordinate in point.chunks_exact(32) {
+        if !is_less_than_bn254_base_field_size_be(coordinate.try_into().unwrap()) {
+            return Err(Groth16Error::NonCanonicalPointEncoding);
+        }
+    }
+    Ok(())
+}
+
+/**
+ * Checks a G1 point in the big-endian x || y encoding of the alt_bn128 syscalls: coordinates
+ * must be canonical, since the syscalls reduce them, and the point must be on the curve and not
+ * the identity. G1 has cofactor 1, so the subgroup check can't fail but is kept for symmetry.
+ */
+pub fn validate_g1_point(point: &[u8; 64]) -> Result<(), Groth16Error> {
+    validate_encoding(point)?;
+
+    // arkworks takes each coordinate little-endian
+    let mut le_bytes = *point;
+    le_bytes[..32].reverse();
+    le_bytes[32..].reverse();
+    let point = G1Affine::deserialize_with_mode(&le_bytes[..], Compress::No, Validate::No)
+        .map_err(|_| Groth16Error::NonCanonicalPointEncoding)?;
+
+    if !point.is_on_curve() {
+        return Err(Groth16Error::PointNotOnCurve);
+    }
+    if !point.is_in_correct_subgroup_assuming_on_curve() {
+        return Err(Groth16Error::PointNotInSubgroup);
+    }
+    Ok(())
+}
+
+/**
+ * Checks a G2 point in the big-endian x.c1 || x.c0 || y.c1 || y.c0 encoding of the alt_bn128
+ * syscalls, as `validate_g1_point`. The twist has a large cofactor, so points on the curve
+ * outside the prime order subgroup exist and must be rejected here.
+ */
+pub fn validate_g2_point(point: &[u8; 128]) -> Result<(), Groth16Error> {
+    validate_encoding(point)?;
+
+    // Reversing each 64-byte half turns c1 || c0 big-endian into c0 || c1 little-endian
+    let mut le_bytes = *point;
+    le_bytes[..64].reverse();
+    le_bytes[64..].reverse();
+    let point = G2Affine::deserialize_with_mode(&le_bytes[..], Compress::No, Validate::No)
+        .map_err(|_| Groth16Error::NonCanonicalPointEncoding)?;
+
+    if !point.is_on_curve() {
+        return Err(Groth16Error::PointNotOnCurve);
+    }
+    if !point.is_in_correct_subgroup_assuming_on_curve() {
+        return Err(Groth16Error::PointNotInSubgroup);
+    }
+    Ok(())
 } 
\ No newline at end of file
@@ -1,5 +1,4 @@
 use anchor_lang::prelude::*;
-use light_hasher::Poseidon;
 use anchor_lang::solana_program::sysvar::rent::Rent;
 use ark_ff::PrimeField;
 use ark_bn254::Fr;
@@ -10,11 +9,21 @@ pub mod merkle_tree;
 pub mod utils;
 pub mod groth16;
 pub mod errors;
+pub mod blocklist;
+pub mod callback;
+pub mod migration;
+pub mod nullifier_tree;
+pub mod disclosure;
+pub mod referral;
+pub mod swap;
 
-use merkle_tree::MerkleTree;
+use light_hasher::Poseidon;
+use merkle_tree::{MerkleTree, TreeHasher};
+use nullifier_tree::{NullifierInsertProof, NullifierMode, NullifierTree, NULLIFIER_TREE_HEIGHT};
 
 // Constants
 const MERKLE_TREE_HEIGHT: u8 = 26;
+const DEFAULT_MAX_ENCRYPTED_OUTPUT_LEN: u16 = 1024;
 
 #[cfg(any(feature = "localnet", test))]
 pub const ADMIN_PUBKEY: Option<Pubkey> = None;
@@ -24,15 +33,14 @@ pub const ADMIN_PUBKEY: Option<Pubkey> = Some(pubkey!("AWexibGxNFKTa1b5R5MN4PJr9
 
 #[program]
 pub mod zkcash {
-    use crate::utils::{verify_proof, VERIFYING_KEY, SOL_ADDRESS};
+    use crate::utils::{verify_proof, SOL_ADDRESS, VERIFYING_KEY};
 
     use super::*;
 
-    pub fn initialize(ctx: Context<Initialize>) -> Result<()> {
+    pub fn initialize(ctx: Context<Initialize>, nullifier_mode: NullifierMode) -> Result<()> {
         if let Some(admin_key) = ADMIN_PUBKEY {
             require!(ctx.accounts.authority.key().eq(&admin_key), ErrorCode::Unauthorized);
         }
-        
         let tree_account = &mut ctx.accounts.tree_account.load_init()?;
         tree_account.authority = ctx.accounts.authority.key();
         tree_account.next_index = 0;
@@ -41,20 +49,49 @@ pub mod zkcash {
         tree_account.max_deposit_amount = 1_000_000_000_000; // 1000 SOL default limit
         tree_account.height = MERKLE_TREE_HEIGHT; // Hardcoded height
         tree_account.root_history_size = 100; // Hardcoded root history size
+        tree_account.hasher = TreeHasher::Poseidon as u8;
+        tree_account.version = MerkleTreeAccount::VERSION;
 
         MerkleTree::initialize::<Poseidon>(tree_account)?;
         
         let token_account = &mut ctx.accounts.tree_token_account;
         token_account.authority = ctx.accounts.authority.key();
+        token_account.version = TreeTokenAccount::VERSION;
         token_account.bump = ctx.bumps.tree_token_account;
         
         // Initialize global config
         let global_config = &mut ctx.accounts.global_config;
         global_config.authority = ctx.accounts.authority.key();
+        global_config.version = GlobalConfig::VERSION;
         global_config.deposit_fee_rate = 0; // 0% - Free deposits
         global_config.withdrawal_fee_rate = 25; // 0.25% (25 basis points)
         global_config.fee_error_margin = 500; // 5% (500 basis points)
+        global_config.screen_withdrawals = false;
+        global_config.min_encrypted_output_len = 0;
+        global_config.max_encrypted_output_len = DEFAULT_MAX_ENCRYPTED_OUTPUT_LEN;
+        global_config.require_output_envelope = false;
+        global_config.commitment_retention_leaves = 0; // Reclamation disabled by default
+        global_config.commitment_retention_slots = 0;
         global_config.bump = ctx.bumps.global_config;
+        global_config.min_deposit_amount = 0; // No minimum and any amount by default
+        global_config.deposit_denominations = [0; utils::MAX_DEPOSIT_DENOMINATIONS];
+        global_config.require_rent_in_fee = false; // Signer absorbs PDA rent by default
+        global_config.nullifier_mode = nullifier_mode;
+        global_config.withdrawal_delay_threshold = 0; // No delayed withdrawals by default
+        global_config.withdrawal_delay_slots = 0;
+        global_config.emergency = false;
+        global_config.swap_program = Pubkey::default(); // Swaps disabled by default
+        global_config.transfer_fee = 0; // Free internal transfers by default
+        global_config.max_transfers_per_slot = 0;
+        
+        // A fresh pool has no nullifiers to backfill
+        require!(
+            ctx.accounts.nullifier_tree.is_some() == nullifier_mode.uses_tree(),
+            ErrorCode::InvalidNullifierAccounts
+        );
+        if let (Some(nullifier_tree), Some(bump)) = (&ctx.accounts.nullifier_tree, ctx.bumps.nullifier_tree) {
+            nullifier_tree.load_init()?.init(ctx.accounts.authority.key(), bump, 0)?;
+        }
         
         msg!("Sparse Merkle Tree initialized successfully with height: {}, root history size: {}, deposit limit: {} lamports, 
             deposit fee rate: {}, withdrawal fee rate: {}, fee error margin: {}",
@@ -106,20 +143,486 @@ pub mod zkcash {
         Ok(())
     }
 
+    /**
+     * Add an address to the deposit blocklist. Only the authority can call this.
+     */
+    pub fn add_to_blocklist(ctx: Context<AddToBlocklist>, address: Pubkey) -> Result<()> {
+        let blocklist_entry = &mut ctx.accounts.blocklist_entry;
+        blocklist_entry.address = address;
+        blocklist_entry.bump = ctx.bumps.blocklist_entry;
+
+        msg!("Address added to blocklist: {}", address);
+        Ok(())
+    }
+
+    /**
+     * Remove an address from the deposit blocklist. Only the authority can call this.
+     * Closing the entry PDA is what unlists the address, see `blocklist::is_listed`.
+     */
+    pub fn remove_from_blocklist(_ctx: Context<RemoveFromBlocklist>, address: Pubkey) -> Result<()> {
+        msg!("Address removed from blocklist: {}", address);
+        Ok(())
+    }
+
+    /**
+     * Enable or disable blocklist screening of withdrawal recipients. Only the authority can call this.
+     * Deposits are always screened.
+     */
+    pub fn set_withdrawal_screening(ctx: Context<UpdateGlobalConfig>, enabled: bool) -> Result<()> {
+        ctx.accounts.global_config.screen_withdrawals = enabled;
+
+        msg!("Withdrawal recipient screening set to: {}", enabled);
+        Ok(())
+    }
+
+    /**
+     * Update the size bounds and envelope requirement for encrypted outputs. Only the authority can call this.
+     */
+    pub fn update_encrypted_output_rules(
+        ctx: Context<UpdateGlobalConfig>,
+        min_len: u16,
+        max_len: u16,
+        require_envelope: bool,
+    ) -> Result<()> {
+        require!(min_len <= max_len, ErrorCode::InvalidEncryptedOutputBounds);
+        
+        let global_config = &mut ctx.accounts.global_config;
+        global_config.min_encrypted_output_len = min_len;
+        global_config.max_encrypted_output_len = max_len;
+        global_config.require_output_envelope = require_envelope;
+        
+        msg!("Encrypted output rules updated: min length {}, max length {}, envelope required: {}", min_len, max_len, require_envelope);
+        Ok(())
+    }
+
+    /**
+     * Update the minimum deposit amount (0 for none) and the allowed deposit denominations
+     * (empty to allow any amount). Only the authority can call this.
+     */
+    pub fn update_deposit_rules(
+        ctx: Context<UpdateGlobalConfig>,
+        min_deposit_amount: u64,
+        denominations: Vec<u64>,
+    ) -> Result<()> {
+        let packed_denominations = utils::pack_deposit_denominations(min_deposit_amount, &denominations)?;
+        
+        let global_config = &mut ctx.accounts.global_config;
+        global_config.min_deposit_amount = min_deposit_amount;
+        global_config.deposit_denominations = packed_denominations;
+        
+        msg!("Deposit rules updated: minimum {} lamports, denominations {:?}", min_deposit_amount, denominations);
+        Ok(())
+    }
+
+    /**
+     * Require the fee of relayed transactions to also cover the rent of the PDAs paid by the
+     * signer, see `utils::transact_rent`. Self-funded deposits are exempt since the signer pays
+     * its own rent. Only the authority can call this.
+     */
+    pub fn set_rent_fee_mode(ctx: Context<UpdateGlobalConfig>, enabled: bool) -> Result<()> {
+        let global_config = &mut ctx.accounts.global_config;
+        global_config.require_rent_in_fee = enabled;
+        
+        msg!("Rent in fee requirement set to: {}", enabled);
+        Ok(())
+    }
+    
+    /**
+     * Update which withdrawals are delayed: those withdrawing more than `threshold` lamports wait
+     * `delay_slots` slots in a `PendingWithdrawal` before they can be finalized. A threshold of 0
+     * disables delays. Withdrawals already pending keep their release slot.
+     * Only the authority can call this.
+     */
+    pub fn update_withdrawal_delay(
+        ctx: Context<UpdateGlobalConfig>,
+        threshold: u64,
+        delay_slots: u64,
+    ) -> Result<()> {
+        let global_config = &mut ctx.accounts.global_config;
+        global_config.withdrawal_delay_threshold = threshold;
+        global_config.withdrawal_delay_slots = delay_slots;
+        
+        msg!("Withdrawal delay updated to: {} slots above {} lamports", delay_slots, threshold);
+        Ok(())
+    }
+    
+    /**
+     * Update the program withdrawals with `ext_data.swap` are swapped through. The default pubkey
+     * disables swaps. Only the authority can call this.
+     */
+    pub fn update_swap_program(ctx: Context<UpdateGlobalConfig>, swap_program: Pubkey) -> Result<()> {
+        let global_config = &mut ctx.accounts.global_config;
+        global_config.swap_program = swap_program;
+        
+        msg!("Swap program updated to: {}", swap_program);
+        Ok(())
+    }
+    
+    /**
+     * Update the rules for internal transfers and consolidations (ext_amount == 0): the flat fee
+     * in lamports they must pay, and how many are accepted per slot (0 for no cap). Their amounts
+     * are hidden, so the fee can't be a rate. Only the authority can call this.
+     */
+    pub fn update_transfer_rules(
+        ctx: Context<UpdateGlobalConfig>,
+        transfer_fee: u64,
+        max_transfers_per_slot: u16,
+    ) -> Result<()> {
+        let global_config = &mut ctx.accounts.global_config;
+        global_config.transfer_fee = transfer_fee;
+        global_config.max_transfers_per_slot = max_transfers_per_slot;
+        
+        msg!("Transfer rules updated: fee {} lamports, at most {} per slot", transfer_fee, max_transfers_per_slot);
+        Ok(())
+    }
+    
+    /**
+     * Declare or end an emergency. While it is active, pending withdrawals can't be finalized
+     * and the authority can veto them. Only the authority can call this.
+     */
+    pub fn set_emergency(ctx: Context<UpdateGlobalConfig>, active: bool) -> Result<()> {
+        let global_config = &mut ctx.accounts.global_config;
+        global_config.emergency = active;
+        
+        msg!("Emergency set to: {}", active);
+        Ok(())
+    }
+    
+    /**
+     * Pay out a pending withdrawal once its delay has elapsed, releasing its reserved lamports,
+     * and refund the PDA rent to the payer of the request. Anyone can call this, e.g. the relayer
+     * that submitted the request.
+     */
+    pub fn finalize_withdrawal(ctx: Context<FinalizeWithdrawal>) -> Result<()> {
+        let pending_withdrawal = &ctx.accounts.pending_withdrawal;
+        require!(
+            utils::is_withdrawal_releasable(
+                pending_withdrawal.release_slot,
+                Clock::get()?.slot,
+                ctx.accounts.global_config.emergency,
+            ),
+            ErrorCode::WithdrawalNotReleasable
+        );
+        
+        let tree_token_account = &mut ctx.accounts.tree_token_account;
+        tree_token_account.release(pending_withdrawal.amount);
+        let tree_token_account_info = tree_token_account.to_account_info();
+        let rent_exempt_minimum = Rent::get()?.minimum_balance(tree_token_account_info.data_len());
+        let total_required = pending_withdrawal.amount
+            .checked_add(rent_exempt_minimum)
+            .ok_or(ErrorCode::ArithmeticOverflow)?
+            .checked_add(tree_token_account.reserved_lamports)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        require!(
+            tree_token_account_info.lamports() >= total_required,
+            ErrorCode::InsufficientFundsForWithdrawal
+        );
+        
+        utils::transfer_lamports(
+            &tree_token_account_info,
+            &ctx.accounts.recipient.to_account_info(),
+            pending_withdrawal.amount,
+        )?;
+        
+        msg!("Withdrawal of {} lamports to {} finalized", pending_withdrawal.amount, pending_withdrawal.recipient);
+        Ok(())
+    }
+    
+    /**
+     * Cancel a pending withdrawal during an active emergency. The PDA rent is refunded to the
+     * payer of the request. Its nullifiers stay spent and no note holds the amount, so the
+     * lamports stay reserved in the pool as vetoed funds, which only `release_vetoed_funds`
+     * can pay out. Only the authority can call this.
+     */
+    pub fn veto_withdrawal(ctx: Context<VetoWithdrawal>) -> Result<()> {
+        require!(ctx.accounts.global_config.emergency, ErrorCode::NoActiveEmergency);
+        
+        let pending_withdrawal = &ctx.accounts.pending_withdrawal;
+        let tree_token_account = &mut ctx.accounts.tree_token_account;
+        // Also reserves withdrawals requested before reservations existed, which `release` skips
+        tree_token_account.release(pending_withdrawal.amount);
+        tree_token_account.reserved_lamports = tree_token_account.reserved_lamports
+            .checked_add(pending_withdrawal.amount)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        tree_token_account.vetoed_lamports = tree_token_account.vetoed_lamports
+            .checked_add(pending_withdrawal.amount)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        
+        msg!("Withdrawal of {} lamports to {} vetoed", pending_withdrawal.amount, pending_withdrawal.recipient);
+        Ok(())
+    }
+
+    /**
+     * Pay `amount` lamports of vetoed withdrawals out of the pool to `recipient`, e.g. to return
+     * them to their owner once the emergency is resolved. At most the vetoed lamports the pool
+     * holds can be released. Only the authority can call this.
+     */
+    pub fn release_vetoed_funds(ctx: Context<ReleaseVetoedFunds>, amount: u64) -> Result<()> {
+        let tree_token_account = &mut ctx.accounts.tree_token_account;
+        tree_token_account.vetoed_lamports = tree_token_account.vetoed_lamports
+            .checked_sub(amount)
+            .ok_or(ErrorCode::InsufficientVetoedFunds)?;
+        tree_token_account.release(amount);
+        
+        let tree_token_account_info = tree_token_account.to_account_info();
+        let rent_exempt_minimum = Rent::get()?.minimum_balance(tree_token_account_info.data_len());
+        let total_required = amount
+            .checked_add(rent_exempt_minimum)
+            .ok_or(ErrorCode::ArithmeticOverflow)?
+            .checked_add(tree_token_account.reserved_lamports)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        require!(
+            tree_token_account_info.lamports() >= total_required,
+            ErrorCode::InsufficientFundsForWithdrawal
+        );
+        
+        utils::transfer_lamports(
+            &tree_token_account_info,
+            &ctx.accounts.recipient.to_account_info(),
+            amount,
+        )?;
+        
+        msg!("Released {} vetoed lamports to {}", amount, ctx.accounts.recipient.key());
+        Ok(())
+    }
+
+    /**
+     * Update how long commitment PDAs must be kept before their rent can be reclaimed,
+     * in leaves appended after them and in slots since their creation. 0 disables a criterion,
+     * and reclamation is disabled when both are 0. Only the authority can call this.
+     */
+    pub fn update_commitment_retention(
+        ctx: Context<UpdateGlobalConfig>,
+        retention_leaves: u64,
+        retention_slots: u64,
+    ) -> Result<()> {
+        let global_config = &mut ctx.accounts.global_config;
+        global_config.commitment_retention_leaves = retention_leaves;
+        global_config.commitment_retention_slots = retention_slots;
+        
+        msg!("Commitment retention updated to: {} leaves, {} slots", retention_leaves, retention_slots);
+        Ok(())
+    }
+
+    /**
+     * Close a commitment PDA that is past the retention window and refund its rent to the original payer.
+     * Anyone can call this, e.g. the indexer once it has archived the encrypted output.
+     * 
+     * The tree is unaffected, since leaves live in the MerkleTreeAccount subtrees. Commitment PDAs
+     * created before the payer was recorded can't be deserialized and so can't be reclaimed.
+     * The account must be the commitment PDA of its own commitment and index, see
+     * `utils::commitment_seed_prefix`, so only accounts created by `transact` can be closed.
+     */
+    pub fn reclaim_commitment(ctx: Context<ReclaimCommitment>) -> Result<()> {
+        let tree_account = ctx.accounts.tree_account.load()?;
+        let global_config = &ctx.accounts.global_config;
+        let commitment = &ctx.accounts.commitment;
+        
+        require!(
+            utils::is_commitment_reclaimable(
+                commitment.index,
+                commitment.slot,
+                tree_account.next_index,
+                Clock::get()?.slot,
+                global_config.commitment_retention_leaves,
+                global_config.commitment_retention_slots,
+            ),
+            ErrorCode::CommitmentNotReclaimable
+        );
+        
+        msg!("Commitment at index {} reclaimed, rent refunded to {}", commitment.index, commitment.payer);
+        Ok(())
+    }
+
+    /**
+     * Upgrade the state accounts of a deployment created with an older layout to the current one,
+     * reallocating them with reserved space. The authority pays the additional rent.
+     * Accounts that are already current are left untouched, so this can be called again safely.
+     * Only the authority can call this.
+     */
+    pub fn migrate(ctx: Context<Migrate>) -> Result<()> {
+        let global_config_info = ctx.accounts.global_config.to_account_info();
+        let authority = migration::read_authority(&global_config_info.try_borrow_data()?)?;
+        require_keys_eq!(authority, ctx.accounts.authority.key(), ErrorCode::Unauthorized);
+
+        let payer = ctx.accounts.authority.to_account_info();
+        let system_program = ctx.accounts.system_program.to_account_info();
+        let accounts: [(AccountInfo, migration::MigrateData); 3] = [
+            (ctx.accounts.tree_account.to_account_info(), migration::migrate_merkle_tree_account_data),
+            (ctx.accounts.tree_token_account.to_account_info(), migration::migrate_tree_token_account_data),
+            (global_config_info, migration::migrate_global_config_data),
+        ];
+
+        for (account, migrate_data) in accounts {
+            let new_data = migrate_data(&account.try_borrow_data()?)?;
+            if let Some(new_data) = new_data {
+                migration::write_resized(&account, &new_data, &payer, &system_program)?;
+                msg!("Migrated account {} to {} bytes", account.key(), new_data.len());
+            }
+        }
+        
+        Ok(())
+    }
+
+    /**
+     * Register an auditor's X25519 viewing key, which users can then address copies of their
+     * outputs to, see disclosure.rs. Only the authority can call this.
+     */
+    pub fn register_viewing_key(ctx: Context<RegisterViewingKey>, viewing_key: [u8; 32], auditor: Pubkey) -> Result<()> {
+        let registry = &mut ctx.accounts.viewing_key_registry;
+        registry.viewing_key = viewing_key;
+        registry.auditor = auditor;
+        registry.bump = ctx.bumps.viewing_key_registry;
+        
+        msg!("Viewing key registered for auditor {}", auditor);
+        Ok(())
+    }
+
+    /**
+     * Revoke an auditor viewing key. Disclosures already made stay decryptable by the auditor,
+     * new transactions can no longer address it. Only the authority can call this.
+     */
+    pub fn revoke_viewing_key(ctx: Context<RevokeViewingKey>, _viewing_key: [u8; 32]) -> Result<()> {
+        msg!("Viewing key of auditor {} revoked", ctx.accounts.viewing_key_registry.auditor);
+        Ok(())
+    }
+
+    /**
+     * Create the nullifier tree of an existing pool, see nullifier_tree.rs. The pool keeps using
+     * nullifier PDAs until `set_nullifier_mode` switches it. Only the authority can call this.
+     */
+    pub fn initialize_nullifier_tree(ctx: Context<InitializeNullifierTree>) -> Result<()> {
+        // Every transact so far appended two leaves and created two nullifier PDAs, see
+        // `NullifierTreeAccount.legacy_nullifiers`
+        let legacy_nullifiers = ctx.accounts.tree_account.load()?.next_index;
+        ctx.accounts.nullifier_tree.load_init()?.init(
+            ctx.accounts.authority.key(),
+            ctx.bumps.nullifier_tree,
+            legacy_nullifiers,
+        )?;
+        
+        msg!("Nullifier tree initialized with {} nullifiers to backfill", legacy_nullifiers);
+        Ok(())
+    }
+
+    /**
+     * Switch how spent nullifiers are recorded. Leaving Pda requires the nullifier tree, and
+     * Compressed additionally requires every nullifier spent under Pda to be backfilled. Returning
+     * to Pda is not allowed, since nullifiers that only live in the tree would be forgotten.
+     * Only the authority can call this.
+     */
+    pub fn set_nullifier_mode(ctx: Context<SetNullifierMode>, nullifier_mode: NullifierMode) -> Result<()> {
+        let global_config = &mut ctx.accounts.global_config;
+        let current_mode = global_config.nullifier_mode;
+        require!(
+            nullifier_mode.uses_tree() || !current_mode.uses_tree(),
+            ErrorCode::NullifierModeTransitionNotAllowed
+        );
+        
+        if nullifier_mode.uses_tree() {
+            let nullifier_tree = &mut ctx.accounts.nullifier_tree
+                .as_ref()
+                .ok_or(ErrorCode::InvalidNullifierAccounts)?
+                .load_mut()?;
+            if !current_mode.uses_tree() {
+                // Transactions under Pda since the tree was created only have PDAs as well
+                nullifier_tree.legacy_nullifiers = ctx.accounts.tree_account.load()?.next_index;
+            }
+            if nullifier_mode == NullifierMode::Compressed {
+                require!(
+                    nullifier_tree.backfilled_nullifiers >= nullifier_tree.legacy_nullifiers,
+                    ErrorCode::NullifierBackfillIncomplete
+                );
+            }
+        }
+        global_config.nullifier_mode = nullifier_mode;
+        
+        msg!("Nullifier mode set to: {:?}", nullifier_mode);
+        Ok(())
+    }
+
+    /**
+     * Insert a nullifier spent under the PDA scheme into the nullifier tree, proven spent by its
+     * nullifier PDA. Anyone can call this, e.g. the indexer before the pool moves to Compressed.
+     */
+    pub fn backfill_nullifier(
+        ctx: Context<BackfillNullifier>,
+        nullifier: [u8; 32],
+        insert_proof: NullifierInsertProof,
+    ) -> Result<()> {
+        let nullifier_account = ctx.accounts.nullifier_account.key();
+        require!(
+            [b"nullifier0".as_ref(), b"nullifier1".as_ref()].iter().any(|seed| {
+                Pubkey::find_program_address(&[*seed, nullifier.as_ref()], &crate::ID).0 == nullifier_account
+            }),
+            ErrorCode::InvalidNullifierProof
+        );
+        
+        let nullifier_tree = &mut ctx.accounts.nullifier_tree.load_mut()?;
+        NullifierTree::insert::<Poseidon>(nullifier, &insert_proof, nullifier_tree)?;
+        nullifier_tree.backfilled_nullifiers = nullifier_tree.backfilled_nullifiers
+            .checked_add(1)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        
+        msg!("Backfilled nullifier {} of {}", nullifier_tree.backfilled_nullifiers, nullifier_tree.legacy_nullifiers);
+        Ok(())
+    }
+
+    /**
+     * Store the insert proof for one input nullifier of the signer's next transact, which doesn't
+     * have room for them in its own instruction data. `position` is the index of the input.
+     */
+    pub fn write_nullifier_proof(
+        ctx: Context<WriteNullifierProof>,
+        position: u8,
+        insert_proof: NullifierInsertProof,
+    ) -> Result<()> {
+        let nullifier_proofs = &mut ctx.accounts.nullifier_proofs;
+        let slot = nullifier_proofs.proofs
+            .get_mut(position as usize)
+            .ok_or(ErrorCode::InvalidNullifierProof)?;
+        *slot = Some(insert_proof);
+        nullifier_proofs.bump = ctx.bumps.nullifier_proofs;
+        
+        Ok(())
+    }
+
     /**
      * Users deposit or withdraw from the program.
      * 
      * Reentrant attacks are not possible, because nullifier creation is checked by anchor first.
      * 
      * encrypted_output1 and encrypted_output2 are passed as separate parameters to save instruction data space (~170 bytes).
+     * 
+     * Other programs can call this through the `cpi` feature. A calling program funds a deposit from one of
+     * its PDAs by passing it as `depositor` and signing for it, while `signer` still pays rent for the new PDAs.
+     * A withdrawal can carry a `callback`, which invokes the recipient program once the funds have settled.
+     * Any transaction can carry an `auditor` disclosure, emitted as an `AuditorDisclosureEvent`.
+     * Any transaction can carry a `memo`, and deposits a `referrer`, emitted as an `ExtDataTagEvent`.
+     * Deposits with a referrer also count towards its `ReferrerStats`, created on its first deposit.
+     * A withdrawal can carry a `swap`, which swaps the withdrawn SOL into an SPL token for the recipient
+     * through `GlobalConfig.swap_program`, and fails unless the recipient receives at least `min_out`.
+     * 
+     * `remaining_accounts` layout: when `ext_data.recipients` lists N recipients, the N recipient accounts
+     * come first, followed by their N blocklist entry PDAs if `GlobalConfig.screen_withdrawals` is on.
+     * The callback program and its accounts follow, or the swap program and its accounts for a swap.
+     * 
+     * Depending on `GlobalConfig.nullifier_mode`, spent nullifiers are recorded as PDAs, in the nullifier
+     * tree, or both. Tree inserts use the proofs the signer stored with `write_nullifier_proof`.
+     *
+     * Withdrawals above `GlobalConfig.withdrawal_delay_threshold` don't pay the recipient: they spend their
+     * nullifiers and pay the fee now, and record the payout in `pending_withdrawal` for `finalize_withdrawal`.
+     *
+     * Internal transfers and consolidations (ext_amount == 0) pay their fee out of the shielded amounts,
+     * at least `GlobalConfig.transfer_fee`, and are capped at `GlobalConfig.max_transfers_per_slot`.
      */
-    pub fn transact(ctx: Context<Transact>, proof: Proof, ext_data: ExtData, encrypted_output1: Vec<u8>, encrypted_output2: Vec<u8>) -> Result<()> {
+    pub fn transact<'info>(ctx: Context<'_, '_, 'info, 'info, Transact<'info>>, proof: Proof, ext_data: ExtData, encrypted_output1: Vec<u8>, encrypted_output2: Vec<u8>) -> Result<()> {
         let tree_account = &mut ctx.accounts.tree_account.load_mut()?;
         let global_config = &ctx.accounts.global_config;
 
         // check if proof.root is in the tree_account's proof history
         require!(
-            MerkleTree::is_known_root(&tree_account, proof.root),
+            MerkleTree::is_known_root(tree_account, proof.root),
             ErrorCode::UnknownRoot
         );
 
@@ -137,15 +640,114 @@ pub mod zkcash {
             ErrorCode::InvalidFeeRecipient
         );
 
+        // The account funding a deposit: a calling program's PDA if given, otherwise the signer
+        let depositor_info = match &ctx.accounts.depositor {
+            Some(depositor) => depositor.to_account_info(),
+            None => ctx.accounts.signer.to_account_info(),
+        };
+
+        // Screen the depositor, and the recipient of withdrawals if enabled, against the blocklist.
+        // The recipient entry's address is already checked by its seeds constraint.
+        if ext_data.ext_amount > 0 {
+            blocklist::require_not_listed(depositor_info.key, &ctx.accounts.depositor_blocklist_entry)?;
+        } else if ext_data.ext_amount < 0 && global_config.screen_withdrawals {
+            require!(
+                !blocklist::is_listed(&ctx.accounts.recipient_blocklist_entry),
+                ErrorCode::AddressBlocked
+            );
+        }
+
+        // Blocklist entries of the recipients are only passed, and read, while screening is on
+        let recipient_count = ext_data.recipients.as_ref().map_or(0, |recipients| recipients.len());
+        let recipient_account_count = if global_config.screen_withdrawals {
+            recipient_count * 2
+        } else {
+            recipient_count
+        };
+        require!(
+            ctx.remaining_accounts.len() >= recipient_account_count,
+            ErrorCode::RecipientMismatch
+        );
+        let (recipient_accounts, cpi_accounts) = ctx.remaining_accounts.split_at(recipient_account_count);
+
+        // A multi-recipient withdrawal pays each listed recipient instead of a single `recipient`.
+        // The listed recipients are bound by the ext data hash, so only the accounts need checking.
+        if let Some(recipients) = &ext_data.recipients {
+            utils::validate_withdrawal_recipients(recipients, ext_data.ext_amount, ext_data.recipient)?;
+            for (i, withdrawal_recipient) in recipients.iter().enumerate() {
+                require!(
+                    recipient_accounts[i].key() == withdrawal_recipient.recipient && recipient_accounts[i].is_writable,
+                    ErrorCode::RecipientMismatch
+                );
+                if global_config.screen_withdrawals {
+                    blocklist::require_not_listed(
+                        &withdrawal_recipient.recipient,
+                        &recipient_accounts[recipient_count + i],
+                    )?;
+                }
+            }
+        }
+        
+        // A delayed withdrawal pays a single recipient later, so it can't split the payout or call back
+        let delayed = utils::is_delayed_withdrawal(ext_data.ext_amount, global_config.withdrawal_delay_threshold);
+        require!(
+            ctx.accounts.pending_withdrawal.is_some() == delayed,
+            ErrorCode::InvalidPendingWithdrawalAccount
+        );
+        if delayed {
+            require!(
+                ext_data.recipients.is_none() && ext_data.callback.is_none() && ext_data.swap.is_none(),
+                ErrorCode::DelayedWithdrawalNotSupported
+            );
+        }
+
+        // Callbacks are only meaningful once funds have been paid out to the recipient
+        if let Some(callback) = &ext_data.callback {
+            require!(ext_data.ext_amount < 0, ErrorCode::CallbackNotAllowed);
+            callback::validate_callback(callback)?;
+        }
+        
+        // A swap converts the whole withdrawal for the single recipient, and uses the callback's accounts
+        require!(
+            ctx.accounts.swap_authority.is_some() == ext_data.swap.is_some(),
+            ErrorCode::InvalidSwapAccounts
+        );
+        if let (Some(swap), Some(swap_authority)) = (&ext_data.swap, &ctx.accounts.swap_authority) {
+            require!(
+                ext_data.ext_amount < 0 && ext_data.recipients.is_none() && ext_data.callback.is_none(),
+                ErrorCode::SwapNotAllowed
+            );
+            swap::validate_swap(swap, &global_config.swap_program, swap_authority.key)?;
+        }
+        
+        referral::validate_tags(&ext_data.memo, &ext_data.referrer, ext_data.ext_amount)?;
+        require!(
+            ctx.accounts.referrer_stats.is_some() == ext_data.referrer.is_some(),
+            ErrorCode::InvalidReferrerStatsAccount
+        );
+
+        if let Some(auditor) = &ext_data.auditor {
+            let registry = ctx.accounts.viewing_key_registry
+                .as_ref()
+                .ok_or(ErrorCode::ViewingKeyNotRegistered)?;
+            disclosure::validate_auditor_disclosure(auditor, registry)?;
+        }
+
+        // Bound the ciphertexts stored in the commitment PDAs, and check their envelope if required
+        for encrypted_output in [&encrypted_output1, &encrypted_output2] {
+            utils::validate_encrypted_output(
+                encrypted_output,
+                global_config.min_encrypted_output_len,
+                global_config.max_encrypted_output_len,
+                global_config.require_output_envelope,
+            )?;
+        }
+
         // check if the ext_data hashes to the same ext_data in the proof
-        let calculated_ext_data_hash = utils::calculate_complete_ext_data_hash(
-            ext_data.recipient,
-            ext_data.ext_amount,
+        let calculated_ext_data_hash = utils::calculate_ext_data_hash(
+            &ext_data,
             &encrypted_output1,
             &encrypted_output2,
-            ext_data.fee,
-            ext_data.fee_recipient,
-            ext_data.mint_address,
         )?;
 
         require!(
@@ -167,20 +769,77 @@ pub mod zkcash {
         let fee = ext_data.fee;
 
         // Validate fee calculation using utility function
-        utils::validate_fee(
+        let self_funded_deposit = ext_amount > 0 && ctx.accounts.depositor.is_none();
+        if global_config.require_rent_in_fee && !self_funded_deposit {
+            utils::validate_fee_with_rent(
+                ext_amount,
+                fee,
+                global_config.deposit_fee_rate,
+                global_config.withdrawal_fee_rate,
+                global_config.fee_error_margin,
+                global_config.transfer_fee,
+                utils::transact_rent(
+                    &Rent::get()?,
+                    global_config.nullifier_mode,
+                    encrypted_output1.len(),
+                    encrypted_output2.len(),
+                    utils::TransactPdas {
+                        pending_withdrawal: ctx.accounts.pending_withdrawal.is_some(),
+                        // Existing stats have recorded a deposit, `init_if_needed` just created empty ones
+                        referrer_stats: ctx.accounts.referrer_stats
+                            .as_ref()
+                            .is_some_and(|referrer_stats| referrer_stats.deposit_count == 0),
+                    },
+                )?,
+            )?;
+        } else {
+            utils::validate_fee(
+                ext_amount,
+                fee,
+                global_config.deposit_fee_rate,
+                global_config.withdrawal_fee_rate,
+                global_config.fee_error_margin,
+                global_config.transfer_fee,
+            )?;
+        }
+
+        utils::validate_deposit_amount(
             ext_amount,
-            fee,
-            global_config.deposit_fee_rate,
-            global_config.withdrawal_fee_rate,
-            global_config.fee_error_margin,
+            global_config.min_deposit_amount,
+            &global_config.deposit_denominations,
         )?;
 
+        if ext_amount == 0 {
+            utils::record_transfer(tree_account, Clock::get()?.slot, global_config.max_transfers_per_slot)?;
+        }
+
         // verify the proof
         require!(verify_proof(proof.clone(), VERIFYING_KEY), ErrorCode::InvalidProof);
 
+        // Nullifier PDAs, if any, were created by `init` above. The tree is always Poseidon.
+        let nullifier_mode = global_config.nullifier_mode;
+        require!(
+            ctx.accounts.nullifier0.is_some() == nullifier_mode.uses_pdas()
+                && ctx.accounts.nullifier1.is_some() == nullifier_mode.uses_pdas()
+                && ctx.accounts.nullifier_tree.is_some() == nullifier_mode.uses_tree(),
+            ErrorCode::InvalidNullifierAccounts
+        );
+        if let Some(nullifier_tree) = &ctx.accounts.nullifier_tree {
+            let nullifier_proofs = ctx.accounts.nullifier_proofs
+                .as_mut()
+                .ok_or(ErrorCode::MissingNullifierProof)?;
+            let nullifier_tree = &mut nullifier_tree.load_mut()?;
+            for (nullifier, slot) in proof.input_nullifiers.iter().zip(nullifier_proofs.proofs.iter_mut()) {
+                // Consumed either way, a failed insert reverts the whole transaction
+                let insert_proof = slot.take().ok_or(ErrorCode::MissingNullifierProof)?;
+                NullifierTree::insert::<Poseidon>(*nullifier, &insert_proof, nullifier_tree)?;
+            }
+        }
+
         let tree_token_account_info = ctx.accounts.tree_token_account.to_account_info();
         let rent = Rent::get()?;
         let rent_exempt_minimum = rent.minimum_balance(tree_token_account_info.data_len());
+        let reserved_lamports = ctx.accounts.tree_token_account.reserved_lamports;
 
         if ext_amount > 0 {
             // Check deposit limit for deposits
@@ -195,26 +854,32 @@ pub mod zkcash {
                 CpiContext::new(
                     ctx.accounts.system_program.to_account_info(),
                     anchor_lang::system_program::Transfer {
-                        from: ctx.accounts.signer.to_account_info(),
+                        from: depositor_info,
                         to: ctx.accounts.tree_token_account.to_account_info(),
                     },
                 ),
                 ext_amount as u64,
             )?;
+            
+            if let (Some(referrer), Some(referrer_stats)) = (ext_data.referrer, ctx.accounts.referrer_stats.as_mut()) {
+                let bump = ctx.bumps.referrer_stats.ok_or(ErrorCode::InvalidReferrerStatsAccount)?;
+                referral::record_deposit(referrer_stats, referrer, deposit_amount, bump)?;
+            }
         } else if ext_amount < 0 {
             // PDA can't directly sign transactions, so we need to transfer SOL via try_borrow_mut_lamports
             // No limit on withdrawals
-            let recipient_account_info = ctx.accounts.recipient.to_account_info();
-
             let ext_amount_abs: u64 = ext_amount.checked_neg()
                 .ok_or(ErrorCode::ArithmeticOverflow)?
                 .try_into()
                 .map_err(|_| ErrorCode::InvalidExtAmount)?;
             
+            // Lamports reserved for pending withdrawals can't be paid out again
             let total_required = ext_amount_abs
                 .checked_add(fee)
                 .ok_or(ErrorCode::ArithmeticOverflow)?
                 .checked_add(rent_exempt_minimum)
+                .ok_or(ErrorCode::ArithmeticOverflow)?
+                .checked_add(reserved_lamports)
                 .ok_or(ErrorCode::ArithmeticOverflow)?;
             
             require!(
@@ -222,16 +887,45 @@ pub mod zkcash {
                 ErrorCode::InsufficientFundsForWithdrawal
             );
 
-            let tree_token_balance = tree_token_account_info.lamports();
-            let recipient_balance = recipient_account_info.lamports();
-            
-            let new_tree_token_balance = tree_token_balance.checked_sub(ext_amount_abs)
-                .ok_or(ErrorCode::ArithmeticOverflow)?;
-            let new_recipient_balance = recipient_balance.checked_add(ext_amount_abs)
-                .ok_or(ErrorCode::ArithmeticOverflow)?;
-                
-            **tree_token_account_info.try_borrow_mut_lamports()? = new_tree_token_balance;
-            **recipient_account_info.try_borrow_mut_lamports()? = new_recipient_balance;
+            match &ext_data.recipients {
+                _ if delayed => {
+                    let pending_withdrawal = ctx.accounts.pending_withdrawal
+                        .as_mut()
+                        .ok_or(ErrorCode::InvalidPendingWithdrawalAccount)?;
+                    let current_slot = Clock::get()?.slot;
+                    pending_withdrawal.nullifier = proof.input_nullifiers[0];
+                    pending_withdrawal.recipient = ext_data.recipient;
+                    pending_withdrawal.amount = ext_amount_abs;
+                    pending_withdrawal.payer = ctx.accounts.signer.key();
+                    pending_withdrawal.request_slot = current_slot;
+                    pending_withdrawal.release_slot = current_slot
+                        .checked_add(global_config.withdrawal_delay_slots)
+                        .ok_or(ErrorCode::ArithmeticOverflow)?;
+                    pending_withdrawal.bump = ctx.bumps.pending_withdrawal
+                        .ok_or(ErrorCode::InvalidPendingWithdrawalAccount)?;
+                    ctx.accounts.tree_token_account.reserved_lamports = reserved_lamports
+                        .checked_add(ext_amount_abs)
+                        .ok_or(ErrorCode::ArithmeticOverflow)?;
+                    
+                    msg!("Withdrawal of {} lamports pending until slot {}", ext_amount_abs, pending_withdrawal.release_slot);
+                }
+                // Swapped for the recipient once all pool state has been updated, see below
+                _ if ext_data.swap.is_some() => {
+                    let swap_authority = ctx.accounts.swap_authority
+                        .as_ref()
+                        .ok_or(ErrorCode::InvalidSwapAccounts)?;
+                    utils::transfer_lamports(&tree_token_account_info, &swap_authority.to_account_info(), ext_amount_abs)?;
+                }
+                Some(recipients) => {
+                    for (recipient_account_info, withdrawal_recipient) in recipient_accounts.iter().zip(recipients.iter()) {
+                        utils::transfer_lamports(&tree_token_account_info, recipient_account_info, withdrawal_recipient.amount)?;
+                    }
+                }
+                None => {
+                    let recipient_account_info = ctx.accounts.recipient.to_account_info();
+                    utils::transfer_lamports(&tree_token_account_info, &recipient_account_info, ext_amount_abs)?;
+                }
+            }
         }
         
         if fee > 0 {
@@ -240,6 +934,8 @@ pub mod zkcash {
             if ext_amount >= 0 {
                 let total_required = fee
                     .checked_add(rent_exempt_minimum)
+                    .ok_or(ErrorCode::ArithmeticOverflow)?
+                    .checked_add(reserved_lamports)
                     .ok_or(ErrorCode::ArithmeticOverflow)?;
                 
                 require!(
@@ -261,19 +957,66 @@ pub mod zkcash {
         }
 
         let next_index_to_insert = tree_account.next_index;
-        MerkleTree::append::<Poseidon>(proof.output_commitments[0], tree_account)?;
-        MerkleTree::append::<Poseidon>(proof.output_commitments[1], tree_account)?;
+        let current_slot = Clock::get()?.slot;
+        MerkleTree::append_pair::<Poseidon>(
+            proof.output_commitments[0],
+            proof.output_commitments[1],
+            tree_account,
+        )?;
 
         ctx.accounts.commitment0.commitment = proof.output_commitments[0];
         ctx.accounts.commitment0.encrypted_output = encrypted_output1;
         ctx.accounts.commitment0.index = next_index_to_insert;
         ctx.accounts.commitment0.bump = ctx.bumps.commitment0;
+        ctx.accounts.commitment0.payer = ctx.accounts.signer.key();
+        ctx.accounts.commitment0.slot = current_slot;
         
         ctx.accounts.commitment1.commitment = proof.output_commitments[1];
         ctx.accounts.commitment1.encrypted_output = encrypted_output2;
         ctx.accounts.commitment1.index = next_index_to_insert.checked_add(1)
             .ok_or(ErrorCode::ArithmeticOverflow)?;
         ctx.accounts.commitment1.bump = ctx.bumps.commitment1;
+        ctx.accounts.commitment1.payer = ctx.accounts.signer.key();
+        ctx.accounts.commitment1.slot = current_slot;
+
+        if let Some(auditor) = ext_data.auditor {
+            emit!(disclosure::AuditorDisclosureEvent {
+                viewing_key: auditor.viewing_key,
+                commitments: proof.output_commitments,
+                indices: [ctx.accounts.commitment0.index, ctx.accounts.commitment1.index],
+                ext_amount: ext_data.ext_amount,
+                fee: ext_data.fee,
+                encrypted_outputs: auditor.encrypted_outputs,
+            });
+        }
+
+        if ext_data.memo.is_some() || ext_data.referrer.is_some() {
+            emit!(referral::ExtDataTagEvent {
+                referrer: ext_data.referrer,
+                memo: ext_data.memo,
+                ext_amount: ext_data.ext_amount,
+            });
+        }
+        
+        // Run the recipient program's hook last, once all pool state has been updated
+        if let Some(callback) = &ext_data.callback {
+            callback::invoke_withdraw_callback(callback, cpi_accounts)?;
+        }
+        
+        if let (Some(swap), Some(swap_authority)) = (&ext_data.swap, &ctx.accounts.swap_authority) {
+            let bump = ctx.bumps.swap_authority.ok_or(ErrorCode::InvalidSwapAccounts)?;
+            let amount_out = swap::invoke_swap(
+                swap,
+                &global_config.swap_program,
+                cpi_accounts,
+                swap_authority.key,
+                bump,
+                &ext_data.recipient,
+                ext_data.ext_amount.unsigned_abs(),
+            )?;
+            
+            msg!("Swapped {} lamports for {} of mint {}", ext_data.ext_amount.unsigned_abs(), amount_out, swap.output_mint);
+        }
         
         Ok(())
     }
@@ -299,6 +1042,51 @@ pub struct ExtData {
     pub fee: u64,
     pub fee_recipient: Pubkey,
     pub mint_address: Pubkey,
+    // Optional extensions, only hashed when present (see `utils::calculate_ext_data_hash`)
+    pub callback: Option<WithdrawCallback>,
+    pub recipients: Option<Vec<WithdrawalRecipient>>,
+    pub auditor: Option<AuditorDisclosure>,
+    pub memo: Option<Vec<u8>>,      // opaque, at most `referral::MAX_MEMO_LEN` bytes
+    pub referrer: Option<[u8; 32]>, // opaque partner id, deposits only
+    pub swap: Option<SwapParams>,
+}
+
+/// Copies of the two output ciphertexts addressed to a registered auditor viewing key.
+/// Each is an envelope of scheme X25519ChaCha20Poly1305, see disclosure.rs.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct AuditorDisclosure {
+    pub viewing_key: [u8; 32],
+    pub encrypted_outputs: [Vec<u8>; 2],
+}
+
+/// One payout of a multi-recipient withdrawal. Up to `utils::MAX_WITHDRAWAL_RECIPIENTS`
+/// payouts are allowed and their amounts must sum to the withdrawn amount.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct WithdrawalRecipient {
+    pub recipient: Pubkey,
+    pub amount: u64,
+}
+
+/// A swap of the withdrawn SOL into `output_mint`, paid to a token account of the recipient.
+/// `data` is the instruction of `GlobalConfig.swap_program`, which must list the swap authority
+/// and `recipient_token_account` among its `accounts`. Bounded by `swap::MAX_SWAP_ACCOUNTS` and
+/// `swap::MAX_SWAP_DATA_LEN`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct SwapParams {
+    pub output_mint: Pubkey,
+    pub recipient_token_account: Pubkey,
+    pub min_out: u64, // slippage guard, in base units of `output_mint`
+    pub accounts: Vec<Pubkey>,
+    pub data: Vec<u8>,
+}
+
+/// An instruction invoked on the recipient program after a withdrawal settles.
+/// Bounded by `callback::MAX_CALLBACK_ACCOUNTS` and `callback::MAX_CALLBACK_DATA_LEN`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct WithdrawCallback {
+    pub program_id: Pubkey,
+    pub accounts: Vec<Pubkey>,
+    pub data: Vec<u8>,
 }
 
 #[derive(Accounts)]
@@ -311,36 +1099,36 @@ pub struct Transact<'info> {
     )]
     pub tree_account: AccountLoader<'info, MerkleTreeAccount>,
     
-    /// Nullifier account to mark the first input as spent.
+    /// Nullifier account to mark the first input as spent, omitted in Compressed mode.
     /// Using `init` without `init_if_needed` ensures that the transaction
     /// will automatically fail with a system program error if this nullifier
     /// has already been used (i.e., if the account already exists).
     #[account(
         init,
         payer = signer,
-        space = 8 + std::mem::size_of::<NullifierAccount>(),
+        space = utils::nullifier_account_space(),
         seeds = [b"nullifier0", proof.input_nullifiers[0].as_ref()],
         bump
     )]
-    pub nullifier0: Account<'info, NullifierAccount>,
+    pub nullifier0: Option<Account<'info, NullifierAccount>>,
     
-    /// Nullifier account to mark the second input as spent.
+    /// Nullifier account to mark the second input as spent, omitted in Compressed mode.
     /// Using `init` without `init_if_needed` ensures that the transaction
     /// will automatically fail with a system program error if this nullifier
     /// has already been used (i.e., if the account already exists).
     #[account(
         init,
         payer = signer,
-        space = 8 + std::mem::size_of::<NullifierAccount>(),
+        space = utils::nullifier_account_space(),
         seeds = [b"nullifier1", proof.input_nullifiers[1].as_ref()],
         bump
     )]
-    pub nullifier1: Account<'info, NullifierAccount>,
+    pub nullifier1: Option<Account<'info, NullifierAccount>>,
     
     #[account(
         init,
         payer = signer,
-        space = 8 + std::mem::size_of::<CommitmentAccount>() + encrypted_output1.len(),
+        space = utils::commitment_account_space(encrypted_output1.len()),
         seeds = [b"commitment0", proof.output_commitments[0].as_ref()],
         bump
     )]
@@ -349,7 +1137,7 @@ pub struct Transact<'info> {
     #[account(
         init,
         payer = signer,
-        space = 8 + std::mem::size_of::<CommitmentAccount>() + encrypted_output2.len(),
+        space = utils::commitment_account_space(encrypted_output2.len()),
         seeds = [b"commitment1", proof.output_commitments[1].as_ref()],
         bump
     )]
@@ -380,6 +1168,71 @@ pub struct Transact<'info> {
     #[account(mut)]
     pub signer: Signer<'info>,
     
+    /// Optional funding account for deposits, e.g. a calling program's PDA signing via CPI.
+    /// It must be system-owned to be debited. Defaults to `signer`.
+    #[account(mut)]
+    pub depositor: Option<Signer<'info>>,
+    
+    /// Blocklist entry PDA of the depositor (`depositor` if present, otherwise `signer`).
+    /// Only its existence is checked.
+    /// CHECK: address is checked in `blocklist::require_not_listed`
+    pub depositor_blocklist_entry: UncheckedAccount<'info>,
+    
+    /// Blocklist entry PDA of the recipient. Only its existence is checked.
+    /// CHECK: address is enforced by seeds, see `blocklist::is_listed`
+    #[account(
+        seeds = [b"blocklist", recipient.key().as_ref()],
+        bump
+    )]
+    pub recipient_blocklist_entry: UncheckedAccount<'info>,
+    
+    /// Nullifier tree, required in Dual and Compressed mode.
+    #[account(
+        mut,
+        seeds = [b"nullifier_tree"],
+        bump = nullifier_tree.load()?.bump
+    )]
+    pub nullifier_tree: Option<AccountLoader<'info, NullifierTreeAccount>>,
+    
+    /// Insert proofs for the input nullifiers, required with `nullifier_tree`.
+    #[account(
+        mut,
+        seeds = [b"nullifier_proofs", signer.key().as_ref()],
+        bump = nullifier_proofs.bump
+    )]
+    pub nullifier_proofs: Option<Account<'info, NullifierProofBuffer>>,
+    
+    /// Registry entry of the auditor viewing key, required with `ext_data.auditor`.
+    pub viewing_key_registry: Option<Account<'info, ViewingKeyRegistry>>,
+    
+    /// Payout of a withdrawal above the delay threshold, required exactly for those.
+    #[account(
+        init,
+        payer = signer,
+        space = PendingWithdrawal::SPACE,
+        seeds = [b"pending_withdrawal", proof.input_nullifiers[0].as_ref()],
+        bump
+    )]
+    pub pending_withdrawal: Option<Account<'info, PendingWithdrawal>>,
+    
+    /// Aggregate counters of `ext_data.referrer`, required exactly when it is set.
+    #[account(
+        init_if_needed,
+        payer = signer,
+        space = ReferrerStats::SPACE,
+        seeds = [referral::REFERRER_SEED, referral::referrer_seed(&ext_data.referrer)],
+        bump
+    )]
+    pub referrer_stats: Option<Account<'info, ReferrerStats>>,
+    
+    /// Holds the withdrawn lamports until the swap spends them, required exactly with `ext_data.swap`.
+    #[account(
+        mut,
+        seeds = [swap::SWAP_AUTHORITY_SEED],
+        bump
+    )]
+    pub swap_authority: Option<SystemAccount<'info>>,
+    
     pub system_program: Program<'info, System>,
 }
 
@@ -388,7 +1241,7 @@ pub struct Initialize<'info> {
     #[account(
         init,
         payer = authority,
-        space = 8 + std::mem::size_of::<MerkleTreeAccount>(),
+        space = MerkleTreeAccount::SPACE,
         seeds = [b"merkle_tree"],
         bump
     )]
@@ -397,7 +1250,7 @@ pub struct Initialize<'info> {
     #[account(
         init,
         payer = authority,
-        space = 8 + std::mem::size_of::<TreeTokenAccount>(),
+        space = TreeTokenAccount::SPACE,
         seeds = [b"tree_token"],
         bump
     )]
@@ -406,18 +1259,118 @@ pub struct Initialize<'info> {
     #[account(
         init,
         payer = authority,
-        space = 8 + std::mem::size_of::<GlobalConfig>(),
+        space = GlobalConfig::SPACE,
         seeds = [b"global_config"],
         bump
     )]
     pub global_config: Account<'info, GlobalConfig>,
     
+    /// Nullifier tree, required unless the pool starts in Pda mode.
+    #[account(
+        init,
+        payer = authority,
+        space = NullifierTreeAccount::SPACE,
+        seeds = [b"nullifier_tree"],
+        bump
+    )]
+    pub nullifier_tree: Option<AccountLoader<'info, NullifierTreeAccount>>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeNullifierTree<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = NullifierTreeAccount::SPACE,
+        seeds = [b"nullifier_tree"],
+        bump
+    )]
+    pub nullifier_tree: AccountLoader<'info, NullifierTreeAccount>,
+    
+    #[account(
+        seeds = [b"merkle_tree"],
+        bump = tree_account.load()?.bump
+    )]
+    pub tree_account: AccountLoader<'info, MerkleTreeAccount>,
+    
+    #[account(
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+    
     #[account(mut)]
     pub authority: Signer<'info>,
     
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct SetNullifierMode<'info> {
+    #[account(
+        mut,
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+    
+    #[account(
+        seeds = [b"merkle_tree"],
+        bump = tree_account.load()?.bump
+    )]
+    pub tree_account: AccountLoader<'info, MerkleTreeAccount>,
+    
+    /// Required unless switching to Pda.
+    #[account(
+        mut,
+        seeds = [b"nullifier_tree"],
+        bump = nullifier_tree.load()?.bump
+    )]
+    pub nullifier_tree: Option<AccountLoader<'info, NullifierTreeAccount>>,
+    
+    /// The authority account that can update the global config
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct BackfillNullifier<'info> {
+    #[account(
+        mut,
+        seeds = [b"nullifier_tree"],
+        bump = nullifier_tree.load()?.bump
+    )]
+    pub nullifier_tree: AccountLoader<'info, NullifierTreeAccount>,
+    
+    /// Nullifier PDA of either input position, its address is checked in the handler
+    pub nullifier_account: Account<'info, NullifierAccount>,
+    
+    pub signer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WriteNullifierProof<'info> {
+    #[account(
+        init_if_needed,
+        payer = signer,
+        space = NullifierProofBuffer::SPACE,
+        seeds = [b"nullifier_proofs", signer.key().as_ref()],
+        bump
+    )]
+    pub nullifier_proofs: Account<'info, NullifierProofBuffer>,
+    
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
 #[derive(Accounts)]
 pub struct UpdateDepositLimit<'info> {
     #[account(
@@ -446,10 +1399,262 @@ pub struct UpdateGlobalConfig<'info> {
     pub authority: Signer<'info>,
 }
 
+#[derive(Accounts)]
+#[instruction(address: Pubkey)]
+pub struct AddToBlocklist<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + std::mem::size_of::<BlocklistEntry>(),
+        seeds = [b"blocklist", address.as_ref()],
+        bump
+    )]
+    pub blocklist_entry: Account<'info, BlocklistEntry>,
+    
+    #[account(
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+    
+    /// The authority account that manages the blocklist
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(address: Pubkey)]
+pub struct RemoveFromBlocklist<'info> {
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"blocklist", address.as_ref()],
+        bump = blocklist_entry.bump
+    )]
+    pub blocklist_entry: Account<'info, BlocklistEntry>,
+    
+    #[account(
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+    
+    /// The authority account that manages the blocklist
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(viewing_key: [u8; 32])]
+pub struct RegisterViewingKey<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + std::mem::size_of::<ViewingKeyRegistry>(),
+        seeds = [b"viewing_key", viewing_key.as_ref()],
+        bump
+    )]
+    pub viewing_key_registry: Account<'info, ViewingKeyRegistry>,
+    
+    #[account(
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+    
+    /// The authority account that manages the viewing keys
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(viewing_key: [u8; 32])]
+pub struct RevokeViewingKey<'info> {
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"viewing_key", viewing_key.as_ref()],
+        bump = viewing_key_registry.bump
+    )]
+    pub viewing_key_registry: Account<'info, ViewingKeyRegistry>,
+    
+    #[account(
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+    
+    /// The authority account that manages the viewing keys
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeWithdrawal<'info> {
+    #[account(
+        mut,
+        close = payer,
+        seeds = [b"pending_withdrawal", pending_withdrawal.nullifier.as_ref()],
+        bump = pending_withdrawal.bump,
+        has_one = recipient @ ErrorCode::RecipientMismatch,
+        has_one = payer @ ErrorCode::InvalidWithdrawalPayer
+    )]
+    pub pending_withdrawal: Account<'info, PendingWithdrawal>,
+    
+    #[account(
+        mut,
+        seeds = [b"tree_token"],
+        bump = tree_token_account.bump
+    )]
+    pub tree_token_account: Account<'info, TreeTokenAccount>,
+    
+    #[account(
+        seeds = [b"global_config"],
+        bump = global_config.bump
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+    
+    #[account(mut)]
+    /// CHECK: must be the recipient recorded in the pending withdrawal, enforced by has_one
+    pub recipient: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    /// CHECK: must be the payer recorded in the pending withdrawal, enforced by has_one
+    pub payer: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct VetoWithdrawal<'info> {
+    #[account(
+        mut,
+        close = payer,
+        seeds = [b"pending_withdrawal", pending_withdrawal.nullifier.as_ref()],
+        bump = pending_withdrawal.bump,
+        has_one = payer @ ErrorCode::InvalidWithdrawalPayer
+    )]
+    pub pending_withdrawal: Account<'info, PendingWithdrawal>,
+    
+    #[account(
+        mut,
+        seeds = [b"tree_token"],
+        bump = tree_token_account.bump
+    )]
+    pub tree_token_account: Account<'info, TreeTokenAccount>,
+    
+    #[account(
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+    
+    #[account(mut)]
+    /// CHECK: must be the payer recorded in the pending withdrawal, enforced by has_one
+    pub payer: UncheckedAccount<'info>,
+    
+    /// The authority account that can veto withdrawals
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseVetoedFunds<'info> {
+    #[account(
+        mut,
+        seeds = [b"tree_token"],
+        bump = tree_token_account.bump
+    )]
+    pub tree_token_account: Account<'info, TreeTokenAccount>,
+    
+    #[account(
+        seeds = [b"global_config"],
+        bump = global_config.bump,
+        has_one = authority @ ErrorCode::Unauthorized
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+    
+    #[account(mut)]
+    /// CHECK: any account chosen by the authority to receive the vetoed funds
+    pub recipient: UncheckedAccount<'info>,
+    
+    /// The authority account that can release vetoed funds
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ReclaimCommitment<'info> {
+    #[account(
+        mut,
+        close = payer,
+        seeds = [utils::commitment_seed_prefix(commitment.index), commitment.commitment.as_ref()],
+        bump = commitment.bump,
+        has_one = payer @ ErrorCode::InvalidCommitmentPayer
+    )]
+    pub commitment: Account<'info, CommitmentAccount>,
+    
+    #[account(
+        seeds = [b"merkle_tree"],
+        bump = tree_account.load()?.bump
+    )]
+    pub tree_account: AccountLoader<'info, MerkleTreeAccount>,
+    
+    #[account(
+        seeds = [b"global_config"],
+        bump = global_config.bump
+    )]
+    pub global_config: Account<'info, GlobalConfig>,
+    
+    #[account(mut)]
+    /// CHECK: must be the payer recorded in the commitment account, enforced by has_one
+    pub payer: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Migrate<'info> {
+    /// CHECK: may still have an old layout, validated by migration::migrate_merkle_tree_account_data
+    #[account(mut, seeds = [b"merkle_tree"], bump, owner = crate::ID)]
+    pub tree_account: UncheckedAccount<'info>,
+
+    /// CHECK: may still have an old layout, validated by migration::migrate_tree_token_account_data
+    #[account(mut, seeds = [b"tree_token"], bump, owner = crate::ID)]
+    pub tree_token_account: UncheckedAccount<'info>,
+
+    /// CHECK: may still have an old layout, validated by migration::migrate_global_config_data
+    #[account(mut, seeds = [b"global_config"], bump, owner = crate::ID)]
+    pub global_config: UncheckedAccount<'info>,
+
+    /// The authority recorded in the global config, checked in the handler
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
 #[account]
 pub struct TreeTokenAccount {
     pub authority: Pubkey,
+    pub version: u8,
     pub bump: u8,
+    pub reserved_lamports: u64, // owed to pending withdrawals, not spendable by other withdrawals or fees
+    pub vetoed_lamports: u64, // part of reserved_lamports, held from vetoed withdrawals until released
+}
+
+impl TreeTokenAccount {
+    pub const VERSION: u8 = 2;
+    pub const SPACE: usize = 8 + 128; // reserved space, see migration.rs
+
+    /// Releases the reservation of a finalized or vetoed pending withdrawal, or of released vetoed funds.
+    fn release(&mut self, amount: u64) {
+        // Withdrawals requested before `reserved_lamports` existed were never reserved
+        self.reserved_lamports = self.reserved_lamports.saturating_sub(amount);
+    }
 }
 
 #[account]
@@ -459,6 +1664,120 @@ pub struct GlobalConfig {
     pub withdrawal_fee_rate: u16, // basis points (0-10000, where 10000 = 100%)
     pub fee_error_margin: u16,    // basis points (0-10000, where 10000 = 100%)
     pub bump: u8,
+    // Fields above are the v1 layout, new fields are appended below, see migration.rs
+    pub version: u8,
+    pub screen_withdrawals: bool, // also check withdrawal recipients against the blocklist
+    pub min_encrypted_output_len: u16,
+    pub max_encrypted_output_len: u16,
+    pub require_output_envelope: bool, // encrypted outputs must be `zkcash_envelope` envelopes
+    pub commitment_retention_leaves: u64, // 0 = no leaf-based reclamation
+    pub commitment_retention_slots: u64,  // 0 = no slot-based reclamation
+    pub min_deposit_amount: u64, // 0 = no minimum
+    pub deposit_denominations: [u64; utils::MAX_DEPOSIT_DENOMINATIONS], // all zeros = any amount
+    pub require_rent_in_fee: bool, // fee must also cover the PDA rent paid by the signer
+    pub nullifier_mode: NullifierMode, // Pda for configs that predate the nullifier tree
+    pub withdrawal_delay_threshold: u64, // 0 = all withdrawals settle immediately
+    pub withdrawal_delay_slots: u64,
+    pub emergency: bool, // freezes pending withdrawals and allows vetoing them
+    pub swap_program: Pubkey, // default pubkey = swaps disabled
+    pub transfer_fee: u64, // lamports, minimum fee of ext_amount == 0 transactions
+    pub max_transfers_per_slot: u16, // ext_amount == 0 transactions per slot, 0 = no cap
+}
+
+impl GlobalConfig {
+    pub const VERSION: u8 = 2;
+    pub const SPACE: usize = 8 + 256; // reserved space, see migration.rs
+}
+
+#[account]
+pub struct BlocklistEntry {
+    /// This account's existence indicates that the address is blocked.
+    pub address: Pubkey,
+    pub bump: u8,
+}
+
+/// Root and right-most subtrees of the nullifier tree, see nullifier_tree.rs.
+#[account(zero_copy)]
+pub struct NullifierTreeAccount {
+    pub authority: Pubkey,
+    pub next_index: u64,
+    /// Nullifiers only recorded as PDAs, to be backfilled before the pool can move to Compressed.
+    /// Taken from the commitment tree's `next_index` while the pool is still in Pda, which relies
+    /// on one nullifier PDA per leaf: `transact` is the only instruction appending leaves, and
+    /// under Pda each one appends two and `init`s both nullifier PDAs in the same transaction. An
+    /// instruction appending leaves without creating as many nullifier PDAs would break this.
+    pub legacy_nullifiers: u64,
+    pub backfilled_nullifiers: u64,
+    pub root: [u8; 32],
+    pub subtrees: [[u8; 32]; NULLIFIER_TREE_HEIGHT],
+    pub height: u8,
+    pub bump: u8,
+    pub version: u8,
+    pub _padding: [u8; 5],
+    pub _reserved: [u8; 256],
+}
+
+impl NullifierTreeAccount {
+    pub const VERSION: u8 = 1;
+    pub const SPACE: usize = 8 + std::mem::size_of::<NullifierTreeAccount>();
+
+    fn init(&mut self, authority: Pubkey, bump: u8, legacy_nullifiers: u64) -> Result<()> {
+        self.authority = authority;
+        self.bump = bump;
+        self.version = Self::VERSION;
+        NullifierTree::initialize::<Poseidon>(self, legacy_nullifiers)
+    }
+}
+
+/// Insert proofs for the input nullifiers of a signer's next transact.
+#[account]
+pub struct NullifierProofBuffer {
+    pub proofs: [Option<NullifierInsertProof>; 2],
+    pub bump: u8,
+}
+
+impl NullifierProofBuffer {
+    pub const SPACE: usize = 8 + 2 * (1 + NullifierInsertProof::LEN) + 1;
+}
+
+/// A registered auditor viewing key, one PDA per key seeded with [VIEWING_KEY_SEED, viewing_key].
+#[account]
+pub struct ViewingKeyRegistry {
+    pub viewing_key: [u8; 32], // X25519 public key
+    pub auditor: Pubkey,       // informational, the auditor's own address
+    pub bump: u8,
+}
+
+/// A withdrawal above the delay threshold, paid out by `finalize_withdrawal` from `release_slot` on.
+/// Seeded with the first input nullifier of the withdrawal, which is unique.
+#[account]
+pub struct PendingWithdrawal {
+    pub nullifier: [u8; 32],
+    pub recipient: Pubkey,
+    pub amount: u64,
+    /// The account that paid the rent, refunded when the withdrawal is finalized or vetoed.
+    pub payer: Pubkey,
+    pub request_slot: u64,
+    pub release_slot: u64,
+    pub bump: u8,
+}
+
+impl PendingWithdrawal {
+    pub const SPACE: usize = 8 + 32 + 32 + 8 + 32 + 8 + 8 + 1;
+}
+
+/// Deposit counters of a referrer, one PDA per referrer seeded with [REFERRER_SEED, referrer].
+/// Nothing links them to the commitments of the deposits they count.
+#[account]
+pub struct ReferrerStats {
+    pub referrer: [u8; 32],
+    pub deposit_count: u64,
+    pub deposit_volume: u64, // lamports, fees included
+    pub bump: u8,
+}
+
+impl ReferrerStats {
+    pub const SPACE: usize = 8 + 32 + 8 + 8 + 1;
 }
 
 #[account]
@@ -474,6 +1793,10 @@ pub struct CommitmentAccount {
     pub encrypted_output: Vec<u8>,
     pub index: u64,
     pub bump: u8,
+    /// The account that paid the rent, refunded when the PDA is reclaimed.
+    pub payer: Pubkey,
+    /// The slot the commitment was created in.
+    pub slot: u64,
 }
 
 #[account(zero_copy)]
@@ -488,8 +1811,22 @@ pub struct MerkleTreeAccount {
     pub height: u8,
     pub root_history_size: u8,
     pub bump: u8,
-    // The pub _padding: [u8; 5] is needed because of the #[account(zero_copy)] attribute.
-    pub _padding: [u8; 5],
+    pub hasher: u8, // TreeHasher, 0 (Poseidon) for trees created before it was recorded
+    pub version: u8,
+    // The pub _padding: [u8; 3] is needed because of the #[account(zero_copy)] attribute.
+    pub _padding: [u8; 3],
+    // next_index when each root_history entry was pushed, see `MerkleTree::root_leaf_range`
+    pub root_history_next_index: [u64; 100],
+    // Slot of the last ext_amount == 0 transaction and how many it had, see `utils::record_transfer`
+    pub transfer_slot: u64,
+    pub transfers_in_slot: u64,
+    // New fields are carved out of this, see migration.rs
+    pub _reserved: [u8; 208],
+}
+
+impl MerkleTreeAccount {
+    pub const VERSION: u8 = 3;
+    pub const SPACE: usize = 8 + std::mem::size_of::<MerkleTreeAccount>();
 }
 
 #[error_code]
@@ -530,4 +1867,100 @@ pub enum ErrorCode {
     RecipientMismatch,
     #[msg("Merkle tree is full: cannot add more leaves")]
     MerkleTreeFull,
+    #[msg("Address is on the blocklist")]
+    AddressBlocked,
+    #[msg("Blocklist entry account does not match the screened address")]
+    InvalidBlocklistEntry,
+    #[msg("Callbacks are only allowed on withdrawals")]
+    CallbackNotAllowed,
+    #[msg("Callback exceeds the maximum number of accounts or data length")]
+    CallbackTooLarge,
+    #[msg("Callback accounts do not match the callback in ExtData")]
+    InvalidCallbackAccounts,
+    #[msg("Multiple recipients are only allowed on withdrawals")]
+    MultiRecipientNotAllowed,
+    #[msg("Invalid number of withdrawal recipients")]
+    InvalidRecipientCount,
+    #[msg("Withdrawal recipient amounts do not sum to the withdrawn amount")]
+    RecipientAmountsMismatch,
+    #[msg("Encrypted output size is out of the configured bounds")]
+    EncryptedOutputSizeOutOfBounds,
+    #[msg("Encrypted output is not a valid envelope")]
+    InvalidEncryptedOutputEnvelope,
+    #[msg("Invalid encrypted output bounds: min length must not exceed max length")]
+    InvalidEncryptedOutputBounds,
+    #[msg("Commitment is still within the retention window")]
+    CommitmentNotReclaimable,
+    #[msg("Refund account does not match the commitment payer")]
+    InvalidCommitmentPayer,
+    #[msg("Unknown tree hasher")]
+    InvalidTreeHasher,
+    #[msg("Account data does not match any known layout")]
+    InvalidAccountLayout,
+    #[msg("Account layout version is not supported")]
+    UnsupportedAccountVersion,
+    #[msg("Deposit amount is below the minimum deposit amount")]
+    DepositBelowMinimum,
+    #[msg("Deposit amount is not one of the allowed denominations")]
+    DepositDenominationNotAllowed,
+    #[msg("Deposit denominations must be distinct, non-zero, at least the minimum and at most 8")]
+    InvalidDepositDenominations,
+    #[msg("Fee does not cover the rent of the accounts created by the transaction")]
+    FeeBelowRent,
+    #[msg("Nullifier accounts do not match the nullifier mode")]
+    InvalidNullifierAccounts,
+    #[msg("Nullifier mode can't be changed back to Pda")]
+    NullifierModeTransitionNotAllowed,
+    #[msg("Nullifiers spent before the nullifier tree must be backfilled first")]
+    NullifierBackfillIncomplete,
+    #[msg("Missing insert proof for an input nullifier")]
+    MissingNullifierProof,
+    #[msg("Nullifier has already been spent")]
+    NullifierAlreadySpent,
+    #[msg("Invalid nullifier insert proof")]
+    InvalidNullifierProof,
+    #[msg("Nullifier insert proof does not match the current nullifier tree root")]
+    StaleNullifierProof,
+    #[msg("Nullifier tree is full")]
+    NullifierTreeFull,
+    #[msg("Auditor viewing key is not registered")]
+    ViewingKeyNotRegistered,
+    #[msg("Auditor ciphertext must be an X25519ChaCha20Poly1305 envelope of at most 256 bytes")]
+    InvalidAuditorCiphertext,
+    #[msg("Pending withdrawal account must be passed exactly for withdrawals above the delay threshold")]
+    InvalidPendingWithdrawalAccount,
+    #[msg("Delayed withdrawals can't have multiple recipients, a callback or a swap")]
+    DelayedWithdrawalNotSupported,
+    #[msg("Pending withdrawal is still delayed or frozen by an emergency")]
+    WithdrawalNotReleasable,
+    #[msg("Withdrawals can only be vetoed during an emergency")]
+    NoActiveEmergency,
+    #[msg("Refund account does not match the pending withdrawal payer")]
+    InvalidWithdrawalPayer,
+    #[msg("Memo exceeds the maximum length")]
+    MemoTooLong,
+    #[msg("Referrers are only allowed on deposits")]
+    ReferrerNotAllowed,
+    #[msg("Referrer stats account must be passed exactly when the ext data has a referrer")]
+    InvalidReferrerStatsAccount,
+    #[msg("No swap program is configured")]
+    SwapNotEnabled,
+    #[msg("Swaps are only allowed on single recipient withdrawals without a callback")]
+    SwapNotAllowed,
+    #[msg("Swap exceeds the maximum number of accounts or data length")]
+    SwapTooLarge,
+    #[msg("Swap min_out must be positive")]
+    InvalidSwapParams,
+    #[msg("Swap accounts do not match the swap in ExtData")]
+    InvalidSwapAccounts,
+    #[msg("Swap output must go to a token account of the output mint owned by the recipient")]
+    InvalidSwapTokenAccount,
+    #[msg("Swap output is below min_out")]
+    SlippageExceeded,
+    #[msg("Swap must spend exactly the withdrawn amount")]
+    SwapInputNotSpent,
+    #[msg("Too many internal transfers in this slot")]
+    TransferLimitExceeded,
+    #[msg("Amount exceeds the vetoed lamports held by the pool")]
+    InsufficientVetoedFunds,
 }
\ No newline at end of file
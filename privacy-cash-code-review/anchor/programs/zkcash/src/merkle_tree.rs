@@ -2,6 +2,40 @@
 use light_hasher::Hasher;
 use crate::{MerkleTreeAccount, ErrorCode};
 use anchor_lang::prelude::*;
+use std::ops::Range;
+
+/// `root_history_next_index` of roots pushed before it was recorded, see migration.rs.
+pub const ROOT_NEXT_INDEX_UNKNOWN: u64 = u64::MAX;
+/// First `MerkleTreeAccount` version recording `root_history_next_index`.
+pub const ROOT_NEXT_INDEX_VERSION: u8 = 3;
+
+/**
+ * Hash function of a deployment's tree, recorded in `MerkleTreeAccount.hasher` for off-chain
+ * tooling. Trees created before the field existed read 0, i.e. Poseidon. Another hasher would
+ * need its own circuit and verifying key, so Poseidon is the only one.
+ */
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u8)]
+pub enum TreeHasher {
+    Poseidon = 0,
+}
+
+impl TryFrom<u8> for TreeHasher {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self> {
+        match value {
+            0 => Ok(TreeHasher::Poseidon),
+            _ => err!(ErrorCode::InvalidTreeHasher),
+        }
+    }
+}
+
+impl TreeHasher {
+    pub fn of(tree_account: &MerkleTreeAccount) -> Result<Self> {
+        TreeHasher::try_from(tree_account.hasher)
+    }
+}
 
 pub struct MerkleTree;
 
@@ -10,15 +44,13 @@ impl MerkleTree {
         let height = tree_account.height as usize;
         
         // Initialize empty subtrees
-        let zero_bytes = H::zero_bytes();
-        for i in 0..height {
-            tree_account.subtrees[i] = zero_bytes[i];
-        }
+        tree_account.subtrees[..height].copy_from_slice(&H::zero_bytes()[..height]);
 
         // Set initial root
         let initial_root = H::zero_bytes()[height];
         tree_account.root = initial_root;
         tree_account.root_history[0] = initial_root;
+        tree_account.root_history_next_index[0] = 0;
         
         Ok(())
     }
@@ -28,7 +60,6 @@ impl MerkleTree {
         tree_account: &mut MerkleTreeAccount,
     ) -> Result<Vec<[u8; 32]>> {
         let height = tree_account.height as usize;
-        let root_history_size = tree_account.root_history_size as usize;
         
         // Check if tree is full before appending
         // Maximum capacity is 2^height leaves
@@ -44,7 +75,7 @@ impl MerkleTree {
         let mut right;
         let mut proof: Vec<[u8; 32]> = vec![[0u8; 32]; height];
 
-        for i in 0..height {
+        for (i, sibling) in proof.iter_mut().enumerate() {
             let subtree = &mut tree_account.subtrees[i];
             let zero_byte = H::zero_bytes()[i];
             
@@ -52,33 +83,117 @@ impl MerkleTree {
                 left = current_level_hash;
                 right = zero_byte;
                 *subtree = current_level_hash;
-                proof[i] = right;
+                *sibling = right;
             } else {
                 left = *subtree;
                 right = current_level_hash;
-                proof[i] = left;
+                *sibling = left;
             }
             current_level_hash = H::hashv(&[&left, &right]).unwrap();
             current_index /= 2;
         }
         
-        tree_account.root = current_level_hash;
         tree_account.next_index = tree_account.next_index
             .checked_add(1)
             .ok_or(ErrorCode::ArithmeticOverflow)?;
+        Self::push_root(tree_account, current_level_hash)?;
         
+        Ok(proof)
+    }
+
+    /**
+     * Appends two leaves as siblings `2k` and `2k+1`, which share every level above the
+     * first, so the path is hashed once instead of twice. The resulting root is the same as
+     * with two `append` calls, but only that final root is pushed into the root history:
+     * the intermediate root with just `left` inserted was never observable anyway.
+     *
+     * Falls back to two appends if `next_index` is odd, since the leaves aren't siblings then.
+     */
+    pub fn append_pair<H: Hasher>(
+        left: [u8; 32],
+        right: [u8; 32],
+        tree_account: &mut MerkleTreeAccount,
+    ) -> Result<()> {
+        if tree_account.next_index % 2 == 1 {
+            Self::append::<H>(left, tree_account)?;
+            Self::append::<H>(right, tree_account)?;
+            return Ok(());
+        }
+
+        let height = tree_account.height as usize;
+        let max_capacity = 1u64 << height; // 2^height
+        let new_next_index = tree_account.next_index
+            .checked_add(2)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        require!(new_next_index <= max_capacity, ErrorCode::MerkleTreeFull);
+
+        // Level 0 is the pair itself, the subtree keeps the left leaf as `append` would
+        tree_account.subtrees[0] = left;
+        let mut current_level_hash = H::hashv(&[&left, &right]).unwrap();
+        let mut current_index = (tree_account.next_index / 2) as usize;
+
+        for i in 1..height {
+            let subtree = &mut tree_account.subtrees[i];
+            let (left_node, right_node) = if current_index % 2 == 0 {
+                *subtree = current_level_hash;
+                (current_level_hash, H::zero_bytes()[i])
+            } else {
+                (*subtree, current_level_hash)
+            };
+            current_level_hash = H::hashv(&[&left_node, &right_node]).unwrap();
+            current_index /= 2;
+        }
+
+        tree_account.next_index = new_next_index;
+        Self::push_root(tree_account, current_level_hash)
+    }
+
+    fn push_root(tree_account: &mut MerkleTreeAccount, root: [u8; 32]) -> Result<()> {
+        let root_history_size = tree_account.root_history_size as usize;
+
+        tree_account.root = root;
         let new_root_index = (tree_account.root_index as usize)
             .checked_add(1)
             .ok_or(ErrorCode::ArithmeticOverflow)? % root_history_size;
         tree_account.root_index = new_root_index as u64;
-        tree_account.root_history[new_root_index] = current_level_hash;
-        
-        Ok(proof)
+        tree_account.root_history[new_root_index] = root;
+        tree_account.root_history_next_index[new_root_index] = tree_account.next_index;
+
+        Ok(())
     }
 
     pub fn is_known_root(tree_account: &MerkleTreeAccount, root: [u8; 32]) -> bool {
+        Self::find_root(tree_account, root).is_some()
+    }
+
+    /**
+     * Leaves covered by a root in the history: `0..next_index` as of when it was pushed. Light
+     * clients synced up to `root` only need the leaves from the end of its range to the current
+     * `next_index`.
+     *
+     * None if the root isn't in the history, or was pushed before the account recorded ranges.
+     */
+    pub fn root_leaf_range(tree_account: &MerkleTreeAccount, root: [u8; 32]) -> Option<Range<u64>> {
+        if tree_account.version < ROOT_NEXT_INDEX_VERSION {
+            return None;
+        }
+        let next_index = tree_account.root_history_next_index[Self::find_root(tree_account, root)?];
+        if next_index == ROOT_NEXT_INDEX_UNKNOWN {
+            return None;
+        }
+        Some(0..next_index)
+    }
+
+    /// How many leaves were appended since `root` was current, 0 for the current root.
+    pub fn leaves_since_root(tree_account: &MerkleTreeAccount, root: [u8; 32]) -> Option<u64> {
+        let range = Self::root_leaf_range(tree_account, root)?;
+        tree_account.next_index.checked_sub(range.end)
+    }
+
+    /// Position of `root` in the root history, searching from the most recent entry.
+    fn find_root(tree_account: &MerkleTreeAccount, root: [u8; 32]) -> Option<usize> {
         if root == [0u8; 32] {
-            return false;
+            return None;
         }
         
         let root_history_size = tree_account.root_history_size as usize;
@@ -87,7 +202,7 @@ impl MerkleTree {
         
         loop {
             if root == tree_account.root_history[i] {
-                return true;
+                return Some(i);
             }
             
             if i == 0 {
@@ -101,6 +216,6 @@ impl MerkleTree {
             }
         }
         
-        false
+        None
     }
 } 
\ No newline at end of file
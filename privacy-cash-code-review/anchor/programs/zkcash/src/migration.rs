@@ -0,0 +1,201 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{self, Transfer};
+use anchor_lang::Discriminator;
+use crate::merkle_tree::ROOT_NEXT_INDEX_UNKNOWN;
+use crate::nullifier_tree::NullifierMode;
+use crate::utils::MAX_DEPOSIT_DENOMINATIONS;
+use crate::{ErrorCode, GlobalConfig, MerkleTreeAccount, TreeTokenAccount, DEFAULT_MAX_ENCRYPTED_OUTPUT_LEN};
+
+/**
+ * Layout versioning of the state accounts.
+ *
+ * Version 1 is the original layout: no version field, no reserved space, and sized with
+ * `std::mem::size_of`. It is recognized by its exact length. From version 2 on, every state
+ * account stores its version and is allocated with reserved space (`SPACE`):
+ * - Borsh accounts (`GlobalConfig`, `TreeTokenAccount`) append new fields at the end of the
+ *   struct, read as zeros from the reserved tail of existing accounts. `GlobalConfig` keeps the
+ *   v1 fields first, so its v1 layout is a prefix of every later one.
+ * - `MerkleTreeAccount` carves new fields out of `_reserved`.
+ *
+ * So a field whose all-zero value is a sane default needs no migration at all; anything else
+ * bumps the account's `VERSION` and adds an upgrade step here.
+ */
+pub const GLOBAL_CONFIG_V1_LEN: usize = 8 + 40;
+pub const TREE_TOKEN_ACCOUNT_V1_LEN: usize = 8 + 33;
+pub const MERKLE_TREE_ACCOUNT_V1_LEN: usize = 8 + 4128;
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct GlobalConfigV1 {
+    pub authority: Pubkey,
+    pub deposit_fee_rate: u16,
+    pub withdrawal_fee_rate: u16,
+    pub fee_error_margin: u16,
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct TreeTokenAccountV1 {
+    pub authority: Pubkey,
+    pub bump: u8,
+}
+
+/// The authority is the first field of every state account, in every version.
+pub fn read_authority(data: &[u8]) -> Result<Pubkey> {
+    let bytes: [u8; 32] = data
+        .get(8..40)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or(ErrorCode::InvalidAccountLayout)?;
+    Ok(Pubkey::new_from_array(bytes))
+}
+
+fn serialize_padded<T: AccountSerialize>(account: &T, space: usize) -> Result<Vec<u8>> {
+    let mut data = Vec::with_capacity(space);
+    account.try_serialize(&mut data)?;
+    require!(data.len() <= space, ErrorCode::InvalidAccountLayout);
+    data.resize(space, 0);
+    Ok(data)
+}
+
+pub fn upgrade_global_config_v1(v1: GlobalConfigV1) -> GlobalConfig {
+    // New settings get the defaults of `initialize`
+    GlobalConfig {
+        authority: v1.authority,
+        deposit_fee_rate: v1.deposit_fee_rate,
+        withdrawal_fee_rate: v1.withdrawal_fee_rate,
+        fee_error_margin: v1.fee_error_margin,
+        bump: v1.bump,
+        version: GlobalConfig::VERSION,
+        screen_withdrawals: false,
+        min_encrypted_output_len: 0,
+        max_encrypted_output_len: DEFAULT_MAX_ENCRYPTED_OUTPUT_LEN,
+        require_output_envelope: false,
+        commitment_retention_leaves: 0,
+        commitment_retention_slots: 0,
+        min_deposit_amount: 0,
+        deposit_denominations: [0; MAX_DEPOSIT_DENOMINATIONS],
+        require_rent_in_fee: false,
+        nullifier_mode: NullifierMode::Pda,
+        withdrawal_delay_threshold: 0,
+        withdrawal_delay_slots: 0,
+        emergency: false,
+        swap_program: Pubkey::default(),
+        transfer_fee: 0,
+        max_transfers_per_slot: 0,
+    }
+}
+
+/**
+ * Version 3 records the `next_index` of each root in the history. It is only known for the
+ * current root, older roots report an unknown range until they are pushed out of the history.
+ */
+pub fn upgrade_merkle_tree_account_v2(tree_account: &mut MerkleTreeAccount) {
+    tree_account.root_history_next_index = [ROOT_NEXT_INDEX_UNKNOWN; 100];
+    if let Some(next_index) = tree_account.root_history_next_index.get_mut(tree_account.root_index as usize) {
+        *next_index = tree_account.next_index;
+    }
+    tree_account.version = MerkleTreeAccount::VERSION;
+}
+
+pub fn upgrade_tree_token_account_v1(v1: TreeTokenAccountV1) -> TreeTokenAccount {
+    TreeTokenAccount {
+        authority: v1.authority,
+        version: TreeTokenAccount::VERSION,
+        bump: v1.bump,
+        reserved_lamports: 0,
+        vetoed_lamports: 0,
+    }
+}
+
+/**
+ * Signature of the `migrate_*_data` functions below.
+ */
+pub type MigrateData = fn(&[u8]) -> Result<Option<Vec<u8>>>;
+
+/**
+ * Returns the upgraded account data, or `None` if the account already has the current layout.
+ */
+pub fn migrate_global_config_data(data: &[u8]) -> Result<Option<Vec<u8>>> {
+    require!(data.starts_with(GlobalConfig::DISCRIMINATOR), ErrorCode::InvalidAccountLayout);
+
+    if data.len() == GLOBAL_CONFIG_V1_LEN {
+        let v1 = GlobalConfigV1::deserialize(&mut &data[8..])?;
+        return serialize_padded(&upgrade_global_config_v1(v1), GlobalConfig::SPACE).map(Some);
+    }
+
+    require!(data.len() == GlobalConfig::SPACE, ErrorCode::InvalidAccountLayout);
+    let global_config = GlobalConfig::try_deserialize(&mut &data[..])?;
+    require!(global_config.version == GlobalConfig::VERSION, ErrorCode::UnsupportedAccountVersion);
+    Ok(None)
+}
+
+pub fn migrate_tree_token_account_data(data: &[u8]) -> Result<Option<Vec<u8>>> {
+    require!(data.starts_with(TreeTokenAccount::DISCRIMINATOR), ErrorCode::InvalidAccountLayout);
+
+    if data.len() == TREE_TOKEN_ACCOUNT_V1_LEN {
+        let v1 = TreeTokenAccountV1::deserialize(&mut &data[8..])?;
+        return serialize_padded(&upgrade_tree_token_account_v1(v1), TreeTokenAccount::SPACE).map(Some);
+    }
+
+    require!(data.len() == TreeTokenAccount::SPACE, ErrorCode::InvalidAccountLayout);
+    let tree_token_account = TreeTokenAccount::try_deserialize(&mut &data[..])?;
+    require!(tree_token_account.version == TreeTokenAccount::VERSION, ErrorCode::UnsupportedAccountVersion);
+    Ok(None)
+}
+
+pub fn migrate_merkle_tree_account_data(data: &[u8]) -> Result<Option<Vec<u8>>> {
+    require!(data.starts_with(MerkleTreeAccount::DISCRIMINATOR), ErrorCode::InvalidAccountLayout);
+
+    if data.len() == MERKLE_TREE_ACCOUNT_V1_LEN {
+        // v1 is a prefix of the v2 layout, with zeros where `version` and `hasher` are
+        let mut new_data = data.to_vec();
+        new_data.resize(MerkleTreeAccount::SPACE, 0);
+        let mut tree_account: MerkleTreeAccount = bytemuck::pod_read_unaligned(&new_data[8..]);
+        upgrade_merkle_tree_account_v2(&mut tree_account);
+        new_data[8..].copy_from_slice(bytemuck::bytes_of(&tree_account));
+        return Ok(Some(new_data));
+    }
+
+    require!(data.len() == MerkleTreeAccount::SPACE, ErrorCode::InvalidAccountLayout);
+    let mut tree_account: MerkleTreeAccount = bytemuck::pod_read_unaligned(&data[8..]);
+    if tree_account.version == 2 {
+        // Same size, `root_history_next_index` was reserved space
+        upgrade_merkle_tree_account_v2(&mut tree_account);
+        let mut new_data = data.to_vec();
+        new_data[8..].copy_from_slice(bytemuck::bytes_of(&tree_account));
+        return Ok(Some(new_data));
+    }
+    require!(tree_account.version == MerkleTreeAccount::VERSION, ErrorCode::UnsupportedAccountVersion);
+    Ok(None)
+}
+
+/**
+ * Reallocates `account` to fit `new_data` and writes it. The payer covers the additional rent,
+ * so pool funds held by the tree token account never pay for its own growth.
+ */
+pub fn write_resized<'info>(
+    account: &AccountInfo<'info>,
+    new_data: &[u8],
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    let rent = Rent::get()?;
+    let additional_rent = rent
+        .minimum_balance(new_data.len())
+        .saturating_sub(rent.minimum_balance(account.data_len()));
+    if additional_rent > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                Transfer {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+            ),
+            additional_rent,
+        )?;
+    }
+
+    account.realloc(new_data.len(), false)?;
+    account.try_borrow_mut_data()?.copy_from_slice(new_data);
+    Ok(())
+}
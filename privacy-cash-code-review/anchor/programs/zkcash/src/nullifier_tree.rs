@@ -0,0 +1,197 @@
+//! Spent nullifiers as an indexed Merkle tree, an alternative to one `NullifierAccount` PDA each.
+//!
+//! Leaves form a linked list sorted by value: each holds a nullifier and the index and value of
+//! the next larger one, with `next_value` 0 standing for "no larger value". Leaf 0 is the
+//! sentinel (0, 0, 0). A nullifier `v` is unspent iff some leaf satisfies `value < v < next_value`,
+//! the low leaf, so inserting `v` proves non-membership by opening the low leaf against the
+//! current root. The low leaf is then pointed at `v` and `v` is appended with the low leaf's old
+//! successor. The account only keeps the root and the right-most subtrees, so any number of
+//! nullifiers fits in one fixed-size account; clients keep the full tree to produce the proofs,
+//! see `zkcash_client::nullifier_tree`.
+//!
+//! Proofs open the current root, not a recent one: a stale root could predate the insertion of
+//! the very nullifier being spent. Concurrent inserts therefore invalidate each other's proofs
+//! and the later transaction has to be retried with fresh ones.
+use light_hasher::Hasher;
+use crate::{ErrorCode, NullifierTreeAccount};
+use anchor_lang::prelude::*;
+
+pub const NULLIFIER_TREE_HEIGHT: usize = 26;
+
+/**
+ * How `transact` records spent nullifiers, chosen at `initialize` and stored in
+ * `GlobalConfig.nullifier_mode`. Configs created before the field existed read 0, i.e. Pda.
+ *
+ * Dual records every nullifier both ways so an existing pool can move to the tree: once the
+ * nullifiers that predate the tree have been backfilled, Compressed stops creating PDAs.
+ */
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u8)]
+pub enum NullifierMode {
+    Pda = 0,
+    Dual = 1,
+    Compressed = 2,
+}
+
+impl NullifierMode {
+    pub fn uses_pdas(self) -> bool {
+        self != NullifierMode::Compressed
+    }
+
+    pub fn uses_tree(self) -> bool {
+        self != NullifierMode::Pda
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct IndexedLeaf {
+    pub value: [u8; 32],
+    pub next_index: u64,
+    pub next_value: [u8; 32], // [0; 32] = no larger value
+}
+
+impl IndexedLeaf {
+    pub fn hash<H: Hasher>(&self) -> Result<[u8; 32]> {
+        let mut next_index = [0u8; 32];
+        next_index[24..].copy_from_slice(&self.next_index.to_be_bytes());
+        H::hashv(&[&self.value, &next_index, &self.next_value])
+            .map_err(|_| error!(ErrorCode::InvalidNullifierProof))
+    }
+
+    /// Whether `value` falls strictly between this leaf and its successor.
+    pub fn is_low_leaf_for(&self, value: &[u8; 32]) -> bool {
+        self.value < *value && (self.next_value == [0u8; 32] || *value < self.next_value)
+    }
+}
+
+/// Opening of the low leaf of a nullifier against the current root of the nullifier tree.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct NullifierInsertProof {
+    pub low_leaf: IndexedLeaf,
+    pub low_leaf_index: u64,
+    pub siblings: [[u8; 32]; NULLIFIER_TREE_HEIGHT],
+}
+
+impl NullifierInsertProof {
+    pub const LEN: usize = 32 + 8 + 32 + 8 + 32 * NULLIFIER_TREE_HEIGHT;
+}
+
+pub struct NullifierTree;
+
+impl NullifierTree {
+    pub fn initialize<H: Hasher>(
+        tree_account: &mut NullifierTreeAccount,
+        legacy_nullifiers: u64,
+    ) -> Result<()> {
+        let height = NULLIFIER_TREE_HEIGHT;
+        let zero_bytes = H::zero_bytes();
+        tree_account.height = height as u8;
+        tree_account.next_index = 0;
+        tree_account.subtrees = zero_bytes[..height].try_into().unwrap();
+        tree_account.root = zero_bytes[height];
+        tree_account.legacy_nullifiers = legacy_nullifiers;
+        tree_account.backfilled_nullifiers = 0;
+
+        Self::append::<H>(IndexedLeaf::default().hash::<H>()?, tree_account)
+    }
+
+    /**
+     * Inserts `value`, failing if it is already in the tree. `proof` must open the low leaf of
+     * `value` against the current root.
+     */
+    pub fn insert<H: Hasher>(
+        value: [u8; 32],
+        proof: &NullifierInsertProof,
+        tree_account: &mut NullifierTreeAccount,
+    ) -> Result<()> {
+        let low_leaf = proof.low_leaf;
+        require!(
+            low_leaf.value != value && low_leaf.next_value != value,
+            ErrorCode::NullifierAlreadySpent
+        );
+        require!(
+            low_leaf.is_low_leaf_for(&value) && proof.low_leaf_index < tree_account.next_index,
+            ErrorCode::InvalidNullifierProof
+        );
+        require!(
+            Self::compute_root::<H>(low_leaf.hash::<H>()?, proof.low_leaf_index, &proof.siblings)?
+                == tree_account.root,
+            ErrorCode::StaleNullifierProof
+        );
+
+        let new_index = tree_account.next_index;
+        require!(
+            new_index < 1u64 << tree_account.height,
+            ErrorCode::NullifierTreeFull
+        );
+        let updated_low_leaf = IndexedLeaf {
+            value: low_leaf.value,
+            next_index: new_index,
+            next_value: value,
+        };
+        let new_leaf = IndexedLeaf {
+            value,
+            next_index: low_leaf.next_index,
+            next_value: low_leaf.next_value,
+        };
+
+        // Rehash the low leaf's path. The siblings are unaffected, but the low leaf may be under
+        // a left subtree the next append hashes with, which must then be updated too.
+        let mut current_index = proof.low_leaf_index;
+        let mut current_level_hash = updated_low_leaf.hash::<H>()?;
+        for (level, sibling) in proof.siblings.iter().enumerate() {
+            let append_index = new_index >> level;
+            if append_index % 2 == 1 && current_index == append_index - 1 {
+                tree_account.subtrees[level] = current_level_hash;
+            }
+            current_level_hash = Self::hash_pair::<H>(current_index, current_level_hash, *sibling)?;
+            current_index /= 2;
+        }
+        tree_account.root = current_level_hash;
+
+        Self::append::<H>(new_leaf.hash::<H>()?, tree_account)
+    }
+
+    pub fn compute_root<H: Hasher>(
+        leaf: [u8; 32],
+        index: u64,
+        siblings: &[[u8; 32]],
+    ) -> Result<[u8; 32]> {
+        let mut current_index = index;
+        let mut current_level_hash = leaf;
+        for sibling in siblings {
+            current_level_hash = Self::hash_pair::<H>(current_index, current_level_hash, *sibling)?;
+            current_index /= 2;
+        }
+        Ok(current_level_hash)
+    }
+
+    fn hash_pair<H: Hasher>(index: u64, node: [u8; 32], sibling: [u8; 32]) -> Result<[u8; 32]> {
+        let (left, right) = if index % 2 == 0 { (node, sibling) } else { (sibling, node) };
+        H::hashv(&[&left, &right]).map_err(|_| error!(ErrorCode::InvalidNullifierProof))
+    }
+
+    fn append<H: Hasher>(leaf: [u8; 32], tree_account: &mut NullifierTreeAccount) -> Result<()> {
+        let height = tree_account.height as usize;
+        let mut current_index = tree_account.next_index;
+        let mut current_level_hash = leaf;
+
+        for i in 0..height {
+            let subtree = &mut tree_account.subtrees[i];
+            let (left, right) = if current_index % 2 == 0 {
+                *subtree = current_level_hash;
+                (current_level_hash, H::zero_bytes()[i])
+            } else {
+                (*subtree, current_level_hash)
+            };
+            current_level_hash = H::hashv(&[&left, &right]).unwrap();
+            current_index /= 2;
+        }
+
+        tree_account.root = current_level_hash;
+        tree_account.next_index = tree_account.next_index
+            .checked_add(1)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,49 @@
+use anchor_lang::prelude::*;
+use crate::{ErrorCode, ReferrerStats};
+
+pub const REFERRER_SEED: &[u8] = b"referrer";
+pub const MAX_MEMO_LEN: usize = 64;
+
+/**
+ * Emitted by `transact` when the ext data carries a memo or referrer. It deliberately omits the
+ * commitments and their indices, so partners can attribute volume from the logs without
+ * indexing which notes their users created.
+ */
+#[event]
+pub struct ExtDataTagEvent {
+    pub referrer: Option<[u8; 32]>,
+    pub memo: Option<Vec<u8>>,
+    pub ext_amount: i64,
+}
+
+/**
+ * Memos are opaque to the program, only their length is bounded. Referrers are only accepted on
+ * deposits, since the per-referrer counters only track deposit volume.
+ */
+pub fn validate_tags(memo: &Option<Vec<u8>>, referrer: &Option<[u8; 32]>, ext_amount: i64) -> Result<()> {
+    if let Some(memo) = memo {
+        require!(memo.len() <= MAX_MEMO_LEN, ErrorCode::MemoTooLong);
+    }
+    if referrer.is_some() {
+        require!(ext_amount > 0, ErrorCode::ReferrerNotAllowed);
+    }
+    Ok(())
+}
+
+/// Seed of the `ReferrerStats` PDA, empty when there is no referrer and the account is omitted.
+pub fn referrer_seed(referrer: &Option<[u8; 32]>) -> &[u8] {
+    referrer.as_ref().map_or(&[], |referrer| referrer)
+}
+
+/// Adds a deposit to the aggregate counters of its referrer, created on its first deposit.
+pub fn record_deposit(stats: &mut ReferrerStats, referrer: [u8; 32], amount: u64, bump: u8) -> Result<()> {
+    stats.referrer = referrer;
+    stats.bump = bump;
+    stats.deposit_count = stats.deposit_count
+        .checked_add(1)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+    stats.deposit_volume = stats.deposit_volume
+        .checked_add(amount)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+    Ok(())
+}
@@ -0,0 +1,131 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::invoke_signed;
+use crate::{ErrorCode, SwapParams};
+
+pub const SWAP_AUTHORITY_SEED: &[u8] = b"swap_authority";
+pub const MAX_SWAP_ACCOUNTS: usize = 16;
+pub const MAX_SWAP_DATA_LEN: usize = 256;
+
+pub const TOKEN_PROGRAM_ID: Pubkey = anchor_lang::pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+pub const TOKEN_2022_PROGRAM_ID: Pubkey = anchor_lang::pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+
+// Layout shared by SPL Token and Token-2022 accounts: mint, owner, amount
+const TOKEN_ACCOUNT_LEN: usize = 165;
+const TOKEN_ACCOUNT_MINT_OFFSET: usize = 0;
+const TOKEN_ACCOUNT_OWNER_OFFSET: usize = 32;
+const TOKEN_ACCOUNT_AMOUNT_OFFSET: usize = 64;
+
+/// Checks a swap against the configured swap program before any funds move.
+pub fn validate_swap(swap: &SwapParams, swap_program: &Pubkey, swap_authority: &Pubkey) -> Result<()> {
+    require!(*swap_program != Pubkey::default(), ErrorCode::SwapNotEnabled);
+    require!(
+        swap.accounts.len() <= MAX_SWAP_ACCOUNTS && swap.data.len() <= MAX_SWAP_DATA_LEN,
+        ErrorCode::SwapTooLarge
+    );
+    require!(swap.min_out > 0, ErrorCode::InvalidSwapParams);
+
+    // The swap program must be able to pay out to the token account and spend the authority's lamports
+    require!(
+        swap.accounts.contains(&swap.recipient_token_account) && swap.accounts.contains(swap_authority),
+        ErrorCode::InvalidSwapAccounts
+    );
+    Ok(())
+}
+
+/**
+ * Reads the balance of an SPL Token or Token-2022 account, checking that it holds `mint` and
+ * belongs to `owner`.
+ */
+pub fn token_account_balance(account: &AccountInfo, mint: &Pubkey, owner: &Pubkey) -> Result<u64> {
+    require!(
+        *account.owner == TOKEN_PROGRAM_ID || *account.owner == TOKEN_2022_PROGRAM_ID,
+        ErrorCode::InvalidSwapTokenAccount
+    );
+    let data = account.try_borrow_data()?;
+    require!(data.len() >= TOKEN_ACCOUNT_LEN, ErrorCode::InvalidSwapTokenAccount);
+    require!(
+        data[TOKEN_ACCOUNT_MINT_OFFSET..TOKEN_ACCOUNT_MINT_OFFSET + 32] == mint.to_bytes()
+            && data[TOKEN_ACCOUNT_OWNER_OFFSET..TOKEN_ACCOUNT_OWNER_OFFSET + 32] == owner.to_bytes(),
+        ErrorCode::InvalidSwapTokenAccount
+    );
+    let amount = data[TOKEN_ACCOUNT_AMOUNT_OFFSET..TOKEN_ACCOUNT_AMOUNT_OFFSET + 8]
+        .try_into()
+        .map_err(|_| ErrorCode::InvalidSwapTokenAccount)?;
+    Ok(u64::from_le_bytes(amount))
+}
+
+/**
+ * Swaps the `amount_in` lamports of a withdrawal, already moved to the swap authority, into
+ * `swap.output_mint` for the withdrawal recipient. Returns the amount received.
+ *
+ * `swap_accounts` must be the configured swap program followed by exactly the accounts listed
+ * in `swap.accounts`, in order. Only the swap authority signs, and accounts owned by this program
+ * are rejected, as for callbacks. The swap must spend exactly `amount_in` and credit at least
+ * `swap.min_out` to `swap.recipient_token_account`, which must be owned by `recipient`.
+ */
+pub fn invoke_swap<'info>(
+    swap: &SwapParams,
+    swap_program: &Pubkey,
+    swap_accounts: &[AccountInfo<'info>],
+    swap_authority: &Pubkey,
+    swap_authority_bump: u8,
+    recipient: &Pubkey,
+    amount_in: u64,
+) -> Result<u64> {
+    validate_swap(swap, swap_program, swap_authority)?;
+    require!(
+        swap_accounts.len() == swap.accounts.len() + 1,
+        ErrorCode::InvalidSwapAccounts
+    );
+
+    let program_account = &swap_accounts[0];
+    require!(
+        program_account.key() == *swap_program && program_account.executable,
+        ErrorCode::InvalidSwapAccounts
+    );
+    require!(*swap_program != crate::ID, ErrorCode::InvalidSwapAccounts);
+
+    let mut account_metas = Vec::with_capacity(swap.accounts.len());
+    for (account_info, expected_key) in swap_accounts[1..].iter().zip(swap.accounts.iter()) {
+        require!(
+            account_info.key() == *expected_key && *account_info.owner != crate::ID,
+            ErrorCode::InvalidSwapAccounts
+        );
+        let is_signer = expected_key == swap_authority;
+        account_metas.push(if account_info.is_writable {
+            AccountMeta::new(*expected_key, is_signer)
+        } else {
+            AccountMeta::new_readonly(*expected_key, is_signer)
+        });
+    }
+
+    // Both are listed in `swap.accounts`, see `validate_swap`
+    let find_account = |key: &Pubkey| swap_accounts[1..].iter().find(|account_info| account_info.key == key);
+    let token_account = find_account(&swap.recipient_token_account).ok_or(ErrorCode::InvalidSwapAccounts)?;
+    let authority_account = find_account(swap_authority).ok_or(ErrorCode::InvalidSwapAccounts)?;
+
+    let balance_before = token_account_balance(token_account, &swap.output_mint, recipient)?;
+    let authority_lamports_before = authority_account.lamports();
+
+    let instruction = Instruction {
+        program_id: *swap_program,
+        accounts: account_metas,
+        data: swap.data.clone(),
+    };
+    invoke_signed(&instruction, swap_accounts, &[&[SWAP_AUTHORITY_SEED, &[swap_authority_bump]]])?;
+
+    // Exact-in only: leftover lamports would stay with the authority instead of the recipient
+    let authority_lamports_spent = authority_lamports_before
+        .checked_sub(authority_account.lamports())
+        .ok_or(ErrorCode::SwapInputNotSpent)?;
+    require!(authority_lamports_spent == amount_in, ErrorCode::SwapInputNotSpent);
+
+    let balance_after = token_account_balance(token_account, &swap.output_mint, recipient)?;
+    let amount_out = balance_after
+        .checked_sub(balance_before)
+        .ok_or(ErrorCode::SlippageExceeded)?;
+    require!(amount_out >= swap.min_out, ErrorCode::SlippageExceeded);
+
+    Ok(amount_out)
+}
@@ -1,4 +1,5 @@
-use crate::Proof;
+use crate::{CommitmentAccount, ExtData, MerkleTreeAccount, NullifierAccount, PendingWithdrawal, Proof, ReferrerStats, WithdrawalRecipient};
+use crate::nullifier_tree::NullifierMode;
 use crate::groth16::{Groth16Verifier, Groth16Verifyingkey};
 use crate::ErrorCode;
 use ark_bn254;
@@ -8,11 +9,23 @@ use ark_bn254::Fr;
 use ark_ff::PrimeField;
 use anchor_lang::prelude::*;
 use anchor_lang::solana_program::hash::hash;
+use zkcash_envelope::Envelope;
 
 type G1 = ark_bn254::g1::G1Affine;
 
 pub const SOL_ADDRESS: Pubkey = anchor_lang::pubkey!("11111111111111111111111111111112");
 
+// Tags prefixing optional ExtData extensions in the hashed serialization.
+pub const EXT_DATA_TAG_CALLBACK: u8 = 1;
+pub const EXT_DATA_TAG_RECIPIENTS: u8 = 2;
+pub const EXT_DATA_TAG_AUDITOR: u8 = 3;
+pub const EXT_DATA_TAG_MEMO: u8 = 4;
+pub const EXT_DATA_TAG_REFERRER: u8 = 5;
+pub const EXT_DATA_TAG_SWAP: u8 = 6;
+
+pub const MAX_WITHDRAWAL_RECIPIENTS: usize = 4;
+pub const MAX_DEPOSIT_DENOMINATIONS: usize = 8;
+
 pub const VERIFYING_KEY: Groth16Verifyingkey =  Groth16Verifyingkey {
 	nr_pubinputs: 7,
 
@@ -107,8 +120,9 @@ pub fn check_public_amount(ext_amount: i64, fee: u64, public_amount_bytes: [u8;
         Fr::from(abs_ext_amount as u64)
     };
 
-    // return false if the deposit amount is barely enough to cover the fee
-    if ext_amount >= 0 && ext_amount_fr <= fee_fr {
+    // return false if the deposit amount is barely enough to cover the fee. Internal transfers
+    // (ext_amount == 0) pay the fee out of the shielded amounts, like withdrawals.
+    if ext_amount > 0 && ext_amount_fr <= fee_fr {
         return false;
     }
 
@@ -128,7 +142,7 @@ pub fn check_public_amount(ext_amount: i64, fee: u64, public_amount_bytes: [u8;
 }
 
 /**
- * Validates that the provided fee meets the minimum required fee based on global configuration.
+ * Minimum fee accepted for a transaction based on global configuration.
  * 
  * For deposits (ext_amount > 0):
  * - expected_fee = (ext_amount * deposit_fee_rate) / 10000
@@ -138,11 +152,61 @@ pub fn check_public_amount(ext_amount: i64, fee: u64, public_amount_bytes: [u8;
  * - expected_fee = (abs(ext_amount) * withdrawal_fee_rate) / 10000
  * - minimum_fee = expected_fee * (1 - fee_error_margin/10000)
  * 
+ * For internal transfers (ext_amount == 0) the minimum fee is the flat transfer_fee, without
+ * error margin: there is no amount to apply a rate to.
+ * 
+ * @param ext_amount The external amount (positive for deposits, negative for withdrawals)
+ * @param deposit_fee_rate Fee rate for deposits (in basis points, 0-10000)
+ * @param withdrawal_fee_rate Fee rate for withdrawals (in basis points, 0-10000)
+ * @param fee_error_margin Tolerance rate (in basis points, 0-10000)
+ * @param transfer_fee Fee for internal transfers, in lamports
+ */
+pub fn minimum_fee(
+    ext_amount: i64,
+    deposit_fee_rate: u16,
+    withdrawal_fee_rate: u16,
+    fee_error_margin: u16,
+    transfer_fee: u64,
+) -> Result<u64> {
+    let (amount, fee_rate) = if ext_amount > 0 {
+        (ext_amount as u64, deposit_fee_rate)
+    } else if ext_amount < 0 {
+        let withdrawal_amount = ext_amount.checked_neg()
+            .ok_or(ErrorCode::ArithmeticOverflow)? as u64;
+        (withdrawal_amount, withdrawal_fee_rate)
+    } else {
+        return Ok(transfer_fee);
+    };
+
+    let expected_fee = (amount as u128)
+        .checked_mul(fee_rate as u128)
+        .ok_or(ErrorCode::ArithmeticOverflow)?
+        .checked_div(10000)
+        .ok_or(ErrorCode::ArithmeticOverflow)? as u64;
+    
+    // Calculate minimum acceptable fee: expected_fee * (1 - fee_error_margin/10000)
+    if expected_fee == 0 {
+        return Ok(0); // If expected fee is 0, minimum is also 0
+    }
+    let error_multiplier = 10000u128.checked_sub(fee_error_margin as u128)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+    Ok((expected_fee as u128)
+        .checked_mul(error_multiplier)
+        .ok_or(ErrorCode::ArithmeticOverflow)?
+        .checked_div(10000)
+        .ok_or(ErrorCode::ArithmeticOverflow)? as u64)
+}
+
+/**
+ * Validates that the provided fee meets the minimum required fee based on global configuration,
+ * see `minimum_fee`.
+ * 
  * @param ext_amount The external amount (positive for deposits, negative for withdrawals)
  * @param provided_fee The fee provided by the user
  * @param deposit_fee_rate Fee rate for deposits (in basis points, 0-10000)
  * @param withdrawal_fee_rate Fee rate for withdrawals (in basis points, 0-10000)
  * @param fee_error_margin Tolerance rate (in basis points, 0-10000)
+ * @param transfer_fee Fee for internal transfers, in lamports
  * @return Ok(()) if fee is valid, Err(ErrorCode) if invalid
  */
 pub fn validate_fee(
@@ -151,63 +215,296 @@ pub fn validate_fee(
     deposit_fee_rate: u16,
     withdrawal_fee_rate: u16,
     fee_error_margin: u16,
+    transfer_fee: u64,
 ) -> Result<()> {
-    if ext_amount > 0 {
-        // Deposit: check fee against deposit rate
-        let expected_fee = (ext_amount as u128)
-            .checked_mul(deposit_fee_rate as u128)
-            .ok_or(ErrorCode::ArithmeticOverflow)?
-            .checked_div(10000)
-            .ok_or(ErrorCode::ArithmeticOverflow)? as u64;
-        
-        // Calculate minimum acceptable fee: expected_fee * (1 - fee_error_margin/10000)
-        let min_acceptable_fee = if expected_fee > 0 {
-            let error_multiplier = 10000u128.checked_sub(fee_error_margin as u128)
-                .ok_or(ErrorCode::ArithmeticOverflow)?;
-            (expected_fee as u128)
-                .checked_mul(error_multiplier)
-                .ok_or(ErrorCode::ArithmeticOverflow)?
-                .checked_div(10000)
-                .ok_or(ErrorCode::ArithmeticOverflow)? as u64
-        } else {
-            0 // If expected fee is 0, minimum is also 0
-        };
-        
+    let min_acceptable_fee = minimum_fee(ext_amount, deposit_fee_rate, withdrawal_fee_rate, fee_error_margin, transfer_fee)?;
+    require!(
+        provided_fee >= min_acceptable_fee,
+        ErrorCode::InvalidFeeAmount
+    );
+    
+    Ok(())
+}
+
+/**
+ * Validates that the fee covers the rent of the PDAs created by a transact on top of the
+ * minimum fee, for deployments where the signer is reimbursed through the fee.
+ * 
+ * @param rent_lamports Rent of the created accounts, see `transact_rent`
+ */
+pub fn validate_fee_with_rent(
+    ext_amount: i64,
+    provided_fee: u64,
+    deposit_fee_rate: u16,
+    withdrawal_fee_rate: u16,
+    fee_error_margin: u16,
+    transfer_fee: u64,
+    rent_lamports: u64,
+) -> Result<()> {
+    validate_fee(ext_amount, provided_fee, deposit_fee_rate, withdrawal_fee_rate, fee_error_margin, transfer_fee)?;
+
+    let min_acceptable_fee = minimum_fee(ext_amount, deposit_fee_rate, withdrawal_fee_rate, fee_error_margin, transfer_fee)?
+        .checked_add(rent_lamports)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+    require!(
+        provided_fee >= min_acceptable_fee,
+        ErrorCode::FeeBelowRent
+    );
+    
+    Ok(())
+}
+
+pub fn nullifier_account_space() -> usize {
+    8 + std::mem::size_of::<NullifierAccount>()
+}
+
+pub fn commitment_account_space(encrypted_output_len: usize) -> usize {
+    8 + std::mem::size_of::<CommitmentAccount>() + encrypted_output_len
+}
+
+/**
+ * PDAs a transact may create with the signer as payer on top of the commitment and nullifier
+ * PDAs, see `transact_rent`.
+ *
+ * Callback and swap accounts have no entry: neither CPI passes on the signer's signature, so
+ * whatever they create is paid by the callee or out of the swapped lamports, never by the signer.
+ */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TransactPdas {
+    /// The `PendingWithdrawal` of a delayed withdrawal
+    pub pending_withdrawal: bool,
+    /// The referrer's `ReferrerStats`, created by its first referred deposit
+    pub referrer_stats: bool,
+}
+
+/**
+ * Rent paid by the signer of a transact for the two commitment PDAs it creates, the two
+ * nullifier PDAs unless `nullifier_mode` is Compressed, and the PDAs in `pdas`. Commitment PDAs
+ * are sized by their encrypted output, so the rent depends on them.
+ */
+pub fn transact_rent(
+    rent: &Rent,
+    nullifier_mode: NullifierMode,
+    encrypted_output1_len: usize,
+    encrypted_output2_len: usize,
+    pdas: TransactPdas,
+) -> Result<u64> {
+    let nullifier_pdas = if nullifier_mode.uses_pdas() { 2 } else { 0 };
+    [
+        commitment_account_space(encrypted_output1_len),
+        commitment_account_space(encrypted_output2_len),
+    ]
+    .into_iter()
+    .chain(std::iter::repeat_n(nullifier_account_space(), nullifier_pdas))
+    .chain(pdas.pending_withdrawal.then_some(PendingWithdrawal::SPACE))
+    .chain(pdas.referrer_stats.then_some(ReferrerStats::SPACE))
+    .try_fold(0u64, |total, space| total.checked_add(rent.minimum_balance(space)))
+    .ok_or(ErrorCode::ArithmeticOverflow.into())
+}
+
+/**
+ * Validates the amount of a deposit (ext_amount > 0) against the pool's deposit rules.
+ * Withdrawals are not affected.
+ *
+ * @param min_deposit_amount Smallest accepted ext_amount, 0 for no minimum
+ * @param denominations Accepted ext_amounts, zero entries are unused slots. When all entries
+ *        are zero, any amount is accepted.
+ */
+pub fn validate_deposit_amount(
+    ext_amount: i64,
+    min_deposit_amount: u64,
+    denominations: &[u64],
+) -> Result<()> {
+    if ext_amount <= 0 {
+        return Ok(());
+    }
+    let deposit_amount = ext_amount as u64;
+
+    require!(deposit_amount >= min_deposit_amount, ErrorCode::DepositBelowMinimum);
+
+    let mut allowed = denominations.iter().filter(|denomination| **denomination > 0).peekable();
+    if allowed.peek().is_some() {
         require!(
-            provided_fee >= min_acceptable_fee,
-            ErrorCode::InvalidFeeAmount
+            allowed.any(|denomination| *denomination == deposit_amount),
+            ErrorCode::DepositDenominationNotAllowed
         );
-    } else if ext_amount < 0 {
-        // Withdrawal: check fee against withdrawal rate
-        let withdrawal_amount = ext_amount.checked_neg()
-            .ok_or(ErrorCode::ArithmeticOverflow)? as u64;
-        
-        let expected_fee = (withdrawal_amount as u128)
-            .checked_mul(withdrawal_fee_rate as u128)
-            .ok_or(ErrorCode::ArithmeticOverflow)?
-            .checked_div(10000)
-            .ok_or(ErrorCode::ArithmeticOverflow)? as u64;
-        
-        // Calculate minimum acceptable fee: expected_fee * (1 - fee_error_margin/10000)
-        let min_acceptable_fee = if expected_fee > 0 {
-            let error_multiplier = 10000u128.checked_sub(fee_error_margin as u128)
-                .ok_or(ErrorCode::ArithmeticOverflow)?;
-            (expected_fee as u128)
-                .checked_mul(error_multiplier)
-                .ok_or(ErrorCode::ArithmeticOverflow)?
-                .checked_div(10000)
-                .ok_or(ErrorCode::ArithmeticOverflow)? as u64
-        } else {
-            0 // If expected fee is 0, minimum is also 0
-        };
-        
+    }
+
+    Ok(())
+}
+
+/**
+ * Counts an internal transfer (ext_amount == 0) towards the per-slot cap of the pool, resetting
+ * the count on the first one of a slot. Trees that predate the counters start from slot 0.
+ *
+ * @param max_transfers_per_slot Cap on internal transfers per slot, 0 for no cap
+ */
+pub fn record_transfer(
+    tree_account: &mut MerkleTreeAccount,
+    current_slot: u64,
+    max_transfers_per_slot: u16,
+) -> Result<()> {
+    if tree_account.transfer_slot != current_slot {
+        tree_account.transfer_slot = current_slot;
+        tree_account.transfers_in_slot = 0;
+    }
+    require!(
+        max_transfers_per_slot == 0 || tree_account.transfers_in_slot < max_transfers_per_slot as u64,
+        ErrorCode::TransferLimitExceeded
+    );
+    tree_account.transfers_in_slot = tree_account.transfers_in_slot
+        .checked_add(1)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+    Ok(())
+}
+
+/**
+ * Validates new deposit rules and packs the denominations into the fixed-size config array.
+ * Denominations must be non-zero, distinct and at least the minimum deposit amount, so that
+ * every configured denomination can actually be deposited.
+ */
+pub fn pack_deposit_denominations(
+    min_deposit_amount: u64,
+    denominations: &[u64],
+) -> Result<[u64; MAX_DEPOSIT_DENOMINATIONS]> {
+    require!(
+        denominations.len() <= MAX_DEPOSIT_DENOMINATIONS,
+        ErrorCode::InvalidDepositDenominations
+    );
+
+    let mut packed = [0u64; MAX_DEPOSIT_DENOMINATIONS];
+    for (i, denomination) in denominations.iter().enumerate() {
         require!(
-            provided_fee >= min_acceptable_fee,
-            ErrorCode::InvalidFeeAmount
+            *denomination > 0
+                && *denomination >= min_deposit_amount
+                && !denominations[..i].contains(denomination),
+            ErrorCode::InvalidDepositDenominations
         );
+        packed[i] = *denomination;
     }
-    // For ext_amount == 0, no fee validation needed
-    
+
+    Ok(packed)
+}
+
+/**
+ * Validates the recipient list of a multi-recipient withdrawal.
+ *
+ * - Only withdrawals (ext_amount < 0) can have multiple recipients.
+ * - Between 1 and MAX_WITHDRAWAL_RECIPIENTS recipients, each receiving a non-zero amount.
+ * - The first listed recipient must be ExtData.recipient, so the existing recipient checks still apply.
+ * - The amounts must sum exactly to abs(ext_amount).
+ */
+pub fn validate_withdrawal_recipients(
+    recipients: &[WithdrawalRecipient],
+    ext_amount: i64,
+    recipient: Pubkey,
+) -> Result<()> {
+    require!(ext_amount < 0, ErrorCode::MultiRecipientNotAllowed);
+    require!(
+        !recipients.is_empty() && recipients.len() <= MAX_WITHDRAWAL_RECIPIENTS,
+        ErrorCode::InvalidRecipientCount
+    );
+    require!(recipients[0].recipient == recipient, ErrorCode::RecipientMismatch);
+
+    let withdrawal_amount = ext_amount.checked_neg()
+        .ok_or(ErrorCode::ArithmeticOverflow)? as u64;
+    let mut total_amount: u64 = 0;
+    for withdrawal_recipient in recipients {
+        require!(withdrawal_recipient.amount > 0, ErrorCode::RecipientAmountsMismatch);
+        total_amount = total_amount
+            .checked_add(withdrawal_recipient.amount)
+            .ok_or(ErrorCode::ArithmeticOverflow)?;
+    }
+    require!(total_amount == withdrawal_amount, ErrorCode::RecipientAmountsMismatch);
+
+    Ok(())
+}
+
+/**
+ * Validates an encrypted output against the configured size bounds (inclusive), and
+ * against the envelope format when envelopes are required.
+ */
+pub fn validate_encrypted_output(
+    encrypted_output: &[u8],
+    min_len: u16,
+    max_len: u16,
+    require_envelope: bool,
+) -> Result<()> {
+    require!(
+        encrypted_output.len() >= min_len as usize && encrypted_output.len() <= max_len as usize,
+        ErrorCode::EncryptedOutputSizeOutOfBounds
+    );
+
+    if require_envelope {
+        Envelope::decode(encrypted_output).map_err(|_| ErrorCode::InvalidEncryptedOutputEnvelope)?;
+    }
+
+    Ok(())
+}
+
+/**
+ * Returns whether a commitment PDA is past the retention window and can be closed.
+ * A commitment is reclaimable once at least `retention_leaves` leaves were appended after it,
+ * or once at least `retention_slots` slots passed since it was created. A retention of 0
+ * disables that criterion, so nothing is reclaimable when both are 0.
+ */
+pub fn is_commitment_reclaimable(
+    commitment_index: u64,
+    created_slot: u64,
+    next_index: u64,
+    current_slot: u64,
+    retention_leaves: u64,
+    retention_slots: u64,
+) -> bool {
+    let old_enough_in_leaves = retention_leaves > 0
+        && next_index.saturating_sub(commitment_index.saturating_add(1)) >= retention_leaves;
+    let old_enough_in_slots = retention_slots > 0
+        && current_slot.saturating_sub(created_slot) >= retention_slots;
+
+    old_enough_in_leaves || old_enough_in_slots
+}
+
+/**
+ * Seed prefix of the commitment PDA of leaf `index`. Outputs are only ever appended in pairs,
+ * so the first output of a transact, `commitment0`, always has an even index.
+ */
+pub fn commitment_seed_prefix(index: u64) -> &'static [u8] {
+    if index % 2 == 0 {
+        b"commitment0"
+    } else {
+        b"commitment1"
+    }
+}
+
+/**
+ * Returns whether a withdrawal must wait in a `PendingWithdrawal` instead of settling immediately,
+ * i.e. whether it withdraws more than `threshold` lamports, fee excluded. A threshold of 0 disables delays.
+ */
+pub fn is_delayed_withdrawal(ext_amount: i64, threshold: u64) -> bool {
+    threshold > 0 && ext_amount < 0 && ext_amount.unsigned_abs() > threshold
+}
+
+/**
+ * Returns whether a pending withdrawal can be released. Releases are frozen while an emergency
+ * is active, so that the authority can veto withdrawals whose delay has already elapsed.
+ */
+pub fn is_withdrawal_releasable(release_slot: u64, current_slot: u64, emergency: bool) -> bool {
+    !emergency && current_slot >= release_slot
+}
+
+/**
+ * Moves lamports out of a program-owned account by mutating balances directly,
+ * since PDAs holding data can't be the source of a system transfer.
+ */
+pub fn transfer_lamports(from: &AccountInfo, to: &AccountInfo, amount: u64) -> Result<()> {
+    let new_from_balance = from.lamports().checked_sub(amount)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+    let new_to_balance = to.lamports().checked_add(amount)
+        .ok_or(ErrorCode::ArithmeticOverflow)?;
+
+    **from.try_borrow_mut_lamports()? = new_from_balance;
+    **to.try_borrow_mut_lamports()? = new_to_balance;
+
     Ok(())
 }
 
@@ -280,6 +577,85 @@ pub fn calculate_complete_ext_data_hash(
     fee_recipient: Pubkey,
     mint_address: Pubkey,
 ) -> Result<[u8; 32]> {
+    let serialized_ext_data = serialize_complete_ext_data(
+        recipient,
+        ext_amount,
+        encrypted_output1,
+        encrypted_output2,
+        fee,
+        fee_recipient,
+        mint_address,
+    )?;
+    let calculated_ext_data_hash = hash(&serialized_ext_data).to_bytes();
+    
+    Ok(calculated_ext_data_hash)
+}
+
+/**
+ * Calculate the hash of a full ExtData, including its optional extensions.
+ *
+ * Each extension that is present is appended to the complete ext data serialization as a
+ * tag byte followed by its borsh encoding. The base serialization is self-delimiting, so
+ * ExtData without extensions hashes exactly like calculate_complete_ext_data_hash and
+ * proofs generated before extensions existed remain valid.
+ */
+pub fn calculate_ext_data_hash(
+    ext_data: &ExtData,
+    encrypted_output1: &[u8],
+    encrypted_output2: &[u8],
+) -> Result<[u8; 32]> {
+    let mut serialized_ext_data = serialize_complete_ext_data(
+        ext_data.recipient,
+        ext_data.ext_amount,
+        encrypted_output1,
+        encrypted_output2,
+        ext_data.fee,
+        ext_data.fee_recipient,
+        ext_data.mint_address,
+    )?;
+
+    if let Some(callback) = &ext_data.callback {
+        serialized_ext_data.push(EXT_DATA_TAG_CALLBACK);
+        callback.serialize(&mut serialized_ext_data)?;
+    }
+
+    if let Some(recipients) = &ext_data.recipients {
+        serialized_ext_data.push(EXT_DATA_TAG_RECIPIENTS);
+        recipients.serialize(&mut serialized_ext_data)?;
+    }
+
+    if let Some(auditor) = &ext_data.auditor {
+        serialized_ext_data.push(EXT_DATA_TAG_AUDITOR);
+        auditor.serialize(&mut serialized_ext_data)?;
+    }
+
+    if let Some(memo) = &ext_data.memo {
+        serialized_ext_data.push(EXT_DATA_TAG_MEMO);
+        memo.serialize(&mut serialized_ext_data)?;
+    }
+
+    if let Some(referrer) = &ext_data.referrer {
+        serialized_ext_data.push(EXT_DATA_TAG_REFERRER);
+        referrer.serialize(&mut serialized_ext_data)?;
+    }
+
+    if let Some(swap) = &ext_data.swap {
+        serialized_ext_data.push(EXT_DATA_TAG_SWAP);
+        swap.serialize(&mut serialized_ext_data)?;
+    }
+
+    Ok(hash(&serialized_ext_data).to_bytes())
+}
+
+fn serialize_complete_ext_data(
+    recipient: Pubkey,
+    ext_amount: i64,
+    encrypted_output1: &[u8],
+    encrypted_output2: &[u8],
+    fee: u64,
+    fee_recipient: Pubkey,
+    mint_address: Pubkey,
+) -> Result<Vec<u8>> {
     #[derive(AnchorSerialize)]
     struct CompleteExtData {
         pub recipient: Pubkey,
@@ -303,9 +679,8 @@ pub fn calculate_complete_ext_data_hash(
     
     let mut serialized_ext_data = Vec::new();
     complete_ext_data.serialize(&mut serialized_ext_data)?;
-    let calculated_ext_data_hash = hash(&serialized_ext_data).to_bytes();
     
-    Ok(calculated_ext_data_hash)
+    Ok(serialized_ext_data)
 }
 
 pub fn change_endianness(bytes: &[u8]) -> Vec<u8> {
@@ -333,6 +708,7 @@ mod tests {
             0,     // deposit_fee_rate (0% - free deposits)
             25,    // withdrawal_fee_rate (0.25%)
             500,   // error_rate (5%)
+            0,     // transfer_fee
         );
         assert!(result.is_ok());
     }
@@ -346,6 +722,7 @@ mod tests {
             0,     // deposit_fee_rate (0% - free deposits)
             25,    // withdrawal_fee_rate (0.25%)
             500,   // error_rate (5%)
+            0,     // transfer_fee
         );
         assert!(result.is_ok());
     }
@@ -362,6 +739,7 @@ mod tests {
             0,     // deposit_fee_rate (0% - free deposits)
             25,    // withdrawal_fee_rate (0.25%)
             500,   // error_rate (5%)
+            0,     // transfer_fee
         );
         assert!(result.is_ok()); // Should pass since deposits are free
     }
@@ -375,6 +753,7 @@ mod tests {
             25,    // deposit_fee_rate
             0,     // withdrawal_fee_rate (0%)
             500,   // error_rate (5%)
+            0,     // transfer_fee
         );
         assert!(result.is_ok());
     }
@@ -390,6 +769,7 @@ mod tests {
             25,    // deposit_fee_rate
             50,    // withdrawal_fee_rate (0.5%)
             500,   // error_rate (5%)
+            0,     // transfer_fee
         );
         assert!(result.is_ok());
     }
@@ -405,6 +785,7 @@ mod tests {
             25,    // deposit_fee_rate
             100,   // withdrawal_fee_rate (1%)
             500,   // error_rate (5%)
+            0,     // transfer_fee
         );
         assert!(result.is_err());
         // In anchor, the error is wrapped, so we need to check the error differently
@@ -419,13 +800,14 @@ mod tests {
 
     #[test]
     fn test_validate_fee_zero_amount() {
-        // Test with zero ext_amount (should always pass)
+        // Test with zero ext_amount, free while transfer_fee is 0
         let result = validate_fee(
             0,     // ext_amount (neither deposit nor withdrawal)
             100,   // provided_fee
             25,    // deposit_fee_rate
             50,    // withdrawal_fee_rate
             500,   // error_rate
+            0,     // transfer_fee
         );
         assert!(result.is_ok());
     }
@@ -440,6 +822,7 @@ mod tests {
             25,    // deposit_fee_rate (0.25%)
             0,     // withdrawal_fee_rate
             500,   // error_rate (5%)
+            0,     // transfer_fee
         );
         assert!(result.is_ok());
     }
@@ -455,6 +838,7 @@ mod tests {
             25,    // deposit_fee_rate (0.25%)
             0,     // withdrawal_fee_rate
             5000,  // fee_error_margin (50%)
+            0,     // transfer_fee
         );
         assert!(result.is_ok());
     }
@@ -469,6 +853,7 @@ mod tests {
             1,             // deposit_fee_rate (small rate to avoid overflow)
             0,             // withdrawal_fee_rate
             500,           // error_rate (5%)
+            0,             // transfer_fee
         );
         assert!(result.is_ok());
     }
@@ -482,6 +867,7 @@ mod tests {
             25,    // deposit_fee_rate
             0,     // withdrawal_fee_rate (0%, so any fee is fine)
             500,   // error_rate (5%)
+            0,     // transfer_fee
         );
         assert!(result.is_ok());
     }
@@ -496,6 +882,7 @@ mod tests {
             10000,     // deposit_fee_rate (100% - maximum rate)
             0,         // withdrawal_fee_rate
             0,         // fee_error_margin (0% to test exact calculation)
+            0,         // transfer_fee
         );
         // This should return an error (either arithmetic overflow or invalid fee amount)
         assert!(result.is_err());
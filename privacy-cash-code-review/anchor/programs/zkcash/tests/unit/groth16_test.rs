@@ -1,6 +1,8 @@
-use zkcash::groth16::{Groth16Verifier, Groth16Verifyingkey, is_less_than_bn254_field_size_be};
+use zkcash::groth16::{
+    Groth16Verifier, Groth16Verifyingkey, is_less_than_bn254_field_size_be,
+    is_less_than_bn254_base_field_size_be, validate_g1_point, validate_g2_point,
+};
 use zkcash::errors::Groth16Error;
-use ark_bn254;
 use ark_ff::PrimeField;
 use ark_bn254::Fr;
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress, Validate};
@@ -284,4 +286,125 @@ fn ext_data_hash_should_match() {
     
     // Compare field elements with field elements
     assert_eq!(computed_hash_fr, provided_proof_hash_fr);
-}
\ No newline at end of file
+}
+
+fn negated_proof_a() -> [u8; 64] {
+    let g1_point = G1::deserialize_with_mode(
+        &*[&change_endianness(&PROOF_A[0..64]), &[0u8][..]].concat(),
+        Compress::No,
+        Validate::Yes,
+    )
+    .unwrap();
+
+    let mut proof_a_neg = [0u8; 65];
+    g1_point.neg().x.serialize_with_mode(&mut proof_a_neg[..32], Compress::No).unwrap();
+    g1_point.neg().y.serialize_with_mode(&mut proof_a_neg[32..], Compress::No).unwrap();
+    change_endianness(&proof_a_neg[..64]).try_into().unwrap()
+}
+
+fn base_field_modulus_be() -> [u8; 32] {
+    BigUint::from(ark_bn254::Fq::MODULUS).to_bytes_be().try_into().unwrap()
+}
+
+/// A point on the G2 twist outside the prime order subgroup, in the syscall encoding.
+fn g2_point_outside_subgroup() -> [u8; 128] {
+    let point = (1u64..)
+        .filter_map(|x| ark_bn254::G2Affine::get_point_from_x_unchecked(ark_bn254::Fq2::from(x), true))
+        .find(|point| !point.is_in_correct_subgroup_assuming_on_curve())
+        .unwrap();
+    assert!(point.is_on_curve());
+
+    // Serializing the point itself would put arkworks' y sign flag in the top bits of y.c1, so
+    // write each coordinate on its own
+    let mut bytes = [0u8; 128];
+    point.x.serialize_with_mode(&mut bytes[..64], Compress::No).unwrap();
+    point.y.serialize_with_mode(&mut bytes[64..], Compress::No).unwrap();
+    bytes[..64].reverse();
+    bytes[64..].reverse();
+    bytes
+}
+
+#[test]
+fn test_is_less_than_bn254_base_field_size_be() {
+    assert!(is_less_than_bn254_base_field_size_be(&[0u8; 32]));
+    assert!(!is_less_than_bn254_base_field_size_be(&base_field_modulus_be()));
+
+    // The base field is larger than the scalar field
+    let scalar_modulus: [u8; 32] = BigUint::from(ark_bn254::Fr::MODULUS).to_bytes_be().try_into().unwrap();
+    assert!(is_less_than_bn254_base_field_size_be(&scalar_modulus));
+}
+
+#[test]
+fn valid_proof_points_pass_validation() {
+    validate_g1_point(&PROOF_A).unwrap();
+    validate_g1_point(&negated_proof_a()).unwrap();
+    validate_g2_point(&PROOF_B).unwrap();
+    validate_g1_point(&PROOF_C).unwrap();
+    validate_g1_point(&VERIFYING_KEY.vk_alpha_g1).unwrap();
+    validate_g2_point(&VERIFYING_KEY.vk_beta_g2).unwrap();
+    validate_g2_point(&VERIFYING_KEY.vk_delta_g2).unwrap();
+}
+
+struct NegativeVector {
+    name: &'static str,
+    proof_a: [u8; 64],
+    proof_b: [u8; 128],
+    proof_c: [u8; 64],
+    error: Groth16Error,
+}
+
+fn negative_vectors() -> Vec<NegativeVector> {
+    let proof_a = negated_proof_a();
+    let vector = |name, proof_a, proof_b, proof_c, error| NegativeVector { name, proof_a, proof_b, proof_c, error };
+
+    // Flipping the lowest bit of y keeps the coordinate canonical but leaves the curve
+    let mut off_curve_a = proof_a;
+    off_curve_a[63] ^= 1;
+    let mut off_curve_b = PROOF_B;
+    off_curve_b[127] ^= 1;
+    let mut off_curve_c = PROOF_C;
+    off_curve_c[63] ^= 1;
+
+    // Coordinates at or above the modulus, which the syscalls would silently reduce
+    let mut non_canonical_a = proof_a;
+    non_canonical_a[..32].copy_from_slice(&base_field_modulus_be());
+    let mut non_canonical_b = PROOF_B;
+    non_canonical_b[32..64].copy_from_slice(&base_field_modulus_be());
+    let mut non_canonical_c = PROOF_C;
+    non_canonical_c[32..].copy_from_slice(&[0xff; 32]);
+
+    // x + p still fits in 32 bytes and encodes the same field element as x
+    let mut aliased_c = PROOF_C;
+    let aliased_x = BigUint::from_bytes_be(&PROOF_C[..32]) + BigUint::from(ark_bn254::Fq::MODULUS);
+    aliased_c[..32].copy_from_slice(&aliased_x.to_bytes_be());
+
+    vec![
+        vector("identity proof_a", [0u8; 64], PROOF_B, PROOF_C, Groth16Error::IdentityPoint),
+        vector("identity proof_b", proof_a, [0u8; 128], PROOF_C, Groth16Error::IdentityPoint),
+        vector("identity proof_c", proof_a, PROOF_B, [0u8; 64], Groth16Error::IdentityPoint),
+        vector("proof_a off curve", off_curve_a, PROOF_B, PROOF_C, Groth16Error::PointNotOnCurve),
+        vector("proof_b off curve", proof_a, off_curve_b, PROOF_C, Groth16Error::PointNotOnCurve),
+        vector("proof_c off curve", proof_a, PROOF_B, off_curve_c, Groth16Error::PointNotOnCurve),
+        vector("proof_b outside subgroup", proof_a, g2_point_outside_subgroup(), PROOF_C, Groth16Error::PointNotInSubgroup),
+        vector("proof_a x = p", non_canonical_a, PROOF_B, PROOF_C, Groth16Error::NonCanonicalPointEncoding),
+        vector("proof_b x.c0 = p", proof_a, non_canonical_b, PROOF_C, Groth16Error::NonCanonicalPointEncoding),
+        vector("proof_c y = 2^256 - 1", proof_a, PROOF_B, non_canonical_c, Groth16Error::NonCanonicalPointEncoding),
+        vector("proof_c x + p", proof_a, PROOF_B, aliased_c, Groth16Error::NonCanonicalPointEncoding),
+        vector("proof_a reused as proof_c", proof_a, PROOF_B, proof_a, Groth16Error::ProofVerificationFailed),
+    ]
+}
+
+#[test]
+fn negative_vectors_should_not_succeed() {
+    for vector in negative_vectors() {
+        let result = Groth16Verifier::new(
+            &vector.proof_a,
+            &vector.proof_b,
+            &vector.proof_c,
+            &PUBLIC_INPUTS,
+            &VERIFYING_KEY,
+        )
+        .and_then(|mut verifier| verifier.verify());
+        assert_eq!(result, Err(vector.error), "{}", vector.name);
+    }
+}
@@ -1,6 +1,6 @@
 use light_hasher::{Poseidon, Hasher};
 use std::mem::MaybeUninit;
-use zkcash::{MerkleTreeAccount, merkle_tree::MerkleTree};
+use zkcash::{MerkleTreeAccount, merkle_tree::{MerkleTree, ROOT_NEXT_INDEX_UNKNOWN}};
 
 fn create_test_account() -> MerkleTreeAccount {
     let mut uninit: MaybeUninit<MerkleTreeAccount> = MaybeUninit::uninit();
@@ -250,4 +250,172 @@ fn test_root_history_wraparound() {
     // But the current root should be known
     assert!(MerkleTree::is_known_root(&account, account.root), 
            "Current root should be known");
-}
\ No newline at end of file
+}
+fn test_leaf(i: u64) -> [u8; 32] {
+    let mut leaf = [0u8; 32];
+    leaf[24..].copy_from_slice(&(i + 1).to_be_bytes());
+    leaf
+}
+
+#[test]
+fn test_append_pair_matches_two_appends() {
+    let mut paired = create_test_account();
+    let mut single = create_test_account();
+    let _ = MerkleTree::initialize::<Poseidon>(&mut paired);
+    let _ = MerkleTree::initialize::<Poseidon>(&mut single);
+
+    for pair in 0..6u64 {
+        let (left, right) = (test_leaf(2 * pair), test_leaf(2 * pair + 1));
+        MerkleTree::append_pair::<Poseidon>(left, right, &mut paired).unwrap();
+        MerkleTree::append::<Poseidon>(left, &mut single).unwrap();
+        MerkleTree::append::<Poseidon>(right, &mut single).unwrap();
+
+        assert_eq!(paired.root, single.root, "Root mismatch after pair {}", pair);
+        assert_eq!(paired.next_index, single.next_index);
+        assert_eq!(paired.subtrees, single.subtrees, "Subtrees mismatch after pair {}", pair);
+    }
+}
+
+#[test]
+fn test_append_pair_pushes_single_root() {
+    let mut account = create_test_account();
+    let _ = MerkleTree::initialize::<Poseidon>(&mut account);
+
+    MerkleTree::append_pair::<Poseidon>(test_leaf(0), test_leaf(1), &mut account).unwrap();
+    assert_eq!(account.next_index, 2);
+    assert_eq!(account.root_index, 1, "Only one root should be pushed per pair");
+    assert_eq!(account.root_history[1], account.root);
+    assert!(MerkleTree::is_known_root(&account, account.root));
+
+    // The root with only the left leaf inserted was never pushed
+    let mut single = create_test_account();
+    let _ = MerkleTree::initialize::<Poseidon>(&mut single);
+    MerkleTree::append::<Poseidon>(test_leaf(0), &mut single).unwrap();
+    assert!(!MerkleTree::is_known_root(&account, single.root));
+}
+
+#[test]
+fn test_append_pair_odd_next_index_falls_back() {
+    let mut paired = create_test_account();
+    let mut single = create_test_account();
+    let _ = MerkleTree::initialize::<Poseidon>(&mut paired);
+    let _ = MerkleTree::initialize::<Poseidon>(&mut single);
+
+    MerkleTree::append::<Poseidon>(test_leaf(0), &mut paired).unwrap();
+    MerkleTree::append_pair::<Poseidon>(test_leaf(1), test_leaf(2), &mut paired).unwrap();
+    MerkleTree::append_pair::<Poseidon>(test_leaf(3), test_leaf(4), &mut paired).unwrap();
+    for i in 0..5 {
+        MerkleTree::append::<Poseidon>(test_leaf(i), &mut single).unwrap();
+    }
+
+    assert_eq!(paired.next_index, 5);
+    assert_eq!(paired.root, single.root);
+}
+
+#[test]
+fn test_append_pair_capacity() {
+    let mut account = create_test_account();
+    let _ = MerkleTree::initialize::<Poseidon>(&mut account);
+    let max_capacity = 1u64 << account.height;
+
+    account.next_index = max_capacity - 2;
+    let result = MerkleTree::append_pair::<Poseidon>(test_leaf(0), test_leaf(1), &mut account);
+    assert!(result.is_ok(), "Pair should fit exactly at capacity-2");
+    assert_eq!(account.next_index, max_capacity);
+
+    let result = MerkleTree::append_pair::<Poseidon>(test_leaf(0), test_leaf(1), &mut account);
+    match result.unwrap_err() {
+        anchor_lang::error::Error::AnchorError(anchor_error) => {
+            assert_eq!(anchor_error.error_code_number, 6017); // MerkleTreeFull error code
+        }
+        error => panic!("Expected MerkleTreeFull, got: {:?}", error),
+    }
+
+    // At capacity-1 the fallback's second append hits the limit
+    account.next_index = max_capacity - 1;
+    assert!(MerkleTree::append_pair::<Poseidon>(test_leaf(0), test_leaf(1), &mut account).is_err());
+}
+
+#[test]
+fn test_zeroed_hasher_is_poseidon() {
+    use zkcash::merkle_tree::TreeHasher;
+
+    // Trees created before the hasher was recorded have a zero byte in its place
+    let account = create_test_account();
+    assert_eq!(TreeHasher::of(&account).unwrap(), TreeHasher::Poseidon);
+}
+
+#[test]
+fn test_unknown_hasher_rejected() {
+    use zkcash::merkle_tree::TreeHasher;
+
+    let mut account = create_test_account();
+    account.hasher = 1;
+    assert!(TreeHasher::of(&account).is_err());
+}
+
+#[test]
+fn test_root_leaf_range_tracks_next_index() {
+    let mut account = create_test_account();
+    account.version = MerkleTreeAccount::VERSION;
+    let _ = MerkleTree::initialize::<Poseidon>(&mut account);
+
+    let initial_root = account.root;
+    assert_eq!(MerkleTree::root_leaf_range(&account, initial_root), Some(0..0));
+
+    MerkleTree::append::<Poseidon>(test_leaf(0), &mut account).unwrap();
+    let one_leaf_root = account.root;
+    MerkleTree::append_pair::<Poseidon>(test_leaf(1), test_leaf(2), &mut account).unwrap();
+    let three_leaves_root = account.root;
+
+    assert_eq!(MerkleTree::root_leaf_range(&account, one_leaf_root), Some(0..1));
+    assert_eq!(MerkleTree::root_leaf_range(&account, three_leaves_root), Some(0..3));
+
+    // A client synced to an older root only needs the leaves appended since
+    assert_eq!(MerkleTree::leaves_since_root(&account, initial_root), Some(3));
+    assert_eq!(MerkleTree::leaves_since_root(&account, one_leaf_root), Some(2));
+    assert_eq!(MerkleTree::leaves_since_root(&account, three_leaves_root), Some(0));
+
+    assert_eq!(MerkleTree::root_leaf_range(&account, [0u8; 32]), None);
+    assert_eq!(MerkleTree::root_leaf_range(&account, [1u8; 32]), None);
+}
+
+#[test]
+fn test_root_leaf_range_after_wraparound() {
+    let mut account = create_test_account();
+    account.version = MerkleTreeAccount::VERSION;
+    account.root_history_size = 3;
+    let _ = MerkleTree::initialize::<Poseidon>(&mut account);
+
+    let mut roots = vec![account.root];
+    for i in 0..5u64 {
+        MerkleTree::append::<Poseidon>(test_leaf(i), &mut account).unwrap();
+        roots.push(account.root);
+    }
+
+    // Only the last three roots are kept, each with its own range
+    for (next_index, root) in roots.iter().enumerate() {
+        let expected = (next_index >= 3).then_some(0..next_index as u64);
+        assert_eq!(MerkleTree::root_leaf_range(&account, *root), expected, "root after {} leaves", next_index);
+    }
+}
+
+#[test]
+fn test_root_leaf_range_unknown_before_recorded() {
+    let mut account = create_test_account();
+    account.version = MerkleTreeAccount::VERSION;
+    let _ = MerkleTree::initialize::<Poseidon>(&mut account);
+    MerkleTree::append::<Poseidon>(test_leaf(0), &mut account).unwrap();
+    let root = account.root;
+
+    // Roots pushed before the account was migrated are known, but their range isn't
+    account.root_history_next_index[account.root_index as usize] = ROOT_NEXT_INDEX_UNKNOWN;
+    assert!(MerkleTree::is_known_root(&account, root));
+    assert_eq!(MerkleTree::root_leaf_range(&account, root), None);
+    assert_eq!(MerkleTree::leaves_since_root(&account, root), None);
+
+    // Accounts that predate the field don't record ranges at all
+    account.root_history_next_index[account.root_index as usize] = 1;
+    account.version = 2;
+    assert_eq!(MerkleTree::root_leaf_range(&account, root), None);
+}
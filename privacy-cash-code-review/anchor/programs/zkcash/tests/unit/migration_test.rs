@@ -0,0 +1,236 @@
+use anchor_lang::prelude::*;
+use anchor_lang::{AccountDeserialize, Discriminator};
+use zkcash::migration::{
+    migrate_global_config_data, migrate_merkle_tree_account_data, migrate_tree_token_account_data,
+    read_authority, GLOBAL_CONFIG_V1_LEN, MERKLE_TREE_ACCOUNT_V1_LEN, TREE_TOKEN_ACCOUNT_V1_LEN,
+};
+use zkcash::merkle_tree::{MerkleTree, ROOT_NEXT_INDEX_UNKNOWN};
+use zkcash::nullifier_tree::NullifierMode;
+use zkcash::{GlobalConfig, MerkleTreeAccount, TreeTokenAccount};
+
+const AUTHORITY: [u8; 32] = [7u8; 32];
+
+/// GlobalConfig as allocated by the original `initialize`: 8 + size_of, no version.
+fn global_config_v1() -> Vec<u8> {
+    let mut data = Vec::new();
+    data.extend_from_slice(GlobalConfig::DISCRIMINATOR);
+    data.extend_from_slice(&AUTHORITY);
+    data.extend_from_slice(&10u16.to_le_bytes()); // deposit_fee_rate
+    data.extend_from_slice(&25u16.to_le_bytes()); // withdrawal_fee_rate
+    data.extend_from_slice(&500u16.to_le_bytes()); // fee_error_margin
+    data.push(254); // bump
+    data.resize(GLOBAL_CONFIG_V1_LEN, 0);
+    data
+}
+
+fn tree_token_account_v1() -> Vec<u8> {
+    let mut data = Vec::new();
+    data.extend_from_slice(TreeTokenAccount::DISCRIMINATOR);
+    data.extend_from_slice(&AUTHORITY);
+    data.push(253); // bump
+    assert_eq!(data.len(), TREE_TOKEN_ACCOUNT_V1_LEN);
+    data
+}
+
+/// MerkleTreeAccount with the original zero-copy layout, written field by field.
+fn merkle_tree_account_v1() -> Vec<u8> {
+    let mut data = Vec::new();
+    data.extend_from_slice(MerkleTreeAccount::DISCRIMINATOR);
+    data.extend_from_slice(&AUTHORITY);
+    data.extend_from_slice(&6u64.to_le_bytes()); // next_index
+    for i in 0..26u8 {
+        data.extend_from_slice(&[i; 32]); // subtrees
+    }
+    data.extend_from_slice(&[0xaa; 32]); // root
+    for i in 0..100u8 {
+        data.extend_from_slice(&[i.wrapping_add(100); 32]); // root_history
+    }
+    data.extend_from_slice(&6u64.to_le_bytes()); // root_index
+    data.extend_from_slice(&1_000_000_000_000u64.to_le_bytes()); // max_deposit_amount
+    data.push(26); // height
+    data.push(100); // root_history_size
+    data.push(252); // bump
+    data.extend_from_slice(&[0u8; 5]); // _padding
+    assert_eq!(data.len(), MERKLE_TREE_ACCOUNT_V1_LEN);
+    data
+}
+
+#[test]
+fn test_migrate_global_config_v1() {
+    let v1 = global_config_v1();
+    let new_data = migrate_global_config_data(&v1).unwrap().unwrap();
+    assert_eq!(new_data.len(), GlobalConfig::SPACE);
+    // The v1 fields keep their offsets, `version` takes the zero byte v1 left after `bump`
+    let v1_fields_len = 8 + 32 + 3 * 2 + 1;
+    assert_eq!(&new_data[..v1_fields_len], &v1[..v1_fields_len]);
+
+    let global_config = GlobalConfig::try_deserialize(&mut &new_data[..]).unwrap();
+    assert_eq!(global_config.authority, Pubkey::new_from_array(AUTHORITY));
+    assert_eq!(global_config.version, GlobalConfig::VERSION);
+    assert_eq!(global_config.deposit_fee_rate, 10);
+    assert_eq!(global_config.withdrawal_fee_rate, 25);
+    assert_eq!(global_config.fee_error_margin, 500);
+    assert_eq!(global_config.bump, 254);
+    // Settings added after v1 get the defaults of initialize
+    assert!(!global_config.screen_withdrawals);
+    assert_eq!(global_config.min_encrypted_output_len, 0);
+    assert_eq!(global_config.max_encrypted_output_len, 1024);
+    assert!(!global_config.require_output_envelope);
+    assert_eq!(global_config.commitment_retention_leaves, 0);
+    assert_eq!(global_config.commitment_retention_slots, 0);
+    assert_eq!(global_config.min_deposit_amount, 0);
+    assert_eq!(global_config.deposit_denominations, [0u64; 8]);
+    assert!(!global_config.require_rent_in_fee);
+    assert_eq!(global_config.nullifier_mode, NullifierMode::Pda);
+    assert_eq!(global_config.withdrawal_delay_threshold, 0);
+    assert_eq!(global_config.withdrawal_delay_slots, 0);
+    assert!(!global_config.emergency);
+    assert_eq!(global_config.swap_program, Pubkey::default());
+    assert_eq!(global_config.transfer_fee, 0);
+    assert_eq!(global_config.max_transfers_per_slot, 0);
+
+    // Migrating again is a no-op
+    assert_eq!(migrate_global_config_data(&new_data).unwrap(), None);
+}
+
+#[test]
+fn test_migrate_tree_token_account_v1() {
+    let new_data = migrate_tree_token_account_data(&tree_token_account_v1()).unwrap().unwrap();
+    assert_eq!(new_data.len(), TreeTokenAccount::SPACE);
+
+    let tree_token_account = TreeTokenAccount::try_deserialize(&mut &new_data[..]).unwrap();
+    assert_eq!(tree_token_account.authority, Pubkey::new_from_array(AUTHORITY));
+    assert_eq!(tree_token_account.version, TreeTokenAccount::VERSION);
+    assert_eq!(tree_token_account.bump, 253);
+    assert_eq!(tree_token_account.reserved_lamports, 0);
+    assert_eq!(tree_token_account.vetoed_lamports, 0);
+
+    assert_eq!(migrate_tree_token_account_data(&new_data).unwrap(), None);
+}
+
+#[test]
+fn test_migrate_merkle_tree_account_v1() {
+    let v1 = merkle_tree_account_v1();
+    let new_data = migrate_merkle_tree_account_data(&v1).unwrap().unwrap();
+    assert_eq!(new_data.len(), MerkleTreeAccount::SPACE);
+    // Existing bytes are preserved as-is
+    assert_eq!(&new_data[..v1.len() - 5], &v1[..v1.len() - 5]);
+
+    let tree_account: MerkleTreeAccount = bytemuck::pod_read_unaligned(&new_data[8..]);
+    assert_eq!(tree_account.authority, Pubkey::new_from_array(AUTHORITY));
+    assert_eq!(tree_account.next_index, 6);
+    assert_eq!(tree_account.subtrees[25], [25u8; 32]);
+    assert_eq!(tree_account.root, [0xaa; 32]);
+    assert_eq!(tree_account.root_history[99], [199u8; 32]);
+    assert_eq!(tree_account.root_index, 6);
+    assert_eq!(tree_account.max_deposit_amount, 1_000_000_000_000);
+    assert_eq!(tree_account.height, 26);
+    assert_eq!(tree_account.root_history_size, 100);
+    assert_eq!(tree_account.bump, 252);
+    assert_eq!(tree_account.hasher, 0);
+    assert_eq!(tree_account.version, MerkleTreeAccount::VERSION);
+    assert!(tree_account._reserved.iter().all(|byte| *byte == 0));
+    assert_root_history_next_index_upgraded(&tree_account);
+
+    assert_eq!(migrate_merkle_tree_account_data(&new_data).unwrap(), None);
+}
+
+/// Only the current root's leaf range is known after an upgrade to version 3.
+fn assert_root_history_next_index_upgraded(tree_account: &MerkleTreeAccount) {
+    for (i, next_index) in tree_account.root_history_next_index.iter().enumerate() {
+        if i == tree_account.root_index as usize {
+            assert_eq!(*next_index, tree_account.next_index);
+        } else {
+            assert_eq!(*next_index, ROOT_NEXT_INDEX_UNKNOWN, "entry {}", i);
+        }
+    }
+}
+
+#[test]
+fn test_migrate_merkle_tree_account_v2() {
+    // A version 2 account is the version 3 layout with `root_history_next_index` still reserved
+    let current = migrate_merkle_tree_account_data(&merkle_tree_account_v1()).unwrap().unwrap();
+    let mut tree_account: MerkleTreeAccount = bytemuck::pod_read_unaligned(&current[8..]);
+    tree_account.version = 2;
+    tree_account.root_history_next_index = [0; 100];
+    let mut v2 = MerkleTreeAccount::DISCRIMINATOR.to_vec();
+    v2.extend_from_slice(bytemuck::bytes_of(&tree_account));
+
+    let new_data = migrate_merkle_tree_account_data(&v2).unwrap().unwrap();
+    assert_eq!(new_data.len(), v2.len());
+    let migrated: MerkleTreeAccount = bytemuck::pod_read_unaligned(&new_data[8..]);
+    assert_eq!(migrated.version, MerkleTreeAccount::VERSION);
+    assert_eq!(migrated.root_history, tree_account.root_history);
+    assert_eq!(migrated.root, tree_account.root);
+    assert_root_history_next_index_upgraded(&migrated);
+    assert_eq!(
+        MerkleTree::root_leaf_range(&migrated, migrated.root_history[migrated.root_index as usize]),
+        Some(0..6)
+    );
+    assert_eq!(MerkleTree::root_leaf_range(&migrated, migrated.root_history[0]), None);
+
+    assert_eq!(migrate_merkle_tree_account_data(&new_data).unwrap(), None);
+}
+
+#[test]
+fn test_current_space_has_room_for_fields() {
+    let mut data = Vec::new();
+    GlobalConfig {
+        authority: Pubkey::new_unique(),
+        deposit_fee_rate: 0,
+        withdrawal_fee_rate: 0,
+        fee_error_margin: 0,
+        bump: 0,
+        version: GlobalConfig::VERSION,
+        screen_withdrawals: false,
+        min_encrypted_output_len: 0,
+        max_encrypted_output_len: 0,
+        require_output_envelope: false,
+        commitment_retention_leaves: 0,
+        commitment_retention_slots: 0,
+        min_deposit_amount: u64::MAX,
+        deposit_denominations: [u64::MAX; 8],
+        require_rent_in_fee: true,
+        nullifier_mode: NullifierMode::Compressed,
+        withdrawal_delay_threshold: u64::MAX,
+        withdrawal_delay_slots: u64::MAX,
+        emergency: true,
+        swap_program: Pubkey::new_unique(),
+        transfer_fee: u64::MAX,
+        max_transfers_per_slot: u16::MAX,
+    }
+    .try_serialize(&mut data)
+    .unwrap();
+    assert!(data.len() < GlobalConfig::SPACE);
+    const { assert!(MerkleTreeAccount::SPACE > MERKLE_TREE_ACCOUNT_V1_LEN) };
+}
+
+#[test]
+fn test_migrate_rejects_unknown_layouts() {
+    // Wrong discriminator
+    assert!(migrate_global_config_data(&tree_token_account_v1()).is_err());
+    assert!(migrate_tree_token_account_data(&global_config_v1()).is_err());
+    assert!(migrate_merkle_tree_account_data(&global_config_v1()).is_err());
+
+    // Neither v1 nor the current length
+    let mut data = global_config_v1();
+    data.push(0);
+    assert!(migrate_global_config_data(&data).is_err());
+    let mut data = merkle_tree_account_v1();
+    data.truncate(data.len() - 1);
+    assert!(migrate_merkle_tree_account_data(&data).is_err());
+
+    // Current length with a newer version than this program knows
+    let mut data = migrate_tree_token_account_data(&tree_token_account_v1()).unwrap().unwrap();
+    data[8 + 32] = TreeTokenAccount::VERSION + 1;
+    assert!(migrate_tree_token_account_data(&data).is_err());
+}
+
+#[test]
+fn test_read_authority_is_stable_across_versions() {
+    let v1 = global_config_v1();
+    let current = migrate_global_config_data(&v1).unwrap().unwrap();
+    assert_eq!(read_authority(&v1).unwrap(), Pubkey::new_from_array(AUTHORITY));
+    assert_eq!(read_authority(&current).unwrap(), Pubkey::new_from_array(AUTHORITY));
+    assert!(read_authority(&v1[..20]).is_err());
+}
@@ -1,3 +1,5 @@
 mod merkle_tree_test; 
 mod utils_test;
-mod groth16_test;
\ No newline at end of file
+mod groth16_test;
+mod migration_test;
+mod nullifier_tree_test;
\ No newline at end of file
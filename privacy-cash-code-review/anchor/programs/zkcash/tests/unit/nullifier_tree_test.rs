@@ -0,0 +1,190 @@
+use light_hasher::{Hasher, Poseidon};
+use rand::Rng;
+use std::mem::MaybeUninit;
+use zkcash::nullifier_tree::{IndexedLeaf, NullifierInsertProof, NullifierTree, NULLIFIER_TREE_HEIGHT};
+use zkcash::NullifierTreeAccount;
+
+fn create_test_account() -> NullifierTreeAccount {
+    let mut uninit: MaybeUninit<NullifierTreeAccount> = MaybeUninit::uninit();
+
+    unsafe {
+        std::ptr::write_bytes(uninit.as_mut_ptr(), 0, 1); // Zero-initialize the entire struct
+        let mut account = uninit.assume_init();
+        NullifierTree::initialize::<Poseidon>(&mut account, 0).unwrap();
+        account
+    }
+}
+
+fn value(n: u64) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    bytes[24..].copy_from_slice(&n.to_be_bytes());
+    bytes
+}
+
+/// Keeps every leaf and rebuilds the tree from scratch, as a reference for the on-chain state.
+struct NaiveIndexedTree {
+    leaves: Vec<IndexedLeaf>,
+}
+
+impl NaiveIndexedTree {
+    fn new() -> Self {
+        NaiveIndexedTree { leaves: vec![IndexedLeaf::default()] }
+    }
+
+    fn layers(&self) -> Vec<Vec<[u8; 32]>> {
+        let zero_bytes = Poseidon::zero_bytes();
+        let mut layers = vec![self.leaves.iter().map(|leaf| leaf.hash::<Poseidon>().unwrap()).collect::<Vec<_>>()];
+        for level in 0..NULLIFIER_TREE_HEIGHT {
+            let layer = &layers[level];
+            let parents = layer
+                .chunks(2)
+                .map(|pair| Poseidon::hashv(&[&pair[0], pair.get(1).unwrap_or(&zero_bytes[level])]).unwrap())
+                .collect();
+            layers.push(parents);
+        }
+        layers
+    }
+
+    fn root(&self) -> [u8; 32] {
+        self.layers()[NULLIFIER_TREE_HEIGHT][0]
+    }
+
+    fn insert_proof(&self, value: [u8; 32]) -> NullifierInsertProof {
+        let low_leaf_index = self
+            .leaves
+            .iter()
+            .position(|leaf| leaf.is_low_leaf_for(&value) || leaf.value == value || leaf.next_value == value)
+            .unwrap();
+        let layers = self.layers();
+        let zero_bytes = Poseidon::zero_bytes();
+        let mut siblings = [[0u8; 32]; NULLIFIER_TREE_HEIGHT];
+        let mut index = low_leaf_index;
+        for (level, sibling) in siblings.iter_mut().enumerate() {
+            *sibling = layers[level].get(index ^ 1).copied().unwrap_or(zero_bytes[level]);
+            index /= 2;
+        }
+        NullifierInsertProof {
+            low_leaf: self.leaves[low_leaf_index],
+            low_leaf_index: low_leaf_index as u64,
+            siblings,
+        }
+    }
+
+    fn insert(&mut self, value: [u8; 32]) {
+        let proof = self.insert_proof(value);
+        let new_index = self.leaves.len() as u64;
+        let low_leaf = &mut self.leaves[proof.low_leaf_index as usize];
+        let new_leaf = IndexedLeaf { value, next_index: low_leaf.next_index, next_value: low_leaf.next_value };
+        low_leaf.next_index = new_index;
+        low_leaf.next_value = value;
+        self.leaves.push(new_leaf);
+    }
+}
+
+#[test]
+fn test_initialization_holds_the_sentinel() {
+    let account = create_test_account();
+    assert_eq!(account.next_index, 1);
+    assert_eq!(account.root, NaiveIndexedTree::new().root());
+}
+
+#[test]
+fn test_inserts_match_the_reference() {
+    let mut account = create_test_account();
+    let mut reference = NaiveIndexedTree::new();
+    let mut rng = rand::rng();
+
+    // Enough inserts for low leaves to land under subtrees the next append reuses
+    for _ in 0..40 {
+        let mut nullifier: [u8; 32] = rng.random();
+        nullifier[0] = 0; // keep it in the field
+        let proof = reference.insert_proof(nullifier);
+        NullifierTree::insert::<Poseidon>(nullifier, &proof, &mut account).unwrap();
+        reference.insert(nullifier);
+        assert_eq!(account.root, reference.root());
+    }
+    assert_eq!(account.next_index, 41);
+}
+
+#[test]
+fn test_ascending_and_descending_inserts() {
+    let mut account = create_test_account();
+    let mut reference = NaiveIndexedTree::new();
+
+    // Ascending values always use the last leaf as the low leaf, descending ones the sentinel
+    for n in (1..=8).chain((100..108).rev()) {
+        let proof = reference.insert_proof(value(n));
+        NullifierTree::insert::<Poseidon>(value(n), &proof, &mut account).unwrap();
+        reference.insert(value(n));
+        assert_eq!(account.root, reference.root());
+    }
+}
+
+#[test]
+fn test_insert_rejects_spent_nullifier() {
+    let mut account = create_test_account();
+    let mut reference = NaiveIndexedTree::new();
+    for n in [10, 20, 30] {
+        NullifierTree::insert::<Poseidon>(value(n), &reference.insert_proof(value(n)), &mut account).unwrap();
+        reference.insert(value(n));
+    }
+
+    // Neither the leaf holding it nor its predecessor can serve as low leaf
+    let proof = reference.insert_proof(value(20));
+    let err = NullifierTree::insert::<Poseidon>(value(20), &proof, &mut account).unwrap_err();
+    assert!(err.to_string().contains("NullifierAlreadySpent"));
+
+    let mut proof = reference.insert_proof(value(21));
+    proof.low_leaf = reference.leaves[proof.low_leaf_index as usize - 1];
+    let err = NullifierTree::insert::<Poseidon>(value(20), &proof, &mut account).unwrap_err();
+    assert!(err.to_string().contains("NullifierAlreadySpent"));
+
+    // The sentinel value is never a valid nullifier
+    let err = NullifierTree::insert::<Poseidon>(value(0), &reference.insert_proof(value(5)), &mut account).unwrap_err();
+    assert!(err.to_string().contains("NullifierAlreadySpent"));
+}
+
+#[test]
+fn test_insert_rejects_forged_low_leaf() {
+    let mut account = create_test_account();
+    let mut reference = NaiveIndexedTree::new();
+    for n in [10, 20, 30] {
+        NullifierTree::insert::<Poseidon>(value(n), &reference.insert_proof(value(n)), &mut account).unwrap();
+        reference.insert(value(n));
+    }
+
+    // A low leaf claiming 20 is unspent, not in the tree
+    let mut proof = reference.insert_proof(value(15));
+    proof.low_leaf.next_value = value(25);
+    let err = NullifierTree::insert::<Poseidon>(value(20), &proof, &mut account).unwrap_err();
+    assert!(err.to_string().contains("StaleNullifierProof"));
+
+    // A low leaf that doesn't cover the value
+    let proof = reference.insert_proof(value(15));
+    let err = NullifierTree::insert::<Poseidon>(value(25), &proof, &mut account).unwrap_err();
+    assert!(err.to_string().contains("InvalidNullifierProof"));
+
+    // An empty slot past the last leaf
+    let mut proof = reference.insert_proof(value(35));
+    proof.low_leaf_index = account.next_index;
+    let err = NullifierTree::insert::<Poseidon>(value(35), &proof, &mut account).unwrap_err();
+    assert!(err.to_string().contains("InvalidNullifierProof"));
+}
+
+#[test]
+fn test_insert_rejects_stale_proof() {
+    let mut account = create_test_account();
+    let mut reference = NaiveIndexedTree::new();
+
+    let stale_proof = reference.insert_proof(value(2));
+    NullifierTree::insert::<Poseidon>(value(1), &reference.insert_proof(value(1)), &mut account).unwrap();
+    reference.insert(value(1));
+
+    let err = NullifierTree::insert::<Poseidon>(value(2), &stale_proof, &mut account).unwrap_err();
+    assert!(err.to_string().contains("StaleNullifierProof"));
+
+    // The same insert with a fresh proof goes through
+    NullifierTree::insert::<Poseidon>(value(2), &reference.insert_proof(value(2)), &mut account).unwrap();
+    reference.insert(value(2));
+    assert_eq!(account.root, reference.root());
+}
@@ -1,11 +1,11 @@
 use num_bigint::BigUint;
-use ark_bn254;
 use ark_ff::{PrimeField, BigInteger};
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress, Validate};
 use std::ops::Neg;
 use ark_bn254::Fr;
-use zkcash::{groth16::{is_less_than_bn254_field_size_be, Groth16Verifyingkey}, utils::{change_endianness, check_public_amount, verify_proof, validate_fee, calculate_complete_ext_data_hash, VERIFYING_KEY}, Proof};
+use zkcash::{groth16::{is_less_than_bn254_field_size_be, Groth16Verifyingkey}, utils::{change_endianness, check_public_amount, verify_proof, validate_fee, validate_encrypted_output, is_commitment_reclaimable, is_delayed_withdrawal, is_withdrawal_releasable, validate_withdrawal_recipients, calculate_complete_ext_data_hash, calculate_ext_data_hash, validate_deposit_amount, pack_deposit_denominations, minimum_fee, validate_fee_with_rent, record_transfer, transact_rent, TransactPdas, nullifier_account_space, commitment_account_space, VERIFYING_KEY}, nullifier_tree::NullifierMode, disclosure::{validate_auditor_disclosure, MAX_AUDITOR_CIPHERTEXT_LEN}, referral::{record_deposit, validate_tags, MAX_MEMO_LEN}, swap::{validate_swap, MAX_SWAP_ACCOUNTS, MAX_SWAP_DATA_LEN}, AuditorDisclosure, MerkleTreeAccount, PendingWithdrawal, ReferrerStats, ViewingKeyRegistry, ExtData, Proof, SwapParams, WithdrawCallback, WithdrawalRecipient};
 use anchor_lang::prelude::*;
+use zkcash_envelope::{Envelope, Scheme};
 
 type G1 = ark_bn254::g1::G1Affine;
 
@@ -385,7 +385,7 @@ fn negated_proof_a_verification_should_not_succeed() {
         output_commitments: [PUBLIC_INPUTS[3], PUBLIC_INPUTS[4]],
         public_amount: PUBLIC_INPUTS[5],
         ext_data_hash: PUBLIC_INPUTS[6],
-        proof_a: proof_a,
+        proof_a,
         proof_b: PROOF_B,
         proof_c: PROOF_C,
     };
@@ -633,9 +633,7 @@ fn test_verify_proof_with_truncated_proof_a() {
     // Test with a proof_a that has valid start but becomes invalid
     let mut truncated_proof_a = PROOF_A;
     // Modify the last bytes to make it invalid
-    for i in 32..64 {
-        truncated_proof_a[i] = 255;
-    }
+    truncated_proof_a[32..64].fill(255);
     
     let proof = Proof {
         root: PUBLIC_INPUTS[0],
@@ -691,6 +689,7 @@ fn test_validate_fee_deposit_exact_minimum() {
         0,     // deposit_fee_rate (0% - free deposits)
         25,    // withdrawal_fee_rate (0.25%)
         500,   // error_rate (5%)
+        0,     // transfer_fee
     );
     assert!(result.is_ok());
 }
@@ -704,6 +703,7 @@ fn test_validate_fee_deposit_above_minimum() {
         0,     // deposit_fee_rate (0% - free deposits)
         25,    // withdrawal_fee_rate (0.25%)
         500,   // error_rate (5%)
+        0,     // transfer_fee
     );
     assert!(result.is_ok());
 }
@@ -720,6 +720,7 @@ fn test_validate_fee_deposit_below_minimum() {
         0,     // deposit_fee_rate (0% - free deposits)
         25,    // withdrawal_fee_rate (0.25%)
         500,   // error_rate (5%)
+        0,     // transfer_fee
     );
     assert!(result.is_ok()); // Should pass since deposits are free
 }
@@ -733,6 +734,7 @@ fn test_validate_fee_withdrawal_zero_rate() {
         0,     // deposit_fee_rate (0% - free deposits)
         25,    // withdrawal_fee_rate (0.25%)
         500,   // error_rate (5%)
+        0,     // transfer_fee
     );
     assert!(result.is_ok());
 }
@@ -748,6 +750,7 @@ fn test_validate_fee_withdrawal_with_rate() {
         0,     // deposit_fee_rate (0% - free deposits)
         50,    // withdrawal_fee_rate (0.5%)
         500,   // error_rate (5%)
+        0,     // transfer_fee
     );
     assert!(result.is_ok());
 }
@@ -763,19 +766,21 @@ fn test_validate_fee_withdrawal_below_minimum() {
         0,     // deposit_fee_rate (0% - free deposits)
         100,   // withdrawal_fee_rate (1%)
         500,   // error_rate (5%)
+        0,     // transfer_fee
     );
     assert!(result.is_err());
 }
 
 #[test]
 fn test_validate_fee_zero_amount() {
-    // Test with zero ext_amount (should always pass)
+    // Test with zero ext_amount (any fee passes when no transfer fee is set)
     let result = validate_fee(
         0,     // ext_amount (neither deposit nor withdrawal)
         100,   // provided_fee
         0,     // deposit_fee_rate (0% - free deposits)
         50,    // withdrawal_fee_rate
         500,   // error_rate
+        0,     // transfer_fee
     );
     assert!(result.is_ok());
 }
@@ -790,6 +795,7 @@ fn test_validate_fee_small_deposit_zero_expected() {
         0,     // deposit_fee_rate (0% - free deposits)
         25,    // withdrawal_fee_rate (0.25%)
         500,   // error_rate (5%)
+        0,     // transfer_fee
     );
     assert!(result.is_ok());
 }
@@ -805,6 +811,7 @@ fn test_validate_fee_high_error_rate() {
         0,     // deposit_fee_rate (0% - free deposits)
         25,    // withdrawal_fee_rate (0.25%)
         5000,  // error_rate (50%)
+        0,     // transfer_fee
     );
     assert!(result.is_ok());
 }
@@ -819,6 +826,7 @@ fn test_validate_fee_overflow_protection() {
         1,             // deposit_fee_rate (small rate to avoid overflow)
         25,            // withdrawal_fee_rate (0.25%)
         500,           // error_rate (5%)
+        0,     // transfer_fee
     );
     assert!(result.is_ok());
 }
@@ -832,6 +840,7 @@ fn test_validate_fee_edge_case_min_withdrawal() {
         0,     // deposit_fee_rate (0% - free deposits)
         25,    // withdrawal_fee_rate (0.25%)
         500,   // error_rate (5%)
+        0,     // transfer_fee
     );
     assert!(result.is_ok());
 }
@@ -846,6 +855,7 @@ fn test_validate_fee_arithmetic_overflow_detection() {
         10000,     // deposit_fee_rate (100% - maximum rate)
         25,        // withdrawal_fee_rate (0.25%)
         0,         // error_rate (0% to test exact calculation)
+        0,     // transfer_fee
     );
     // This should return an error (either arithmetic overflow or invalid fee amount)
     assert!(result.is_err());
@@ -860,6 +870,7 @@ fn test_validate_fee_large_fee_rates() {
         10000, // deposit_fee_rate (100%)
         10000, // withdrawal_fee_rate (100%)
         500,   // error_rate (5%)
+        0,     // transfer_fee
     );
     assert!(result.is_ok());
 }
@@ -875,6 +886,7 @@ fn test_validate_fee_exact_boundary_cases() {
         1000,  // deposit_fee_rate (10%)
         0,     // withdrawal_fee_rate
         1000,  // error_rate (10%)
+        0,     // transfer_fee
     );
     assert!(result.is_ok());
 }
@@ -889,6 +901,7 @@ fn test_validate_fee_withdrawal_i64_min_protection() {
         0,        // deposit_fee_rate (0% - free deposits)
         25,       // withdrawal_fee_rate (0.25%)
         500,      // error_rate (5%)
+        0,     // transfer_fee
     );
     // This should return an error due to arithmetic overflow protection
     assert!(result.is_err());
@@ -903,6 +916,7 @@ fn test_validate_fee_max_basis_points() {
         10000, // deposit_fee_rate (100%)
         10000, // withdrawal_fee_rate (100%)
         0,     // error_rate (0% - no tolerance)
+        0,     // transfer_fee
     );
     assert!(result.is_ok());
 }
@@ -917,6 +931,7 @@ fn test_validate_fee_precision_edge_cases() {
         1,     // deposit_fee_rate (0.01%)
         25,    // withdrawal_fee_rate (0.25%)
         0,     // error_rate (0% - exact calculation)
+        0,     // transfer_fee
     );
     assert!(result.is_ok());
 }
@@ -932,6 +947,7 @@ fn test_validate_fee_withdrawal_large_amount() {
         0,          // deposit_fee_rate (0% - free deposits)
         50,         // withdrawal_fee_rate (0.5%)
         1000,       // error_rate (10%)
+        0,     // transfer_fee
     );
     assert!(result.is_ok());
 }
@@ -1219,4 +1235,511 @@ fn test_calculate_complete_ext_data_hash_consistency_with_borsh() {
     let manual_hash = hash(&serialized).to_bytes();
     
     assert_eq!(our_hash, manual_hash, "Our function should match manual Borsh serialization");
-}
\ No newline at end of file
+}
+
+fn test_ext_data(ext_amount: i64) -> ExtData {
+    ExtData {
+        recipient: Pubkey::new_unique(),
+        ext_amount,
+        fee: 10,
+        fee_recipient: Pubkey::new_unique(),
+        mint_address: Pubkey::new_unique(),
+        callback: None,
+        recipients: None,
+        auditor: None,
+        memo: None,
+        referrer: None,
+        swap: None,
+    }
+}
+
+#[test]
+fn test_ext_data_hash_without_extensions_matches_complete_hash() {
+    let ext_data = test_ext_data(-100);
+    let encrypted_output1 = b"encrypted_output_1_data";
+    let encrypted_output2 = b"encrypted_output_2_data";
+
+    let complete_hash = calculate_complete_ext_data_hash(
+        ext_data.recipient,
+        ext_data.ext_amount,
+        encrypted_output1,
+        encrypted_output2,
+        ext_data.fee,
+        ext_data.fee_recipient,
+        ext_data.mint_address,
+    ).unwrap();
+    let ext_data_hash = calculate_ext_data_hash(&ext_data, encrypted_output1, encrypted_output2).unwrap();
+
+    assert_eq!(complete_hash, ext_data_hash, "ExtData without extensions must keep the existing hash");
+}
+
+#[test]
+fn test_ext_data_hash_binds_callback() {
+    let mut ext_data = test_ext_data(-100);
+    let encrypted_output1 = b"encrypted_output_1_data";
+    let encrypted_output2 = b"encrypted_output_2_data";
+    let hash_without_callback = calculate_ext_data_hash(&ext_data, encrypted_output1, encrypted_output2).unwrap();
+
+    ext_data.callback = Some(WithdrawCallback {
+        program_id: Pubkey::new_unique(),
+        accounts: vec![Pubkey::new_unique()],
+        data: vec![1, 2, 3],
+    });
+    let hash_with_callback = calculate_ext_data_hash(&ext_data, encrypted_output1, encrypted_output2).unwrap();
+    assert_ne!(hash_without_callback, hash_with_callback, "Callback must be bound into the hash");
+
+    // Any change to the callback must change the hash
+    ext_data.callback.as_mut().unwrap().data = vec![1, 2, 4];
+    let hash_with_other_data = calculate_ext_data_hash(&ext_data, encrypted_output1, encrypted_output2).unwrap();
+    assert_ne!(hash_with_callback, hash_with_other_data, "Callback data must be bound into the hash");
+}
+
+#[test]
+fn test_ext_data_hash_binds_memo_and_referrer() {
+    let mut ext_data = test_ext_data(100);
+    let encrypted_output1 = b"encrypted_output_1_data";
+    let encrypted_output2 = b"encrypted_output_2_data";
+    let hash_without_tags = calculate_ext_data_hash(&ext_data, encrypted_output1, encrypted_output2).unwrap();
+
+    ext_data.memo = Some(b"invoice 42".to_vec());
+    let hash_with_memo = calculate_ext_data_hash(&ext_data, encrypted_output1, encrypted_output2).unwrap();
+    assert_ne!(hash_without_tags, hash_with_memo, "Memo must be bound into the hash");
+
+    ext_data.referrer = Some([9u8; 32]);
+    let hash_with_referrer = calculate_ext_data_hash(&ext_data, encrypted_output1, encrypted_output2).unwrap();
+    assert_ne!(hash_with_memo, hash_with_referrer, "Referrer must be bound into the hash");
+
+    // The same bytes as a memo or a referrer hash differently
+    let mut as_memo = test_ext_data(100);
+    as_memo.recipient = ext_data.recipient;
+    as_memo.fee_recipient = ext_data.fee_recipient;
+    as_memo.mint_address = ext_data.mint_address;
+    as_memo.memo = Some([9u8; 32].to_vec());
+    let mut as_referrer = as_memo.clone();
+    as_referrer.memo = None;
+    as_referrer.referrer = Some([9u8; 32]);
+    assert_ne!(
+        calculate_ext_data_hash(&as_memo, encrypted_output1, encrypted_output2).unwrap(),
+        calculate_ext_data_hash(&as_referrer, encrypted_output1, encrypted_output2).unwrap(),
+    );
+}
+
+#[test]
+fn test_validate_tags() {
+    assert!(validate_tags(&None, &None, -100).is_ok());
+    assert!(validate_tags(&Some(vec![0u8; MAX_MEMO_LEN]), &None, -100).is_ok());
+    assert!(validate_tags(&Some(vec![0u8; MAX_MEMO_LEN + 1]), &None, 100).is_err());
+
+    // Referrers only count deposits
+    assert!(validate_tags(&None, &Some([1u8; 32]), 100).is_ok());
+    assert!(validate_tags(&None, &Some([1u8; 32]), -100).is_err());
+    assert!(validate_tags(&None, &Some([1u8; 32]), 0).is_err());
+}
+
+#[test]
+fn test_record_deposit() {
+    let mut stats = ReferrerStats { referrer: [0u8; 32], deposit_count: 0, deposit_volume: 0, bump: 0 };
+    record_deposit(&mut stats, [1u8; 32], 1_000, 254).unwrap();
+    record_deposit(&mut stats, [1u8; 32], 500, 254).unwrap();
+    assert_eq!(stats.referrer, [1u8; 32]);
+    assert_eq!(stats.deposit_count, 2);
+    assert_eq!(stats.deposit_volume, 1_500);
+    assert_eq!(stats.bump, 254);
+
+    stats.deposit_volume = u64::MAX;
+    assert!(record_deposit(&mut stats, [1u8; 32], 1, 254).is_err());
+}
+
+fn test_swap(swap_authority: Pubkey) -> SwapParams {
+    let recipient_token_account = Pubkey::new_unique();
+    SwapParams {
+        output_mint: Pubkey::new_unique(),
+        recipient_token_account,
+        min_out: 1_000,
+        accounts: vec![swap_authority, Pubkey::new_unique(), recipient_token_account],
+        data: vec![1, 2, 3],
+    }
+}
+
+#[test]
+fn test_ext_data_hash_binds_swap() {
+    let mut ext_data = test_ext_data(-100);
+    let encrypted_output1 = b"encrypted_output_1_data";
+    let encrypted_output2 = b"encrypted_output_2_data";
+    let hash_without_swap = calculate_ext_data_hash(&ext_data, encrypted_output1, encrypted_output2).unwrap();
+
+    let swap = test_swap(Pubkey::new_unique());
+    ext_data.swap = Some(swap.clone());
+    let hash_with_swap = calculate_ext_data_hash(&ext_data, encrypted_output1, encrypted_output2).unwrap();
+    assert_ne!(hash_without_swap, hash_with_swap, "Swap must be bound into the hash");
+
+    // A relayer can't loosen the slippage guard
+    ext_data.swap = Some(SwapParams { min_out: swap.min_out - 1, ..swap });
+    let hash_with_lower_min_out = calculate_ext_data_hash(&ext_data, encrypted_output1, encrypted_output2).unwrap();
+    assert_ne!(hash_with_swap, hash_with_lower_min_out);
+}
+
+#[test]
+fn test_validate_swap() {
+    let swap_program = Pubkey::new_unique();
+    let swap_authority = Pubkey::new_unique();
+    let swap = test_swap(swap_authority);
+    assert!(validate_swap(&swap, &swap_program, &swap_authority).is_ok());
+
+    // Disabled until the authority configures a swap program
+    assert!(validate_swap(&swap, &Pubkey::default(), &swap_authority).is_err());
+
+    let no_min_out = SwapParams { min_out: 0, ..swap.clone() };
+    assert!(validate_swap(&no_min_out, &swap_program, &swap_authority).is_err());
+
+    let too_many_accounts = SwapParams { accounts: vec![swap_authority; MAX_SWAP_ACCOUNTS + 1], ..swap.clone() };
+    assert!(validate_swap(&too_many_accounts, &swap_program, &swap_authority).is_err());
+    let too_much_data = SwapParams { data: vec![0u8; MAX_SWAP_DATA_LEN + 1], ..swap.clone() };
+    assert!(validate_swap(&too_much_data, &swap_program, &swap_authority).is_err());
+
+    // The swap program must be handed both the authority and the recipient's token account
+    assert!(validate_swap(&swap, &swap_program, &Pubkey::new_unique()).is_err());
+    let missing_token_account = SwapParams { recipient_token_account: Pubkey::new_unique(), ..swap };
+    assert!(validate_swap(&missing_token_account, &swap_program, &swap_authority).is_err());
+}
+
+fn auditor_envelope(ciphertext: &[u8]) -> Vec<u8> {
+    Envelope::new(Scheme::X25519ChaCha20Poly1305, [3u8; 32], ciphertext).unwrap().encode()
+}
+
+#[test]
+fn test_ext_data_hash_binds_auditor_disclosure() {
+    let mut ext_data = test_ext_data(-100);
+    let encrypted_output1 = b"encrypted_output_1_data";
+    let encrypted_output2 = b"encrypted_output_2_data";
+    let hash_without_auditor = calculate_ext_data_hash(&ext_data, encrypted_output1, encrypted_output2).unwrap();
+
+    ext_data.auditor = Some(AuditorDisclosure {
+        viewing_key: [1u8; 32],
+        encrypted_outputs: [auditor_envelope(b"output1"), auditor_envelope(b"output2")],
+    });
+    let hash_with_auditor = calculate_ext_data_hash(&ext_data, encrypted_output1, encrypted_output2).unwrap();
+    assert_ne!(hash_without_auditor, hash_with_auditor, "Auditor disclosure must be bound into the hash");
+
+    // A relayer can't swap in other ciphertexts
+    ext_data.auditor.as_mut().unwrap().encrypted_outputs[1] = auditor_envelope(b"other");
+    let hash_with_other_ciphertext = calculate_ext_data_hash(&ext_data, encrypted_output1, encrypted_output2).unwrap();
+    assert_ne!(hash_with_auditor, hash_with_other_ciphertext, "Auditor ciphertexts must be bound into the hash");
+}
+
+#[test]
+fn test_validate_auditor_disclosure() {
+    let registry = ViewingKeyRegistry { viewing_key: [1u8; 32], auditor: Pubkey::new_unique(), bump: 255 };
+    let mut disclosure = AuditorDisclosure {
+        viewing_key: [1u8; 32],
+        encrypted_outputs: [auditor_envelope(b"output1"), auditor_envelope(b"output2")],
+    };
+    assert!(validate_auditor_disclosure(&disclosure, &registry).is_ok());
+
+    disclosure.viewing_key = [2u8; 32];
+    let err = validate_auditor_disclosure(&disclosure, &registry).unwrap_err();
+    assert!(err.to_string().contains("ViewingKeyNotRegistered"));
+    disclosure.viewing_key = [1u8; 32];
+
+    // Raw bytes, the wallet's symmetric scheme and oversized ciphertexts are rejected
+    for encrypted_output in [
+        b"raw ciphertext".to_vec(),
+        Envelope::new(Scheme::Aes128CtrHmacSha256, [0u8; 32], b"output").unwrap().encode(),
+        auditor_envelope(&[0u8; MAX_AUDITOR_CIPHERTEXT_LEN]),
+    ] {
+        disclosure.encrypted_outputs[0] = encrypted_output;
+        let err = validate_auditor_disclosure(&disclosure, &registry).unwrap_err();
+        assert!(err.to_string().contains("InvalidAuditorCiphertext"));
+    }
+}
+
+#[test]
+fn test_ext_data_hash_binds_recipients() {
+    let mut ext_data = test_ext_data(-100);
+    let encrypted_output1 = b"encrypted_output_1_data";
+    let encrypted_output2 = b"encrypted_output_2_data";
+    let hash_without_recipients = calculate_ext_data_hash(&ext_data, encrypted_output1, encrypted_output2).unwrap();
+
+    ext_data.recipients = Some(vec![
+        WithdrawalRecipient { recipient: ext_data.recipient, amount: 60 },
+        WithdrawalRecipient { recipient: Pubkey::new_unique(), amount: 40 },
+    ]);
+    let hash_with_recipients = calculate_ext_data_hash(&ext_data, encrypted_output1, encrypted_output2).unwrap();
+    assert_ne!(hash_without_recipients, hash_with_recipients, "Recipients must be bound into the hash");
+
+    // Moving value between recipients must change the hash
+    let recipients = ext_data.recipients.as_mut().unwrap();
+    recipients[0].amount = 50;
+    recipients[1].amount = 50;
+    let hash_with_other_split = calculate_ext_data_hash(&ext_data, encrypted_output1, encrypted_output2).unwrap();
+    assert_ne!(hash_with_recipients, hash_with_other_split, "Recipient amounts must be bound into the hash");
+}
+
+#[test]
+fn test_validate_withdrawal_recipients() {
+    let recipient = Pubkey::new_unique();
+    let split = |amounts: &[u64]| -> Vec<WithdrawalRecipient> {
+        amounts.iter().enumerate().map(|(i, &amount)| WithdrawalRecipient {
+            recipient: if i == 0 { recipient } else { Pubkey::new_unique() },
+            amount,
+        }).collect()
+    };
+
+    // Valid splits
+    assert!(validate_withdrawal_recipients(&split(&[100]), -100, recipient).is_ok());
+    assert!(validate_withdrawal_recipients(&split(&[25, 25, 25, 25]), -100, recipient).is_ok());
+
+    // Amounts must sum exactly to the withdrawn amount
+    assert!(validate_withdrawal_recipients(&split(&[50, 49]), -100, recipient).is_err());
+    assert!(validate_withdrawal_recipients(&split(&[50, 51]), -100, recipient).is_err());
+
+    // No zero payouts, no empty list and at most 4 recipients
+    assert!(validate_withdrawal_recipients(&split(&[100, 0]), -100, recipient).is_err());
+    assert!(validate_withdrawal_recipients(&split(&[]), -100, recipient).is_err());
+    assert!(validate_withdrawal_recipients(&split(&[20, 20, 20, 20, 20]), -100, recipient).is_err());
+
+    // Only withdrawals can have multiple recipients
+    assert!(validate_withdrawal_recipients(&split(&[100]), 100, recipient).is_err());
+    assert!(validate_withdrawal_recipients(&split(&[100]), 0, recipient).is_err());
+
+    // The first recipient must be ExtData.recipient
+    assert!(validate_withdrawal_recipients(&split(&[100]), -100, Pubkey::new_unique()).is_err());
+
+    // Overflowing sums are rejected rather than wrapping
+    assert!(validate_withdrawal_recipients(&split(&[u64::MAX, 2]), -1, recipient).is_err());
+}
+
+#[test]
+fn test_validate_encrypted_output_size_bounds() {
+    // Bounds are inclusive
+    assert!(validate_encrypted_output(&[0u8; 10], 10, 20, false).is_ok());
+    assert!(validate_encrypted_output(&[0u8; 20], 10, 20, false).is_ok());
+    assert!(validate_encrypted_output(&[0u8; 9], 10, 20, false).is_err());
+    assert!(validate_encrypted_output(&[0u8; 21], 10, 20, false).is_err());
+}
+
+#[test]
+fn test_validate_encrypted_output_envelope() {
+    let envelope = Envelope::new(Scheme::Aes128CtrHmacSha256, [0u8; 32], b"ciphertext").unwrap().encode();
+    assert!(validate_encrypted_output(&envelope, 0, 1024, true).is_ok());
+
+    // Garbage passes when envelopes are not required, but not when they are
+    let garbage = [0xffu8; 64];
+    assert!(validate_encrypted_output(&garbage, 0, 1024, false).is_ok());
+    assert!(validate_encrypted_output(&garbage, 0, 1024, true).is_err());
+
+    // A valid envelope still has to respect the size bounds
+    assert!(validate_encrypted_output(&envelope, 0, (envelope.len() - 1) as u16, true).is_err());
+}
+
+#[test]
+fn test_is_commitment_reclaimable() {
+    // Disabled when both retention criteria are 0
+    assert!(!is_commitment_reclaimable(0, 0, u64::MAX, u64::MAX, 0, 0));
+
+    // Leaf-based retention: leaf 10 with 100 leaves appended after it
+    assert!(is_commitment_reclaimable(10, 0, 111, 0, 100, 0));
+    assert!(!is_commitment_reclaimable(10, 0, 110, 0, 100, 0));
+
+    // Slot-based retention
+    assert!(is_commitment_reclaimable(10, 1_000, 11, 1_500, 0, 500));
+    assert!(!is_commitment_reclaimable(10, 1_000, 11, 1_499, 0, 500));
+
+    // Either criterion is enough when both are set
+    assert!(is_commitment_reclaimable(10, 1_000, 111, 1_000, 100, 500));
+    assert!(is_commitment_reclaimable(10, 1_000, 11, 1_500, 100, 500));
+    assert!(!is_commitment_reclaimable(10, 1_000, 11, 1_000, 100, 500));
+
+    // Inconsistent inputs saturate instead of underflowing
+    assert!(!is_commitment_reclaimable(10, 2_000, 5, 1_000, 1, 1));
+}
+
+#[test]
+fn test_is_delayed_withdrawal() {
+    // Disabled when the threshold is 0
+    assert!(!is_delayed_withdrawal(i64::MIN, 0));
+
+    // Only withdrawals strictly above the threshold are delayed
+    assert!(is_delayed_withdrawal(-1_001, 1_000));
+    assert!(!is_delayed_withdrawal(-1_000, 1_000));
+    assert!(!is_delayed_withdrawal(1_001, 1_000));
+    assert!(!is_delayed_withdrawal(0, 1_000));
+    assert!(is_delayed_withdrawal(i64::MIN, u64::MAX >> 1));
+}
+
+#[test]
+fn test_is_withdrawal_releasable() {
+    assert!(is_withdrawal_releasable(100, 100, false));
+    assert!(is_withdrawal_releasable(100, 150, false));
+    assert!(!is_withdrawal_releasable(100, 99, false));
+
+    // Frozen during an emergency, even once the delay has elapsed
+    assert!(!is_withdrawal_releasable(100, 150, true));
+}
+
+#[test]
+fn test_validate_deposit_amount_minimum() {
+    let no_denominations = [0u64; 8];
+
+    assert!(validate_deposit_amount(1_000, 1_000, &no_denominations).is_ok());
+    assert!(validate_deposit_amount(5_000, 1_000, &no_denominations).is_ok());
+    assert!(validate_deposit_amount(1, 0, &no_denominations).is_ok(), "0 means no minimum");
+
+    let err = validate_deposit_amount(999, 1_000, &no_denominations).unwrap_err();
+    assert!(err.to_string().contains("DepositBelowMinimum"));
+
+    // Withdrawals are not subject to deposit rules
+    assert!(validate_deposit_amount(-1, 1_000, &no_denominations).is_ok());
+}
+
+#[test]
+fn test_validate_deposit_amount_denominations() {
+    let mut denominations = [0u64; 8];
+    denominations[0] = 100_000_000;
+    denominations[3] = 1_000_000_000;
+
+    assert!(validate_deposit_amount(100_000_000, 0, &denominations).is_ok());
+    assert!(validate_deposit_amount(1_000_000_000, 0, &denominations).is_ok());
+
+    let err = validate_deposit_amount(500_000_000, 0, &denominations).unwrap_err();
+    assert!(err.to_string().contains("DepositDenominationNotAllowed"));
+
+    // The minimum is checked first and reported distinctly
+    let err = validate_deposit_amount(100_000_000, 200_000_000, &denominations).unwrap_err();
+    assert!(err.to_string().contains("DepositBelowMinimum"));
+
+    assert!(validate_deposit_amount(-500_000_000, 0, &denominations).is_ok());
+}
+
+#[test]
+fn test_pack_deposit_denominations() {
+    let packed = pack_deposit_denominations(10, &[10, 100, 1_000]).unwrap();
+    assert_eq!(packed, [10, 100, 1_000, 0, 0, 0, 0, 0]);
+    assert_eq!(pack_deposit_denominations(0, &[]).unwrap(), [0u64; 8]);
+    assert!(pack_deposit_denominations(0, &[1, 2, 3, 4, 5, 6, 7, 8]).is_ok());
+
+    // Too many, zero, duplicated or below the minimum
+    assert!(pack_deposit_denominations(0, &[1, 2, 3, 4, 5, 6, 7, 8, 9]).is_err());
+    assert!(pack_deposit_denominations(0, &[0, 100]).is_err());
+    assert!(pack_deposit_denominations(0, &[100, 100]).is_err());
+    assert!(pack_deposit_denominations(50, &[10, 100]).is_err());
+}
+
+#[test]
+fn test_minimum_fee_matches_validate_fee() {
+    // 1 SOL withdrawal at 25 bps with a 5% margin
+    let min = minimum_fee(-1_000_000_000, 0, 25, 500, 0).unwrap();
+    assert_eq!(min, 2_375_000);
+    assert!(validate_fee(-1_000_000_000, min, 0, 25, 500, 0).is_ok());
+    assert!(validate_fee(-1_000_000_000, min - 1, 0, 25, 500, 0).is_err());
+
+    assert_eq!(minimum_fee(1_000_000_000, 0, 25, 500, 0).unwrap(), 0);
+    assert_eq!(minimum_fee(0, 10000, 10000, 0, 0).unwrap(), 0);
+}
+
+#[test]
+fn test_validate_fee_transfer_fee() {
+    // Internal transfers pay the flat fee, rates and margin only apply to deposits and withdrawals
+    assert_eq!(minimum_fee(0, 10000, 10000, 500, 5_000).unwrap(), 5_000);
+    assert!(validate_fee(0, 5_000, 0, 25, 500, 5_000).is_ok());
+    let err = validate_fee(0, 4_999, 0, 25, 500, 5_000).unwrap_err();
+    assert!(err.to_string().contains("InvalidFeeAmount"));
+    assert_eq!(minimum_fee(-1_000_000_000, 0, 25, 500, 5_000).unwrap(), 2_375_000);
+
+    let rent_lamports = transact_rent(&Rent::default(), NullifierMode::Pda, 100, 100, TransactPdas::default()).unwrap();
+    assert!(validate_fee_with_rent(0, 5_000 + rent_lamports, 0, 25, 500, 5_000, rent_lamports).is_ok());
+    assert!(validate_fee_with_rent(0, 5_000 + rent_lamports - 1, 0, 25, 500, 5_000, rent_lamports).is_err());
+}
+
+#[test]
+fn test_check_public_amount_internal_transfer() {
+    // The fee of an internal transfer comes out of the shielded amounts, as for a withdrawal
+    assert!(check_public_amount(0, 0, u64_to_bytes(0)));
+    assert!(check_public_amount(0, 5_000, fr_to_bytes(-Fr::from(5_000u64))));
+    assert!(!check_public_amount(0, 5_000, u64_to_bytes(0)));
+    assert!(!check_public_amount(0, 5_000, u64_to_bytes(5_000)));
+}
+
+#[test]
+fn test_record_transfer() {
+    let mut tree_account: MerkleTreeAccount = bytemuck::Zeroable::zeroed();
+
+    // No cap
+    for _ in 0..10 {
+        record_transfer(&mut tree_account, 7, 0).unwrap();
+    }
+    assert_eq!((tree_account.transfer_slot, tree_account.transfers_in_slot), (7, 10));
+
+    // The count restarts on a new slot
+    record_transfer(&mut tree_account, 8, 2).unwrap();
+    record_transfer(&mut tree_account, 8, 2).unwrap();
+    let err = record_transfer(&mut tree_account, 8, 2).unwrap_err();
+    assert!(err.to_string().contains("TransferLimitExceeded"));
+    assert_eq!(tree_account.transfers_in_slot, 2);
+
+    record_transfer(&mut tree_account, 9, 2).unwrap();
+    assert_eq!((tree_account.transfer_slot, tree_account.transfers_in_slot), (9, 1));
+}
+
+#[test]
+fn test_transact_rent() {
+    let rent = Rent::default();
+    assert_eq!(nullifier_account_space(), 8 + std::mem::size_of::<zkcash::NullifierAccount>());
+    assert_eq!(commitment_account_space(100), 8 + std::mem::size_of::<zkcash::CommitmentAccount>() + 100);
+
+    let commitments = rent.minimum_balance(commitment_account_space(100))
+        + rent.minimum_balance(commitment_account_space(200));
+    let expected = 2 * rent.minimum_balance(nullifier_account_space()) + commitments;
+    let none = TransactPdas::default();
+    assert_eq!(transact_rent(&rent, NullifierMode::Pda, 100, 200, none).unwrap(), expected);
+    assert_eq!(transact_rent(&rent, NullifierMode::Dual, 100, 200, none).unwrap(), expected);
+    // No nullifier PDAs are created in Compressed mode
+    assert_eq!(transact_rent(&rent, NullifierMode::Compressed, 100, 200, none).unwrap(), commitments);
+
+    // Longer encrypted outputs cost more rent
+    assert!(transact_rent(&rent, NullifierMode::Pda, 100, 300, none).unwrap() > expected);
+}
+
+#[test]
+fn test_transact_rent_pending_withdrawal() {
+    let rent = Rent::default();
+    let base = transact_rent(&rent, NullifierMode::Pda, 100, 100, TransactPdas::default()).unwrap();
+    let pdas = TransactPdas { pending_withdrawal: true, ..TransactPdas::default() };
+
+    assert_eq!(
+        transact_rent(&rent, NullifierMode::Pda, 100, 100, pdas).unwrap(),
+        base + rent.minimum_balance(PendingWithdrawal::SPACE)
+    );
+}
+
+#[test]
+fn test_transact_rent_referrer_stats() {
+    let rent = Rent::default();
+    let base = transact_rent(&rent, NullifierMode::Compressed, 100, 100, TransactPdas::default()).unwrap();
+    let pdas = TransactPdas { referrer_stats: true, ..TransactPdas::default() };
+    assert_eq!(
+        transact_rent(&rent, NullifierMode::Compressed, 100, 100, pdas).unwrap(),
+        base + rent.minimum_balance(ReferrerStats::SPACE)
+    );
+
+    let pdas = TransactPdas { pending_withdrawal: true, referrer_stats: true };
+    assert_eq!(
+        transact_rent(&rent, NullifierMode::Compressed, 100, 100, pdas).unwrap(),
+        base + rent.minimum_balance(ReferrerStats::SPACE) + rent.minimum_balance(PendingWithdrawal::SPACE)
+    );
+}
+
+#[test]
+fn test_validate_fee_with_rent() {
+    let rent_lamports = transact_rent(&Rent::default(), NullifierMode::Pda, 100, 100, TransactPdas::default()).unwrap();
+    let min = minimum_fee(-1_000_000_000, 0, 25, 500, 0).unwrap();
+
+    assert!(validate_fee_with_rent(-1_000_000_000, min + rent_lamports, 0, 25, 500, 0, rent_lamports).is_ok());
+
+    let err = validate_fee_with_rent(-1_000_000_000, min + rent_lamports - 1, 0, 25, 500, 0, rent_lamports).unwrap_err();
+    assert!(err.to_string().contains("FeeBelowRent"));
+
+    // Missing the configured rate is still reported as such
+    let err = validate_fee_with_rent(-1_000_000_000, min - 1, 0, 25, 500, 0, rent_lamports).unwrap_err();
+    assert!(err.to_string().contains("InvalidFeeAmount"));
+}
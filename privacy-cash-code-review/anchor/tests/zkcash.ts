@@ -75,6 +75,16 @@ function findCommitmentPDAs(program: anchor.Program<any>, proof: any) {
   return { commitment0PDA, commitment1PDA };
 }
 
+// Find the blocklist entry PDA of an address, screened as the depositor of deposits
+function findBlocklistEntryPDA(program: anchor.Program<any>, address: PublicKey) {
+  const [blocklistEntryPDA] = PublicKey.findProgramAddressSync(
+    [Buffer.from("blocklist"), address.toBuffer()],
+    program.programId
+  );
+
+  return blocklistEntryPDA;
+}
+
 describe("zkcash", () => {
   // Configure the client to use the local cluster.
   const provider = anchor.AnchorProvider.env();
@@ -166,11 +176,12 @@ describe("zkcash", () => {
     globalConfigPDA = globalConfigPda;
         
     await program.methods
-      .initialize()
+      .initialize({ pda: {} })
       .accounts({
         treeAccount: treeAccountPDA,
         treeTokenAccount: treeTokenAccountPDA,
         globalConfig: globalConfigPDA,
+        nullifierTree: null,
         authority: authority.publicKey,
         systemProgram: anchor.web3.SystemProgram.programId
       })
@@ -414,6 +425,7 @@ describe("zkcash", () => {
         treeTokenAccount: treeTokenAccountPDA,
         globalConfig: globalConfigPDA,
         signer: randomUser.publicKey, // Use random user as signer
+        depositorBlocklistEntry: findBlocklistEntryPDA(program, randomUser.publicKey),
         systemProgram: anchor.web3.SystemProgram.programId
       })
       .signers([randomUser]) // Random user signs the transaction
@@ -613,6 +625,7 @@ describe("zkcash", () => {
         treeTokenAccount: treeTokenAccountPDA,
         globalConfig: globalConfigPDA,
         signer: randomUser.publicKey,
+        depositorBlocklistEntry: findBlocklistEntryPDA(program, randomUser.publicKey),
         systemProgram: anchor.web3.SystemProgram.programId
       })
       .signers([randomUser])
@@ -883,6 +896,7 @@ describe("zkcash", () => {
         treeTokenAccount: treeTokenAccountPDA,
         globalConfig: globalConfigPDA,
         signer: randomUser.publicKey, // Use random user as signer
+        depositorBlocklistEntry: findBlocklistEntryPDA(program, randomUser.publicKey),
         systemProgram: anchor.web3.SystemProgram.programId
       })
       .signers([randomUser]) // Random user signs the transaction
@@ -1085,6 +1099,7 @@ describe("zkcash", () => {
         treeTokenAccount: treeTokenAccountPDA,
         globalConfig: globalConfigPDA,
         signer: randomUser.publicKey,
+        depositorBlocklistEntry: findBlocklistEntryPDA(program, randomUser.publicKey),
         systemProgram: anchor.web3.SystemProgram.programId
       })
       .signers([randomUser])
@@ -1358,6 +1373,7 @@ describe("zkcash", () => {
         treeTokenAccount: treeTokenAccountPDA,
         globalConfig: globalConfigPDA,
         signer: randomUser.publicKey, // Use random user as signer
+        depositorBlocklistEntry: findBlocklistEntryPDA(program, randomUser.publicKey),
         systemProgram: anchor.web3.SystemProgram.programId
       })
       .signers([randomUser]) // Random user signs the transaction
@@ -1560,6 +1576,7 @@ describe("zkcash", () => {
         treeTokenAccount: treeTokenAccountPDA,
         globalConfig: globalConfigPDA,
         signer: randomUser.publicKey,
+        depositorBlocklistEntry: findBlocklistEntryPDA(program, randomUser.publicKey),
         systemProgram: anchor.web3.SystemProgram.programId
       })
       .signers([randomUser])
@@ -1812,6 +1829,7 @@ describe("zkcash", () => {
         treeTokenAccount: treeTokenAccountPDA,
         globalConfig: globalConfigPDA,
         signer: randomUser.publicKey, // Use random user as signer
+        depositorBlocklistEntry: findBlocklistEntryPDA(program, randomUser.publicKey),
         systemProgram: anchor.web3.SystemProgram.programId
       })
       .signers([randomUser]) // Random user signs the transaction
@@ -2010,6 +2028,7 @@ describe("zkcash", () => {
         treeTokenAccount: treeTokenAccountPDA,
         globalConfig: globalConfigPDA,
         signer: randomUser.publicKey,
+        depositorBlocklistEntry: findBlocklistEntryPDA(program, randomUser.publicKey),
         systemProgram: anchor.web3.SystemProgram.programId
       })
       .signers([randomUser])
@@ -2256,6 +2275,7 @@ describe("zkcash", () => {
         treeTokenAccount: treeTokenAccountPDA,
         globalConfig: globalConfigPDA,
         signer: randomUser.publicKey, // Use random user as signer
+        depositorBlocklistEntry: findBlocklistEntryPDA(program, randomUser.publicKey),
         systemProgram: anchor.web3.SystemProgram.programId
       })
       .signers([randomUser]) // Random user signs the transaction
@@ -2456,6 +2476,7 @@ describe("zkcash", () => {
           treeTokenAccount: treeTokenAccountPDA,
           globalConfig: globalConfigPDA,
           signer: randomUser.publicKey,
+          depositorBlocklistEntry: findBlocklistEntryPDA(program, randomUser.publicKey),
           systemProgram: anchor.web3.SystemProgram.programId
         })
         .signers([randomUser])
@@ -2652,6 +2673,7 @@ describe("zkcash", () => {
         treeTokenAccount: treeTokenAccountPDA,
         globalConfig: globalConfigPDA,
         signer: randomUser.publicKey, // Use random user as signer
+        depositorBlocklistEntry: findBlocklistEntryPDA(program, randomUser.publicKey),
         systemProgram: anchor.web3.SystemProgram.programId
       })
       .signers([randomUser]) // Random user signs the transaction
@@ -2850,6 +2872,7 @@ describe("zkcash", () => {
         treeTokenAccount: treeTokenAccountPDA,
         globalConfig: globalConfigPDA,
         signer: randomUser.publicKey,
+        depositorBlocklistEntry: findBlocklistEntryPDA(program, randomUser.publicKey),
         systemProgram: anchor.web3.SystemProgram.programId
       })
       .signers([randomUser])
@@ -3097,6 +3120,7 @@ describe("zkcash", () => {
         treeTokenAccount: treeTokenAccountPDA,
         globalConfig: globalConfigPDA,
         signer: randomUser.publicKey, // Use random user as signer
+        depositorBlocklistEntry: findBlocklistEntryPDA(program, randomUser.publicKey),
         systemProgram: anchor.web3.SystemProgram.programId
       })
       .signers([randomUser]) // Random user signs the transaction
@@ -3295,6 +3319,7 @@ describe("zkcash", () => {
         treeTokenAccount: treeTokenAccountPDA,
         globalConfig: globalConfigPDA,
         signer: randomUser.publicKey,
+        depositorBlocklistEntry: findBlocklistEntryPDA(program, randomUser.publicKey),
         systemProgram: anchor.web3.SystemProgram.programId
       })
       .signers([randomUser])
@@ -3542,6 +3567,7 @@ describe("zkcash", () => {
         treeTokenAccount: treeTokenAccountPDA,
         globalConfig: globalConfigPDA,
         signer: randomUser.publicKey, // Use random user as signer
+        depositorBlocklistEntry: findBlocklistEntryPDA(program, randomUser.publicKey),
         systemProgram: anchor.web3.SystemProgram.programId
       })
       .signers([randomUser]) // Random user signs the transaction
@@ -3740,6 +3766,7 @@ describe("zkcash", () => {
         treeTokenAccount: treeTokenAccountPDA,
         globalConfig: globalConfigPDA,
         signer: randomUser.publicKey,
+        depositorBlocklistEntry: findBlocklistEntryPDA(program, randomUser.publicKey),
         systemProgram: anchor.web3.SystemProgram.programId
       })
       .signers([randomUser])
@@ -3989,6 +4016,7 @@ describe("zkcash", () => {
         treeTokenAccount: treeTokenAccountPDA,
         globalConfig: globalConfigPDA,
         signer: randomUser.publicKey, // Use random user as signer
+        depositorBlocklistEntry: findBlocklistEntryPDA(program, randomUser.publicKey),
         systemProgram: anchor.web3.SystemProgram.programId
       })
       .signers([randomUser]) // Random user signs the transaction
@@ -4187,6 +4215,7 @@ describe("zkcash", () => {
         treeTokenAccount: treeTokenAccountPDA,
         globalConfig: globalConfigPDA,
         signer: randomUser.publicKey,
+        depositorBlocklistEntry: findBlocklistEntryPDA(program, randomUser.publicKey),
         systemProgram: anchor.web3.SystemProgram.programId
       })
       .signers([randomUser])
@@ -4345,6 +4374,7 @@ describe("zkcash", () => {
           treeTokenAccount: treeTokenAccountPDA,
           globalConfig: globalConfigPDA,
           signer: randomUser.publicKey, // Use random user as signer
+          depositorBlocklistEntry: findBlocklistEntryPDA(program, randomUser.publicKey),
           systemProgram: anchor.web3.SystemProgram.programId
         })
         .signers([randomUser]) // Random user signs the transaction
@@ -4442,6 +4472,7 @@ describe("zkcash", () => {
           treeTokenAccount: treeTokenAccountPDA,
           globalConfig: globalConfigPDA,
           signer: randomUser.publicKey, // Use random user as signer
+          depositorBlocklistEntry: findBlocklistEntryPDA(program, randomUser.publicKey),
           systemProgram: anchor.web3.SystemProgram.programId
         })
         .signers([randomUser]) // Random user signs the transaction
@@ -4543,6 +4574,7 @@ describe("zkcash", () => {
           treeTokenAccount: treeTokenAccountPDA,
           globalConfig: globalConfigPDA,
           signer: randomUser.publicKey, // Use random user as signer
+          depositorBlocklistEntry: findBlocklistEntryPDA(program, randomUser.publicKey),
           systemProgram: anchor.web3.SystemProgram.programId
         })
         .signers([randomUser]) // Random user signs the transaction
@@ -4642,6 +4674,7 @@ describe("zkcash", () => {
           treeTokenAccount: treeTokenAccountPDA,
           globalConfig: globalConfigPDA,
           signer: randomUser.publicKey,
+          depositorBlocklistEntry: findBlocklistEntryPDA(program, randomUser.publicKey),
           systemProgram: anchor.web3.SystemProgram.programId
         })
         .signers([randomUser])
@@ -4742,6 +4775,7 @@ describe("zkcash", () => {
           treeTokenAccount: treeTokenAccountPDA,
           globalConfig: globalConfigPDA,
           signer: randomUser.publicKey,
+          depositorBlocklistEntry: findBlocklistEntryPDA(program, randomUser.publicKey),
           systemProgram: anchor.web3.SystemProgram.programId
         })
         .signers([randomUser])
@@ -5053,6 +5087,7 @@ describe("zkcash", () => {
         treeTokenAccount: treeTokenAccountPDA,
         globalConfig: globalConfigPDA,
         signer: randomUser.publicKey,
+        depositorBlocklistEntry: findBlocklistEntryPDA(program, randomUser.publicKey),
         systemProgram: anchor.web3.SystemProgram.programId
       })
       .signers([randomUser])
@@ -5206,6 +5241,7 @@ describe("zkcash", () => {
         treeTokenAccount: treeTokenAccountPDA,
         globalConfig: globalConfigPDA,
         signer: randomUser.publicKey,
+        depositorBlocklistEntry: findBlocklistEntryPDA(program, randomUser.publicKey),
         systemProgram: anchor.web3.SystemProgram.programId
       })
       .signers([randomUser])
@@ -5350,6 +5386,7 @@ describe("zkcash", () => {
         treeTokenAccount: treeTokenAccountPDA,
         globalConfig: globalConfigPDA,
         signer: randomUser.publicKey,
+        depositorBlocklistEntry: findBlocklistEntryPDA(program, randomUser.publicKey),
         systemProgram: anchor.web3.SystemProgram.programId
       })
       .signers([randomUser])
@@ -5509,6 +5546,7 @@ describe("zkcash", () => {
         treeTokenAccount: treeTokenAccountPDA,
         globalConfig: globalConfigPDA,
         signer: randomUser.publicKey,
+        depositorBlocklistEntry: findBlocklistEntryPDA(program, randomUser.publicKey),
         systemProgram: anchor.web3.SystemProgram.programId
       })
       .signers([randomUser])
@@ -5680,6 +5718,7 @@ describe("zkcash", () => {
           treeTokenAccount: treeTokenAccountPDA,
         globalConfig: globalConfigPDA,
           signer: randomUser.publicKey,
+          depositorBlocklistEntry: findBlocklistEntryPDA(program, randomUser.publicKey),
           systemProgram: anchor.web3.SystemProgram.programId
         })
         .signers([randomUser])
@@ -5774,6 +5813,7 @@ describe("zkcash", () => {
           treeTokenAccount: treeTokenAccountPDA,
         globalConfig: globalConfigPDA,
           signer: randomUser.publicKey,
+          depositorBlocklistEntry: findBlocklistEntryPDA(program, randomUser.publicKey),
           systemProgram: anchor.web3.SystemProgram.programId
         })
         .signers([randomUser])
@@ -6267,6 +6307,7 @@ describe("zkcash", () => {
         treeTokenAccount: treeTokenAccountPDA,
         globalConfig: globalConfigPDA,
         signer: randomUser.publicKey,
+        depositorBlocklistEntry: findBlocklistEntryPDA(program, randomUser.publicKey),
         systemProgram: anchor.web3.SystemProgram.programId
       })
       .signers([randomUser])
@@ -6290,4 +6331,89 @@ describe("zkcash", () => {
 
     expect(txSig).to.be.a('string');
   });
+
+  it("Fails deposit from a blocklisted depositor", async () => {
+    const blocklistEntryPDA = findBlocklistEntryPDA(program, randomUser.publicKey);
+    await program.methods
+      .addToBlocklist(randomUser.publicKey)
+      .accounts({
+        blocklistEntry: blocklistEntryPDA,
+        globalConfig: globalConfigPDA,
+        authority: authority.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId
+      })
+      .signers([authority])
+      .rpc();
+
+    const extData = {
+      recipient: recipient.publicKey,
+      extAmount: new anchor.BN(200), // Positive ext amount (deposit)
+      encryptedOutput1: Buffer.from("encryptedOutput1Data"),
+      encryptedOutput2: Buffer.from("encryptedOutput2Data"),
+      fee: new anchor.BN(0),
+      feeRecipient: FEE_RECIPIENT_ACCOUNT,
+      mintAddress: new anchor.web3.PublicKey("11111111111111111111111111111112"), // SOL mint address
+    };
+
+    // The depositor is screened before the proof is verified, so a dummy proof is enough
+    const proof = {
+      proofA: Array(64).fill(1),
+      proofB: Array(128).fill(2),
+      proofC: Array(64).fill(3),
+      root: ZERO_BYTES[DEFAULT_HEIGHT],
+      inputNullifiers: [
+        Array.from(generateRandomNullifier()),
+        Array.from(generateRandomNullifier())
+      ],
+      outputCommitments: [
+        Array(32).fill(3),
+        Array(32).fill(4)
+      ],
+      publicAmount: bnToBytes(new anchor.BN(200)),
+      extDataHash: Array.from(getExtDataHash(extData))
+    };
+
+    const { nullifier0PDA, nullifier1PDA } = findNullifierPDAs(program, proof);
+    const { commitment0PDA, commitment1PDA } = findCommitmentPDAs(program, proof);
+
+    try {
+      const modifyComputeUnits = anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ 
+        units: 1_000_000 
+      });
+
+      await program.methods
+        .transact(proof, { recipient: extData.recipient, extAmount: extData.extAmount, fee: extData.fee, feeRecipient: extData.feeRecipient, mintAddress: extData.mintAddress }, extData.encryptedOutput1, extData.encryptedOutput2)
+        .accounts({
+          treeAccount: treeAccountPDA,
+          nullifier0: nullifier0PDA,
+          nullifier1: nullifier1PDA,
+          commitment0: commitment0PDA,
+          commitment1: commitment1PDA,
+          recipient: recipient.publicKey,
+          feeRecipientAccount: FEE_RECIPIENT_ACCOUNT,
+          treeTokenAccount: treeTokenAccountPDA,
+          globalConfig: globalConfigPDA,
+          signer: randomUser.publicKey,
+          depositorBlocklistEntry: blocklistEntryPDA,
+          systemProgram: anchor.web3.SystemProgram.programId
+        })
+        .signers([randomUser])
+        .preInstructions([modifyComputeUnits])
+        .rpc();
+
+      expect.fail("Transaction should have failed because the depositor is blocklisted but succeeded");
+    } catch (error) {
+      expect(error.toString()).to.include("AddressBlocked");
+    } finally {
+      await program.methods
+        .removeFromBlocklist(randomUser.publicKey)
+        .accounts({
+          blocklistEntry: blocklistEntryPDA,
+          globalConfig: globalConfigPDA,
+          authority: authority.publicKey
+        })
+        .signers([authority])
+        .rpc();
+    }
+  });
 });
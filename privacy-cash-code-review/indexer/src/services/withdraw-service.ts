@@ -148,6 +148,15 @@ function createWithdrawInstruction(
   const globalConfigAccount = new PublicKey(params.globalConfigAccount);
   const recipient = new PublicKey(params.recipient);
   const feeRecipientAccount = new PublicKey(params.feeRecipientAccount);
+  // Only deposits screen the signer, but transact always takes both blocklist entries
+  const [signerBlocklistEntry] = PublicKey.findProgramAddressSync(
+    [Buffer.from('blocklist'), loadRelayerKeypair().publicKey.toBuffer()],
+    PROGRAM_ID
+  );
+  const [recipientBlocklistEntry] = PublicKey.findProgramAddressSync(
+    [Buffer.from('blocklist'), recipient.toBuffer()],
+    PROGRAM_ID
+  );
 
   // The serializedProofData contains the full instruction data in the new format:
   // discriminator + proof + extData (without encrypted outputs) + encrypted_output1 + encrypted_output2
@@ -179,6 +188,20 @@ function createWithdrawInstruction(
       { pubkey: feeRecipientAccount, isSigner: false, isWritable: true },
       // signer (relayer instead of user)
       { pubkey: loadRelayerKeypair().publicKey, isSigner: true, isWritable: true },
+      // depositor: none for withdrawals
+      { pubkey: PROGRAM_ID, isSigner: false, isWritable: false },
+      // blocklist entries of the signer and the recipient
+      { pubkey: signerBlocklistEntry, isSigner: false, isWritable: false },
+      { pubkey: recipientBlocklistEntry, isSigner: false, isWritable: false },
+      // optional accounts, none for a plain withdrawal below the delay threshold from a Pda mode
+      // pool: nullifier tree, nullifier proofs, viewing key registry, pending withdrawal,
+      // referrer stats, swap authority
+      { pubkey: PROGRAM_ID, isSigner: false, isWritable: false },
+      { pubkey: PROGRAM_ID, isSigner: false, isWritable: false },
+      { pubkey: PROGRAM_ID, isSigner: false, isWritable: false },
+      { pubkey: PROGRAM_ID, isSigner: false, isWritable: false },
+      { pubkey: PROGRAM_ID, isSigner: false, isWritable: false },
+      { pubkey: PROGRAM_ID, isSigner: false, isWritable: false },
       { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
     ],
     programId: PROGRAM_ID,
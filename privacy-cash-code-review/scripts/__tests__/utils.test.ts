@@ -0,0 +1,74 @@
+import { PublicKey } from '@solana/web3.js';
+import { getExtDataHash, serializeExtDataExtensions } from '../utils/utils';
+import 'jest';
+
+// Same vectors as `ext_data_hash_vectors` in anchor/crates/zkcash-client/tests/cross_check.rs
+const extData = {
+  recipient: new PublicKey(new Uint8Array(32).fill(1)),
+  extAmount: -1_000_000,
+  encryptedOutput1: Buffer.from('output1'),
+  encryptedOutput2: Buffer.from('output2'),
+  fee: 2_500,
+  feeRecipient: new PublicKey(new Uint8Array(32).fill(2)),
+  mintAddress: new PublicKey(new Uint8Array(32).fill(3)),
+};
+
+const callback = {
+  programId: new PublicKey(new Uint8Array(32).fill(4)),
+  accounts: [new PublicKey(new Uint8Array(32).fill(5)), new PublicKey(new Uint8Array(32).fill(6))],
+  data: [7, 8, 9],
+};
+
+describe('getExtDataHash', () => {
+  it('matches the program without extensions', () => {
+    expect(Buffer.from(getExtDataHash(extData)).toString('hex')).toBe(
+      '04d4f0746d7fd0c115455aebc70dd19c1a7d0c098f87d60ceb5bb9e5d6c5e8d5'
+    );
+    expect(getExtDataHash({ ...extData, callback: null })).toEqual(getExtDataHash(extData));
+  });
+
+  it('matches the program with a callback', () => {
+    expect(Buffer.from(getExtDataHash({ ...extData, callback })).toString('hex')).toBe(
+      '5dc03d82a7148b79aa1e2fd975668644112ad2d45cab94a01549c636b39c3e78'
+    );
+  });
+
+  it('matches the program with every extension', () => {
+    const extensions = {
+      callback,
+      recipients: [
+        { recipient: new PublicKey(new Uint8Array(32).fill(10)), amount: 400_000 },
+        { recipient: new PublicKey(new Uint8Array(32).fill(11)), amount: 600_000 },
+      ],
+      auditor: {
+        viewingKey: new Uint8Array(32).fill(12),
+        encryptedOutputs: [[13, 14], [15]] as [number[], number[]],
+      },
+      memo: [16, 17],
+      referrer: new Uint8Array(32).fill(18),
+      swap: {
+        outputMint: new PublicKey(new Uint8Array(32).fill(19)),
+        recipientTokenAccount: new PublicKey(new Uint8Array(32).fill(20)),
+        minOut: 5,
+        accounts: [new PublicKey(new Uint8Array(32).fill(21))],
+        data: [22],
+      },
+    };
+    expect(Buffer.from(getExtDataHash({ ...extData, ...extensions })).toString('hex')).toBe(
+      '272e22e4286d610bb28f88b93975bf173406cadc100ba9b1470005051889c607'
+    );
+  });
+});
+
+describe('serializeExtDataExtensions', () => {
+  it('writes a None tag per absent extension', () => {
+    expect(serializeExtDataExtensions({})).toEqual(Buffer.from([0, 0, 0, 0, 0, 0]));
+    expect(serializeExtDataExtensions({ callback: null, swap: null })).toEqual(Buffer.from([0, 0, 0, 0, 0, 0]));
+  });
+
+  it('writes Some extensions in declaration order', () => {
+    expect(serializeExtDataExtensions({ memo: [16, 17] })).toEqual(
+      Buffer.from([0, 0, 0, 1, 2, 0, 0, 0, 16, 17, 0, 0])
+    );
+  });
+});
@@ -2,7 +2,7 @@ import { Connection, Keypair, PublicKey, TransactionInstruction, SystemProgram,
 import BN from 'bn.js';
 import { readFileSync } from 'fs';
 import { Utxo } from './models/utxo';
-import { getExtDataHash } from './utils/utils';
+import { getExtDataHash, serializeExtDataExtensions } from './utils/utils';
 import { prove, parseProofToBytesArray, parseToBytesArray } from './utils/prover';
 import * as path from 'path';
 import * as dotenv from 'dotenv';
@@ -101,6 +101,16 @@ function findCommitmentPDAs(proof: any) {
   return { commitment0PDA, commitment1PDA };
 }
 
+// Find the blocklist entry PDA of an address, whether or not it is listed
+function findBlocklistEntryPDA(address: PublicKey) {
+  const [blocklistEntryPDA] = PublicKey.findProgramAddressSync(
+    [Buffer.from("blocklist"), address.toBuffer()],
+    PROGRAM_ID
+  );
+
+  return blocklistEntryPDA;
+}
+
 // Function to serialize proof and extData (same as deposit script format)
 function serializeProofAndExtData(proof: any, extData: any) {
   // Create the ExtData object for the program call (WITHOUT encrypted outputs)
@@ -132,6 +142,8 @@ function serializeProofAndExtData(proof: any, extData: any) {
     Buffer.from(new BN(extDataForProgram.fee).toArray('le', 8)),
     extDataForProgram.feeRecipient.toBuffer(),
     new PublicKey(extDataForProgram.mintAddress).toBuffer(),
+    // Serialize the optional extensions, None unless set on extData
+    serializeExtDataExtensions(extData),
     // Serialize encrypted outputs as separate parameters
     Buffer.from(new BN(extData.encryptedOutput1.length).toArray('le', 4)),
     extData.encryptedOutput1,
@@ -547,6 +559,19 @@ To fix this:
         { pubkey: feeRecipientAccount, isSigner: false, isWritable: true },
         // signer (user, not relayer)
         { pubkey: user.publicKey, isSigner: true, isWritable: true },
+        // depositor: none, the signer funds the deposit
+        { pubkey: PROGRAM_ID, isSigner: false, isWritable: false },
+        // blocklist entries of the depositor and the recipient
+        { pubkey: findBlocklistEntryPDA(user.publicKey), isSigner: false, isWritable: false },
+        { pubkey: findBlocklistEntryPDA(user.publicKey), isSigner: false, isWritable: false },
+        // optional accounts, none for a plain deposit to a Pda mode pool: nullifier tree,
+        // nullifier proofs, viewing key registry, pending withdrawal, referrer stats, swap authority
+        { pubkey: PROGRAM_ID, isSigner: false, isWritable: false },
+        { pubkey: PROGRAM_ID, isSigner: false, isWritable: false },
+        { pubkey: PROGRAM_ID, isSigner: false, isWritable: false },
+        { pubkey: PROGRAM_ID, isSigner: false, isWritable: false },
+        { pubkey: PROGRAM_ID, isSigner: false, isWritable: false },
+        { pubkey: PROGRAM_ID, isSigner: false, isWritable: false },
         { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
       ],
       programId: PROGRAM_ID,
@@ -91,8 +91,8 @@ async function initialize() {
     console.log(`Tree Token Account: ${treeTokenAccount.toString()}`);
     console.log(`Global Config: ${globalConfig.toString()}`);
 
-    // Create instruction data - only discriminator (initialize takes no parameters)
-    const data = INITIALIZE_IX_DISCRIMINATOR;
+    // Create instruction data - discriminator followed by the nullifier mode (0 = Pda)
+    const data = Buffer.concat([INITIALIZE_IX_DISCRIMINATOR, Buffer.from([0])]);
 
     // Create the instruction
     const initializeIx = new TransactionInstruction({
@@ -101,6 +101,8 @@ async function initialize() {
         { pubkey: treeAccount, isSigner: false, isWritable: true },
         { pubkey: treeTokenAccount, isSigner: false, isWritable: true },
         { pubkey: globalConfig, isSigner: false, isWritable: true },
+        // No nullifier tree in Pda mode, the program id stands for the missing optional account
+        { pubkey: PROGRAM_ID, isSigner: false, isWritable: false },
         { pubkey: payer.publicKey, isSigner: true, isWritable: true },
         { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
       ],
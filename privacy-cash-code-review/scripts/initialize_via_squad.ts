@@ -37,10 +37,13 @@ async function generateSquadInitializeInstruction() {
       { pubkey: treeAccount, isSigner: false, isWritable: true },
       { pubkey: treeTokenAccount, isSigner: false, isWritable: true },
       { pubkey: globalConfig, isSigner: false, isWritable: true },
+      // No nullifier tree in Pda mode, the program id stands for the missing optional account
+      { pubkey: PROGRAM_ID, isSigner: false, isWritable: false },
       { pubkey: SQUAD_VAULT_ADDRESS, isSigner: true, isWritable: true },
       { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
     ],
-    data: INITIALIZE_IX_DISCRIMINATOR,
+    // Discriminator followed by the nullifier mode (0 = Pda)
+    data: Buffer.concat([INITIALIZE_IX_DISCRIMINATOR, Buffer.from([0])]),
   });
 
   // Create transaction
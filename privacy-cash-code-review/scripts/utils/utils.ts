@@ -41,7 +41,142 @@ export function mockEncrypt(value: Utxo): string {
 }
 
 /**
- * Calculates the hash of ext data using Borsh serialization
+ * Tags of the optional ExtData extensions, see `EXT_DATA_TAG_*` in the program's utils.rs
+ */
+export const EXT_DATA_TAG_CALLBACK = 1;
+export const EXT_DATA_TAG_RECIPIENTS = 2;
+export const EXT_DATA_TAG_AUDITOR = 3;
+export const EXT_DATA_TAG_MEMO = 4;
+export const EXT_DATA_TAG_REFERRER = 5;
+export const EXT_DATA_TAG_SWAP = 6;
+
+/**
+ * Instruction invoked on the recipient program after a withdrawal settles, see
+ * `WithdrawCallback` in the program
+ */
+export interface WithdrawCallback {
+  programId: string | PublicKey;
+  accounts: (string | PublicKey)[];
+  data: Uint8Array | number[];
+}
+
+/**
+ * One payout of a multi-recipient withdrawal, see `WithdrawalRecipient` in the program
+ */
+export interface WithdrawalRecipient {
+  recipient: string | PublicKey;
+  amount: string | number | BN;
+}
+
+/**
+ * Copies of the two output ciphertexts for an auditor, see `AuditorDisclosure` in the program
+ */
+export interface AuditorDisclosure {
+  viewingKey: Uint8Array | number[];
+  encryptedOutputs: [Uint8Array | number[], Uint8Array | number[]];
+}
+
+/**
+ * Swap of the withdrawn SOL into another mint, see `SwapParams` in the program
+ */
+export interface SwapParams {
+  outputMint: string | PublicKey;
+  recipientTokenAccount: string | PublicKey;
+  minOut: string | number | BN;
+  accounts: (string | PublicKey)[];
+  data: Uint8Array | number[];
+}
+
+/**
+ * The optional fields of ExtData, in the order the program declares them
+ */
+export interface ExtDataExtensions {
+  callback?: WithdrawCallback | null;
+  recipients?: WithdrawalRecipient[] | null;
+  auditor?: AuditorDisclosure | null;
+  memo?: Uint8Array | number[] | null;
+  referrer?: Uint8Array | number[] | null;
+  swap?: SwapParams | null;
+}
+
+function toPublicKey(key: string | PublicKey): PublicKey {
+  return key instanceof PublicKey ? key : new PublicKey(key);
+}
+
+const PUBKEY_SCHEMA = { array: { type: 'u8', len: 32 } };
+const BYTES_SCHEMA = { array: { type: 'u8' } };
+
+/**
+ * Borsh encodings of the extensions that are present, with their tags, in declaration order
+ */
+function encodeExtensions(extData: ExtDataExtensions): [number, Buffer | null][] {
+  const encode = (schema: any, value: any) => Buffer.from(borsh.serialize(schema, value));
+  const { callback, recipients, auditor, memo, referrer, swap } = extData;
+
+  return [
+    [EXT_DATA_TAG_CALLBACK, callback ? encode(
+      { struct: { programId: PUBKEY_SCHEMA, accounts: { array: { type: PUBKEY_SCHEMA } }, data: BYTES_SCHEMA } },
+      {
+        programId: toPublicKey(callback.programId).toBytes(),
+        accounts: callback.accounts.map((account) => toPublicKey(account).toBytes()),
+        data: Uint8Array.from(callback.data),
+      }
+    ) : null],
+    [EXT_DATA_TAG_RECIPIENTS, recipients ? encode(
+      { array: { type: { struct: { recipient: PUBKEY_SCHEMA, amount: 'u64' } } } },
+      recipients.map(({ recipient, amount }) => ({
+        recipient: toPublicKey(recipient).toBytes(),
+        amount: new BN(amount.toString()),
+      }))
+    ) : null],
+    [EXT_DATA_TAG_AUDITOR, auditor ? encode(
+      { struct: { viewingKey: PUBKEY_SCHEMA, encryptedOutputs: { array: { type: BYTES_SCHEMA, len: 2 } } } },
+      {
+        viewingKey: Uint8Array.from(auditor.viewingKey),
+        encryptedOutputs: auditor.encryptedOutputs.map((output) => Uint8Array.from(output)),
+      }
+    ) : null],
+    [EXT_DATA_TAG_MEMO, memo ? encode(BYTES_SCHEMA, Uint8Array.from(memo)) : null],
+    [EXT_DATA_TAG_REFERRER, referrer ? encode(PUBKEY_SCHEMA, Uint8Array.from(referrer)) : null],
+    [EXT_DATA_TAG_SWAP, swap ? encode(
+      {
+        struct: {
+          outputMint: PUBKEY_SCHEMA,
+          recipientTokenAccount: PUBKEY_SCHEMA,
+          minOut: 'u64',
+          accounts: { array: { type: PUBKEY_SCHEMA } },
+          data: BYTES_SCHEMA,
+        }
+      },
+      {
+        outputMint: toPublicKey(swap.outputMint).toBytes(),
+        recipientTokenAccount: toPublicKey(swap.recipientTokenAccount).toBytes(),
+        minOut: new BN(swap.minOut.toString()),
+        accounts: swap.accounts.map((account) => toPublicKey(account).toBytes()),
+        data: Uint8Array.from(swap.data),
+      }
+    ) : null],
+  ];
+}
+
+/**
+ * Serializes the optional fields of ExtData as they follow `mint_address` in `transact`
+ * instruction data: a Borsh Option each, a 0 byte for None, 1 and the value for Some
+ * @param extData The extensions, absent ones are None
+ * @returns The serialized Options
+ */
+export function serializeExtDataExtensions(extData: ExtDataExtensions): Buffer {
+  return Buffer.concat(
+    encodeExtensions(extData).map(([, encoding]) =>
+      encoding ? Buffer.concat([Buffer.from([1]), encoding]) : Buffer.from([0])
+    )
+  );
+}
+
+/**
+ * Calculates the hash of ext data using Borsh serialization.
+ * Extensions that are present are appended as a tag byte followed by their Borsh encoding,
+ * so ext data without extensions hashes as before they existed.
  * @param extData External data object containing recipient, amount, encrypted outputs, fee, fee recipient, and mint address
  * @returns The hash as a Uint8Array (32 bytes)
  */
@@ -53,7 +188,7 @@ export function getExtDataHash(extData: {
   fee: string | number | BN;
   feeRecipient: string | PublicKey;
   mintAddress: string | PublicKey;
-}): Uint8Array {
+} & ExtDataExtensions): Uint8Array {
   // Convert all inputs to their appropriate types
   const recipient = extData.recipient instanceof PublicKey 
     ? extData.recipient 
@@ -103,7 +238,14 @@ export function getExtDataHash(extData: {
   };
   
   // Serialize with Borsh
-  const serializedData = borsh.serialize(schema, value);
+  const parts = [Buffer.from(borsh.serialize(schema, value))];
+
+  for (const [tag, encoding] of encodeExtensions(extData)) {
+    if (encoding) {
+      parts.push(Buffer.from([tag]), encoding);
+    }
+  }
+  const serializedData = Buffer.concat(parts);
   
   // Calculate the SHA-256 hash
   const hashHex = sha256(serializedData);
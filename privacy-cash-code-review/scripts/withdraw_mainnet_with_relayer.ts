@@ -2,7 +2,7 @@ import { Connection, Keypair, PublicKey, Transaction, TransactionInstruction, Sy
 import BN from 'bn.js';
 import { readFileSync } from 'fs';
 import { Utxo } from './models/utxo';
-import { getExtDataHash } from './utils/utils';
+import { getExtDataHash, serializeExtDataExtensions } from './utils/utils';
 import { prove, parseProofToBytesArray, parseToBytesArray } from './utils/prover';
 import * as path from 'path';
 import * as dotenv from 'dotenv';
@@ -133,6 +133,8 @@ function serializeProofAndExtData(proof: any, extData: any) {
     Buffer.from(new BN(extDataForProgram.fee).toArray('le', 8)),
     extDataForProgram.feeRecipient.toBuffer(),
     new PublicKey(extDataForProgram.mintAddress).toBuffer(),
+    // Serialize the optional extensions, None unless set on extData
+    serializeExtDataExtensions(extData),
     // Serialize encrypted outputs as separate parameters
     Buffer.from(new BN(extData.encryptedOutput1.length).toArray('le', 4)),
     extData.encryptedOutput1,